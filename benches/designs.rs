@@ -0,0 +1,40 @@
+//! Compares the runtimes of designs across population sizes.
+//! Run with `cargo bench`.
+
+use envisim_samplr::bench_support::measure_design_time;
+use envisim_samplr::pivotal_method::{rpm, spm};
+use envisim_samplr::unequal::{brewer, pareto, sampford};
+use envisim_samplr::{systematic, SampleOptions, Sampler, SamplingError};
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use std::num::NonZeroU32;
+
+fn main() -> Result<(), SamplingError> {
+    let designs: [(&str, Sampler<SmallRng>); 6] = [
+        ("sampford", sampford),
+        ("pareto", pareto),
+        ("brewer", brewer),
+        ("systematic", systematic::sample),
+        ("spm", spm),
+        ("rpm", rpm),
+    ];
+    let mut rng = SmallRng::seed_from_u64(4242);
+
+    println!("{:<12}{:>12}{:>14}", "design", "population", "time/sample");
+
+    for population_size in [100u32, 1000, 10000] {
+        // Sample size 10, with probabilities varying by a factor 3
+        let sizes: Vec<f64> = (0..population_size).map(|i| f64::from(1 + i % 3)).collect();
+        let total: f64 = sizes.iter().sum();
+        let p: Vec<f64> = sizes.iter().map(|&x| x * 10.0 / total).collect();
+        let options = SampleOptions::new(&p)?;
+        let reps = NonZeroU32::new(1_000_000 / population_size).unwrap();
+
+        for (name, design) in designs.iter() {
+            let time = measure_design_time(&mut rng, design, &options, reps)?;
+            println!("{name:<12}{population_size:>12}{time:>14.2?}");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,155 @@
+// Copyright (C) 2024 Wilmer Prentius, Anton Grafström.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU Affero General Public License as published by the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Balance diagnostics for balanced samples
+
+use envisim_samplr::SamplingError;
+use envisim_utils::{InputError, Matrix, Probabilities};
+
+/// The balance of a sample on a set of balancing variables, see [`balance_report`].
+/// Element `k` of each field refers to balancing variable (column) `k`.
+/// Implements `Serialize` and `Deserialize` with the `serde` feature.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BalanceReport {
+    /// The Horvitz-Thompson estimates of the totals
+    pub estimates: Vec<f64>,
+    /// The known population totals
+    pub totals: Vec<f64>,
+    /// The relative imbalances, `(estimate - total) / |total|`
+    pub relative_imbalances: Vec<f64>,
+}
+
+impl BalanceReport {
+    /// Returns the largest absolute relative imbalance
+    #[inline]
+    pub fn max_relative_imbalance(&self) -> f64 {
+        self.relative_imbalances
+            .iter()
+            .fold(0.0, |acc: f64, &r| acc.max(r.abs()))
+    }
+    /// Returns the balancing variables with an absolute relative imbalance larger than
+    /// `tolerance`, or `NaN`, e.g. constraints dropped in the landing phase of the cube method
+    #[inline]
+    pub fn imbalanced(&self, tolerance: f64) -> Vec<usize> {
+        (0..self.relative_imbalances.len())
+            .filter(|&k| {
+                let r = self.relative_imbalances[k].abs();
+                r > tolerance || r.is_nan()
+            })
+            .collect()
+    }
+}
+
+/// Returns the balance of `sample` on the `balancing` variables, i.e. the Horvitz-Thompson
+/// estimates of the totals of the balancing variables compared to the `population_totals`.
+/// The rows of `balancing` and the `probabilities` refer to the units of the population.
+/// If a population total is zero, the relative imbalance is infinite, or `NaN` if the estimate is
+/// also zero.
+/// Returns an error if the dimensions do not match, or if the sample contains units outside of
+/// the population.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::balanced::balance_report;
+/// use envisim_utils::Matrix;
+///
+/// let p = [0.5; 4];
+/// let m = Matrix::from_vec(vec![1.0, 2.0, 3.0, 4.0], 4);
+/// let report = balance_report(&[0, 3], &p, &m, &[10.0])?;
+///
+/// assert_eq!(report.estimates, vec![10.0]);
+/// assert_eq!(report.relative_imbalances, vec![0.0]);
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+pub fn balance_report(
+    sample: &[usize],
+    probabilities: &[f64],
+    balancing: &Matrix,
+    population_totals: &[f64],
+) -> Result<BalanceReport, SamplingError> {
+    let (population_size, n_variables) = balancing.dim();
+    InputError::check_empty(probabilities)
+        .and(InputError::check_sizes(
+            probabilities.len(),
+            population_size,
+        ))
+        .and(InputError::check_sizes(
+            population_totals.len(),
+            n_variables,
+        ))
+        .and(Probabilities::check(probabilities))?;
+    sample
+        .iter()
+        .try_for_each(|&id| InputError::check_range_usize(id, 0, population_size - 1))?;
+
+    let estimates: Vec<f64> = (0..n_variables)
+        .map(|k| {
+            sample
+                .iter()
+                .fold(0.0, |acc, &id| acc + balancing[(id, k)] / probabilities[id])
+        })
+        .collect();
+    let relative_imbalances = estimates
+        .iter()
+        .zip(population_totals.iter())
+        .map(|(&e, &t)| (e - t) / t.abs())
+        .collect();
+
+    Ok(BalanceReport {
+        estimates,
+        totals: population_totals.to_vec(),
+        relative_imbalances,
+    })
+}
+
+/// Returns the balancing matrix for the cube method, with the `probabilities` as the first
+/// column, followed by the columns of `auxiliaries`.
+/// Balancing on the inclusion probabilities is required for the cube method to produce samples
+/// of fixed size, when the probabilities sum to an integer.
+/// Returns an error if the probabilities are empty or invalid, or if the number of rows of
+/// `auxiliaries` does not equal the number of probabilities.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::balanced::balancing_matrix;
+/// use envisim_samplr::{cube_method::cube, SampleOptions};
+/// use envisim_utils::Matrix;
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let p = [0.2, 0.25, 0.35, 0.4, 0.5, 0.5, 0.55, 0.65, 0.7, 0.9];
+/// let x = Matrix::from_vec(vec![0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9], 10);
+/// let bal_m = balancing_matrix(&p, &x)?;
+/// let s = SampleOptions::new(&p)?.balancing(&bal_m)?.sample(&mut rng, cube)?;
+///
+/// assert_eq!(bal_m.dim(), (10, 2));
+/// assert_eq!(s.len(), 5);
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+pub fn balancing_matrix<'a>(
+    probabilities: &[f64],
+    auxiliaries: &Matrix,
+) -> Result<Matrix<'a>, SamplingError> {
+    InputError::check_empty(probabilities)
+        .and(InputError::check_sizes(
+            auxiliaries.nrow(),
+            probabilities.len(),
+        ))
+        .and(Probabilities::check(probabilities))?;
+
+    let mut data = Vec::<f64>::with_capacity(probabilities.len() * (auxiliaries.ncol() + 1));
+    data.extend_from_slice(probabilities);
+    data.extend_from_slice(auxiliaries.data());
+
+    Ok(Matrix::from_vec(data, probabilities.len()))
+}
@@ -0,0 +1,175 @@
+// Copyright (C) 2024 Wilmer Prentius, Anton Grafström.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU Affero General Public License as published by the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Calibration of design weights
+
+use crate::horvitz_thompson::design_weights;
+use crate::regression::solve;
+use envisim_samplr::SamplingError;
+use envisim_utils::{InputError, Matrix};
+use std::num::NonZeroUsize;
+
+const CALIBRATION_EPS: f64 = 1e-10;
+
+// The logit calibration function g(u) = L + (U - L) s, with s = 1 / (1 + exp(-(A u - ln(c)))),
+// and its derivative, where A = (U - L) / ((1 - L) (U - 1)) and c = (U - 1) / (1 - L), such that
+// g(0) = 1
+#[inline]
+fn logit(u: f64, lower: f64, upper: f64) -> (f64, f64) {
+    let a = (upper - lower) / ((1.0 - lower) * (upper - 1.0));
+    let c = (upper - 1.0) / (1.0 - lower);
+    let s = 1.0 / (1.0 + (-(a * u) + c.ln()).exp());
+    (
+        lower + (upper - lower) * s,
+        (upper - lower) * a * s * (1.0 - s),
+    )
+}
+
+/// Calibrated weights, using the bounded logit method of Deville and Särndal.
+/// The calibrated weights `w_i = d_i g_i` satisfy the calibration equations, `sum w x = totals`,
+/// where `d_i = 1 / p_i` are the design weights, and the g-weights are bounded such that
+/// `lower < g_i < upper`.
+/// Row `i` of `auxiliaries` holds the auxiliary variables of sampled unit `i`, and `totals`
+/// holds the known population totals of the auxiliary variables.
+/// The calibration equations are solved by Newton's method, iterating until the equations hold
+/// to a relative tolerance of `1e-10`.
+///
+/// Returns [`SamplingError::MaxIterations`] if the equations are not solved within
+/// `max_iterations`, which is the case if no weights within the bounds satisfy the equations.
+/// Returns an error if the dimensions do not match, if any probability is not in `(0.0, 1.0]`, if
+/// `lower` is not in `[0.0, 1.0)`, if `upper` is not larger than `1.0`, or if the weighted gram
+/// matrix of the auxiliaries is singular.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::calibration::calibrate;
+/// use envisim_utils::Matrix;
+/// use std::num::NonZeroUsize;
+///
+/// let x = Matrix::new(&[1.0, 1.0, 1.0, 1.0, 1.0, 2.0, 3.0, 4.0], 4);
+/// let p = [0.5; 4];
+/// let max_iterations = NonZeroUsize::new(100).unwrap();
+/// let w = calibrate(&x, &p, &[10.0, 24.0], 0.5, 2.0, max_iterations)?;
+///
+/// assert!((w.iter().sum::<f64>() - 10.0).abs() < 1e-9);
+/// assert!(w.iter().all(|&w| 1.0 < w && w < 4.0));
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+///
+/// # References
+/// Deville, J. C., & Särndal, C. E. (1992).
+/// Calibration estimators in survey sampling.
+/// Journal of the American statistical Association, 87(418), 376-382.
+/// <https://doi.org/10.1080/01621459.1992.10475217>
+pub fn calibrate(
+    auxiliaries: &Matrix,
+    probabilities: &[f64],
+    totals: &[f64],
+    lower: f64,
+    upper: f64,
+    max_iterations: NonZeroUsize,
+) -> Result<Vec<f64>, SamplingError> {
+    let (sample_size, n_vars) = auxiliaries.dim();
+    InputError::check_sizes(probabilities.len(), sample_size)
+        .and(InputError::check_sizes(totals.len(), n_vars))?;
+    totals.iter().try_for_each(|&t| InputError::check_nan(t))?;
+    InputError::check_nan(lower)
+        .and(InputError::check_nan(upper))
+        .and(InputError::check_range_f64(lower, 0.0, 1.0))
+        .and(InputError::check_valid_f64(lower, 1.0))
+        .and(InputError::check_range_f64(upper, 1.0, f64::MAX))
+        .and(InputError::check_valid_f64(upper, 1.0))?;
+
+    let design_weights = design_weights(probabilities)?;
+    let mut lambda = vec![0.0; n_vars];
+
+    // Returns the residuals of the calibration equations, and the jacobian
+    let evaluate = |lambda: &[f64]| {
+        let mut residuals = totals.to_vec();
+        let mut jacobian = Matrix::from_value(0.0, (n_vars, n_vars));
+
+        for (k, &d) in design_weights.iter().enumerate() {
+            let u = auxiliaries
+                .row_iter(k)
+                .zip(lambda.iter())
+                .fold(0.0, |acc, (&x, &l)| acc + x * l);
+            let (g, dg) = logit(u, lower, upper);
+
+            for i in 0..n_vars {
+                let xi = auxiliaries[(k, i)];
+                residuals[i] -= d * g * xi;
+
+                for j in 0..n_vars {
+                    jacobian[(i, j)] += d * dg * xi * auxiliaries[(k, j)];
+                }
+            }
+        }
+
+        (residuals, jacobian)
+    };
+    let norm = |r: &[f64]| r.iter().fold(0.0, |acc: f64, &x| acc.max(x.abs()));
+    let converged = |r: &[f64]| {
+        r.iter()
+            .zip(totals.iter())
+            .all(|(&r, &t)| r.abs() <= CALIBRATION_EPS * (1.0 + t.abs()))
+    };
+
+    let (mut residuals, mut jacobian) = evaluate(&lambda);
+    let mut iteration = 0;
+
+    while !converged(&residuals) {
+        if iteration == max_iterations.get() {
+            return Err(SamplingError::MaxIterations(max_iterations));
+        }
+
+        // A singular jacobian after the first iteration is due to saturated g-weights
+        let step = match solve(&jacobian, &residuals) {
+            Ok(step) => step,
+            Err(err) if iteration == 0 => return Err(err.into()),
+            Err(_) => return Err(SamplingError::MaxIterations(max_iterations)),
+        };
+
+        // Halve the step until the residuals decrease
+        let mut scale = 1.0;
+        loop {
+            let candidate: Vec<f64> = lambda
+                .iter()
+                .zip(step.iter())
+                .map(|(&l, &s)| l + scale * s)
+                .collect();
+            let (r, j) = evaluate(&candidate);
+
+            if norm(&r) < norm(&residuals) || scale < 1e-10 {
+                lambda = candidate;
+                residuals = r;
+                jacobian = j;
+                break;
+            }
+
+            scale *= 0.5;
+        }
+
+        iteration += 1;
+    }
+
+    Ok(design_weights
+        .iter()
+        .enumerate()
+        .map(|(k, &d)| {
+            let u = auxiliaries
+                .row_iter(k)
+                .zip(lambda.iter())
+                .fold(0.0, |acc, (&x, &l)| acc + x * l);
+            d * logit(u, lower, upper).0
+        })
+        .collect())
+}
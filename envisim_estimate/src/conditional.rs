@@ -0,0 +1,118 @@
+// Copyright (C) 2024 Wilmer Prentius, Anton Grafström.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU Affero General Public License as published by the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Conditional inclusion probabilities, given a partially selected sample
+
+use envisim_samplr::{SampleOptions, SamplingError};
+use envisim_utils::utils::usize_to_f64;
+use envisim_utils::{symmetric_polynomials, InputError};
+
+/// Returns the inclusion probabilities of the units, conditional on the units of
+/// `already_selected` being in the sample, under a conditional poisson design.
+/// The design is [`envisim_samplr::poisson::conditional`], with the probabilities of `options` as
+/// the poisson probabilities, and the sample size `n` given by their sum, which must be an
+/// integer (within the `eps` of the options).
+/// Given the selected units, the remaining units form a conditional poisson sample of size
+/// `n - |already_selected|`, with the same poisson probabilities.
+/// The probability of a remaining unit `k` is `w_k e_{m-1}(w_{-k}) / e_m(w)`, where `w` are the
+/// odds `p / (1 - p)` of the remaining units, `m` is the remaining sample size, and `e` are the
+/// elementary symmetric polynomials, see [`envisim_utils::symmetric_polynomials`].
+/// The already selected units are given probability `1.0`.
+///
+/// Returns an error if any selected unit is out of range, duplicated, or has probability zero, or
+/// if the remaining sample size is negative, or larger than the number of remaining units with
+/// positive probability.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::conditional_probabilities;
+/// use envisim_samplr::SampleOptions;
+///
+/// let p = [0.5, 0.5, 0.5, 0.5];
+/// let q = conditional_probabilities(&SampleOptions::new(&p)?, &[2])?;
+///
+/// assert_eq!(q[2], 1.0);
+/// assert!((q[0] - 1.0 / 3.0).abs() < 1e-12);
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+pub fn conditional_probabilities(
+    options: &SampleOptions,
+    already_selected: &[usize],
+) -> Result<Vec<f64>, SamplingError> {
+    let probabilities = options.probabilities();
+    let population_size = probabilities.len();
+    let sample_size = options.expected_size();
+    if !options.is_fixed_size() {
+        return Err(InputError::NotInteger(sample_size).into());
+    }
+
+    let mut result = probabilities.to_vec();
+    let mut selected = vec![false; population_size];
+
+    for &id in already_selected.iter() {
+        InputError::check_range_usize(id, 0, population_size - 1)?;
+        if selected[id] {
+            return Err(InputError::NotUnique.into());
+        }
+        InputError::check_positive(probabilities[id])?;
+        selected[id] = true;
+        result[id] = 1.0;
+    }
+
+    // Units with probability 1.0 are always selected
+    let n_certain = (0..population_size)
+        .filter(|&i| !selected[i] && probabilities[i] >= 1.0)
+        .count();
+    let remaining: Vec<usize> = (0..population_size)
+        .filter(|&i| !selected[i] && 0.0 < probabilities[i] && probabilities[i] < 1.0)
+        .collect();
+    let remaining_size = sample_size.round() - usize_to_f64(already_selected.len() + n_certain);
+    InputError::check_range_f64(remaining_size, 0.0, usize_to_f64(remaining.len()))?;
+    let m = remaining_size as usize;
+
+    if m == 0 {
+        remaining.iter().for_each(|&i| result[i] = 0.0);
+        return Ok(result);
+    }
+
+    let odds: Vec<f64> = remaining
+        .iter()
+        .map(|&i| probabilities[i] / (1.0 - probabilities[i]))
+        .collect();
+
+    // The polynomials of the units excluding k are the convolution of the polynomials of the
+    // units before and after k, which avoids the cancellation of removing k by subtraction
+    let mut suffixes = vec![vec![0.0; m]; odds.len() + 1];
+    suffixes[odds.len()][0] = 1.0;
+    for k in (0..odds.len()).rev() {
+        let (current, next) = suffixes.split_at_mut(k + 1);
+        current[k][0] = 1.0;
+        for j in 1..m {
+            current[k][j] = next[0][j] + odds[k] * next[0][j - 1];
+        }
+    }
+
+    let total = symmetric_polynomials(&odds, m)[m];
+    let mut prefix = vec![0.0; m];
+    prefix[0] = 1.0;
+
+    for (k, &w) in odds.iter().enumerate() {
+        let excluding = (0..m).fold(0.0, |acc, j| acc + prefix[j] * suffixes[k + 1][m - 1 - j]);
+        result[remaining[k]] = (w * excluding / total).clamp(0.0, 1.0);
+
+        for j in (1..m).rev() {
+            prefix[j] += w * prefix[j - 1];
+        }
+    }
+
+    Ok(result)
+}
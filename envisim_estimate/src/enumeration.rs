@@ -0,0 +1,194 @@
+// Copyright (C) 2024 Wilmer Prentius, Anton Grafström.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU Affero General Public License as published by the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Enumeration of the sample space of designs on small populations
+
+use envisim_samplr::{SampleOptions, SamplingError};
+use envisim_utils::InputError;
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use rustc_hash::{FxBuildHasher, FxHashMap};
+use std::num::NonZeroU32;
+
+/// The largest population whose sample space is enumerated by [`enumerate_poisson`], and
+/// handled by [`exact_variance`].
+/// The `2^N` samples are all held in memory, i.e. about 100 MB for `N = 20`.
+const MAX_ENUMERATION_SIZE: usize = 20;
+
+#[inline]
+fn population_size_check(
+    options: &SampleOptions,
+    max_population_size: usize,
+) -> Result<usize, InputError> {
+    let population_size = options.probabilities().len();
+    InputError::check_range_usize(population_size, 1, max_population_size)?;
+    Ok(population_size)
+}
+
+/// Returns every possible sample of a poisson design, and its probability.
+/// The selection probability of a sample `s` is `prod_{i in s} p_i prod_{i not in s} (1 - p_i)`.
+/// Samples with probability zero are omitted.
+/// The samples are sorted, and listed in lexicographic order.
+/// Returns an error if the population is larger than `max_population_size`, or larger than 20,
+/// as the sample space has `2^N` elements, which are all held in memory.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::enumeration::enumerate_poisson;
+/// use envisim_samplr::SampleOptions;
+///
+/// let p = [0.5, 0.2];
+/// let samples = enumerate_poisson(&SampleOptions::new(&p)?, 10)?;
+///
+/// assert_eq!(samples.len(), 4);
+/// assert_eq!(samples[0].0, vec![]);
+/// assert!((samples[0].1 - 0.4).abs() < 1e-12);
+/// assert_eq!(samples[3].0, vec![1]);
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+pub fn enumerate_poisson(
+    options: &SampleOptions,
+    max_population_size: usize,
+) -> Result<Vec<(Vec<usize>, f64)>, SamplingError> {
+    let population_size =
+        population_size_check(options, max_population_size.min(MAX_ENUMERATION_SIZE))?;
+    let probabilities = options.probabilities();
+
+    let mut samples: Vec<(Vec<usize>, f64)> = (0..(1usize << population_size))
+        .filter_map(|mask| {
+            let mut sample = Vec::<usize>::new();
+            let mut prob = 1.0;
+
+            for (i, &p) in probabilities.iter().enumerate() {
+                if mask & (1 << i) != 0 {
+                    sample.push(i);
+                    prob *= p;
+                } else {
+                    prob *= 1.0 - p;
+                }
+            }
+
+            (prob > 0.0).then_some((sample, prob))
+        })
+        .collect();
+
+    samples.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    Ok(samples)
+}
+
+/// Returns the possible samples of `design`, and their probabilities, estimated empirically by
+/// the relative frequencies of `reps` draws, using a [`SmallRng`] seeded by `seed`.
+/// This is the fallback for designs without a closed form design, and the result is an
+/// approximation: samples with small probabilities may be missing.
+/// The samples are sorted, and listed in lexicographic order.
+/// Returns an error if the population is larger than `max_population_size`.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::enumeration::enumerate_design;
+/// use envisim_samplr::pivotal_method::spm;
+/// use envisim_samplr::SampleOptions;
+/// use std::num::NonZeroU32;
+///
+/// let p = [0.5, 0.5, 1.0];
+/// let reps = NonZeroU32::new(10000).unwrap();
+/// let samples = enumerate_design(spm, &SampleOptions::new(&p)?, 10, reps, 4242)?;
+///
+/// assert_eq!(samples.len(), 2);
+/// assert_eq!(samples[0].0, vec![0, 2]);
+/// assert!((samples[0].1 - 0.5).abs() < 0.05);
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+pub fn enumerate_design<F>(
+    design: F,
+    options: &SampleOptions,
+    max_population_size: usize,
+    reps: NonZeroU32,
+    seed: u64,
+) -> Result<Vec<(Vec<usize>, f64)>, SamplingError>
+where
+    F: Fn(&mut SmallRng, &SampleOptions) -> Result<Vec<usize>, SamplingError>,
+{
+    population_size_check(options, max_population_size)?;
+
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let mut counts = FxHashMap::<Vec<usize>, u32>::with_hasher(FxBuildHasher);
+
+    for _ in 0..reps.get() {
+        let mut sample = design(&mut rng, options)?;
+        sample.sort_unstable();
+        *counts.entry(sample).or_insert(0) += 1;
+    }
+
+    let mut samples: Vec<(Vec<usize>, f64)> = counts
+        .into_iter()
+        .map(|(sample, count)| (sample, f64::from(count) / f64::from(reps.get())))
+        .collect();
+
+    samples.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    Ok(samples)
+}
+
+/// Returns the design variance of the Horvitz-Thompson estimator of the total of `y_population`,
+/// `sum p(s) (Y(s) - E[Y(s)])^2`, over the sample space returned by `design`, e.g.
+/// [`enumerate_poisson`].
+/// The inclusion probabilities used by the estimator are those of `options`.
+/// The variance is exact if the enumeration of the sample space is exact, and otherwise e.g. an
+/// approximation based on [`enumerate_design`].
+/// Returns an error if the population is larger than 20 units, as the sample space is held in
+/// memory, or if the lengths do not match.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::enumeration::enumerate_poisson;
+/// use envisim_estimate::exact_variance;
+/// use envisim_samplr::SampleOptions;
+///
+/// let y = [1.0, 2.0, 3.0];
+/// let p = [0.5, 0.5, 0.5];
+/// let v = exact_variance(&y, |o| enumerate_poisson(o, 3), &SampleOptions::new(&p)?)?;
+///
+/// // For poisson sampling, sum y^2 (1 - p) / p
+/// assert!((v - 14.0).abs() < 1e-9);
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+pub fn exact_variance<F>(
+    y_population: &[f64],
+    design: F,
+    options: &SampleOptions,
+) -> Result<f64, SamplingError>
+where
+    F: Fn(&SampleOptions) -> Result<Vec<(Vec<usize>, f64)>, SamplingError>,
+{
+    population_size_check(options, MAX_ENUMERATION_SIZE)?;
+    let probabilities = options.probabilities();
+    InputError::check_lengths(y_population, probabilities)?;
+
+    let estimates: Vec<(f64, f64)> = design(options)?
+        .iter()
+        .map(|(sample, prob)| {
+            let estimate = sample
+                .iter()
+                .fold(0.0, |acc, &id| acc + y_population[id] / probabilities[id]);
+            (estimate, *prob)
+        })
+        .collect();
+
+    let total_prob: f64 = estimates.iter().map(|e| e.1).sum();
+    let mean = estimates.iter().map(|(e, p)| e * p).sum::<f64>() / total_prob;
+
+    Ok(estimates
+        .iter()
+        .map(|(e, p)| p * (e - mean).powi(2))
+        .sum::<f64>()
+        / total_prob)
+}
@@ -13,6 +13,7 @@
 //! Hansen-Hurwitz estimators (multiple count estimators)
 
 use envisim_samplr::SamplingError;
+use envisim_utils::utils::usize_to_f64;
 use envisim_utils::{InputError, Matrix};
 
 #[inline]
@@ -100,3 +101,125 @@ pub fn variance(
 
     Ok(variance)
 }
+
+// Truncated power series product
+fn series_mult(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let mut prod = vec![0.0; a.len()];
+
+    for (i, &x) in a.iter().enumerate() {
+        if x == 0.0 {
+            continue;
+        }
+        for (pr, &y) in prod[i..].iter_mut().zip(b.iter()) {
+            *pr += x * y;
+        }
+    }
+
+    prod
+}
+
+// Truncated power series of exp(c x), or exp(c x) - 1
+fn series_exp(c: f64, len: usize, minus_one: bool) -> Vec<f64> {
+    let mut series = Vec::<f64>::with_capacity(len);
+    let mut term = 1.0;
+
+    for k in 0..len {
+        series.push(term);
+        term *= c / usize_to_f64(k + 1);
+    }
+
+    if minus_one {
+        series[0] = 0.0;
+    }
+
+    series
+}
+
+/// Rao-Blackwellized Hansen-Hurwitz estimator of a total, for a with replacement sample of
+/// `m = sample.len()` draws, with draw `probabilities` (summing to 1.0).
+/// The estimator is the conditional expectation of the Hansen-Hurwitz estimator, given the set of
+/// distinct units in the sample, and thus has a variance at most that of the Hansen-Hurwitz
+/// estimator.
+/// The `y_values` and `probabilities` are given for all `population_size` units, and `sample`
+/// holds the (possibly repeated) drawn units.
+/// Returns an error if the sample is empty, if any index is out of range, if the lengths do not
+/// match `population_size`, or if any drawn unit has a non-positive probability.
+///
+/// The conditional expectations are computed using generating functions, at a cost of
+/// `O(d m^2)`, where `d` is the number of distinct units.
+/// The method is intended for moderate numbers of draws, as the computations may overflow for
+/// `m` of more than about 2000.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::hansen_hurwitz::rao_blackwell;
+///
+/// let y = [1.0, 2.0, 3.0, 4.0];
+/// let p = [0.25; 4];
+/// // With equal probabilities, the estimator is N times the mean of the distinct units
+/// let est = rao_blackwell(&[1, 1, 3], &y, &p, 4).unwrap();
+/// assert!((est - 12.0).abs() < 1e-9);
+/// ```
+///
+/// # References
+/// Pathak, P. K. (1962).
+/// On sampling with unequal probabilities.
+/// Sankhyā: The Indian Journal of Statistics, Series A, 24(3), 315-326.
+pub fn rao_blackwell(
+    sample: &[usize],
+    y_values: &[f64],
+    probabilities: &[f64],
+    population_size: usize,
+) -> Result<f64, SamplingError> {
+    InputError::check_empty(sample)
+        .and(InputError::check_sizes(y_values.len(), population_size))
+        .and(InputError::check_sizes(
+            probabilities.len(),
+            population_size,
+        ))?;
+    sample
+        .iter()
+        .try_for_each(|&id| InputError::check_range_usize(id, 0, population_size - 1))?;
+
+    let mut distinct = sample.to_vec();
+    distinct.sort_unstable();
+    distinct.dedup();
+    distinct.iter().try_for_each(|&id| {
+        InputError::check_nan(probabilities[id])
+            .and(InputError::check_range_f64(probabilities[id], 0.0, 1.0))
+            .and(InputError::check_positive(probabilities[id]))
+    })?;
+
+    // With the distinct units D, the estimator is
+    // sum_{i in D} y_i [x^(m-1)] e^(p_i x) prod_{j != i} (e^(p_j x) - 1) / [x^m] prod_j (e^(p_j x) - 1),
+    // scaled by (m-1)!/m! = 1/m. The argument is scaled by lambda, such that the coefficients stay
+    // in a reasonable range.
+    let m = sample.len();
+    let len = m + 1;
+    let psum = distinct
+        .iter()
+        .fold(0.0, |acc, &id| acc + probabilities[id]);
+    let lambda = usize_to_f64(m) / (std::f64::consts::E * psum);
+    let factors: Vec<Vec<f64>> = distinct
+        .iter()
+        .map(|&id| series_exp(lambda * probabilities[id], len, true))
+        .collect();
+
+    let mut prefix = vec![series_exp(0.0, len, false)];
+    for f in factors.iter() {
+        prefix.push(series_mult(prefix.last().unwrap(), f));
+    }
+    let mut suffix = series_exp(0.0, len, false);
+    let denominator = prefix[distinct.len()][m];
+
+    let mut estimate = 0.0;
+    for (k, &id) in distinct.iter().enumerate().rev() {
+        let others = series_mult(&prefix[k], &suffix);
+        let own = series_exp(lambda * probabilities[id], len, false);
+        let numerator = (0..m).fold(0.0, |acc, r| acc + others[r] * own[m - 1 - r]);
+        estimate += y_values[id] * numerator;
+        suffix = series_mult(&suffix, &factors[k]);
+    }
+
+    Ok(estimate * lambda / (usize_to_f64(m) * denominator))
+}
@@ -12,11 +12,15 @@
 
 //! Horvitz-Thompson estimators (single count estimators)
 
-use envisim_samplr::SamplingError;
+use envisim_samplr::unequal::sampford_second_order;
+use envisim_samplr::{SampleOptions, SamplingError};
 use envisim_utils::kd_tree::{Searcher, TreeBuilder};
-use envisim_utils::utils::{sum, usize_to_f64};
-use envisim_utils::{InputError, Matrix, Probabilities};
-use std::num::NonZeroUsize;
+use envisim_utils::utils::{self, sum, usize_to_f64};
+use envisim_utils::{InputError, Matrix, MatrixLike, Probabilities, SymMatrix};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::io::BufRead;
+use std::num::{NonZeroU32, NonZeroUsize};
 
 /// Horvitz-Thompson estimator of a total
 ///
@@ -39,6 +43,156 @@ pub fn estimate(y_values: &[f64], probabilities: &[f64]) -> Result<f64, Sampling
         .fold(0.0, |acc, (&y, &p)| acc + y / p))
 }
 
+/// Horvitz-Thompson estimator of a total, where the probabilities of the `sample` are looked up
+/// in the `population_probabilities`.
+/// Element `i` of `y_values` is the value of unit `sample[i]`, as returned by the designs.
+/// Returns an error if the lengths of `y_values` and `sample` do not match, or if any index is
+/// out of range.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::horvitz_thompson::estimate_indexed;
+///
+/// let pi = [0.2, 0.25, 0.5, 0.5, 0.4];
+/// let s = [1, 3];
+/// let y = [1.0, 2.0];
+///
+/// let est = estimate_indexed(&s, &y, &pi).unwrap();
+/// assert!((est - 8.0).abs() < 1e-12);
+/// ```
+pub fn estimate_indexed(
+    sample: &[usize],
+    y_values: &[f64],
+    population_probabilities: &[f64],
+) -> Result<f64, SamplingError> {
+    InputError::check_lengths(y_values, sample)
+        .and(InputError::check_empty(population_probabilities))?;
+    sample.iter().try_for_each(|&id| {
+        InputError::check_range_usize(id, 0, population_probabilities.len() - 1)
+    })?;
+
+    let probabilities: Vec<f64> = sample
+        .iter()
+        .map(|&id| population_probabilities[id])
+        .collect();
+    estimate(y_values, &probabilities)
+}
+
+/// Horvitz-Thompson estimator of a total, where the sampled records are read from `reader`.
+/// Each line holds a record `y, pi`, separated by whitespace or a comma, and blank lines are
+/// skipped.
+/// The estimate is accumulated one record at a time, hence the sample is never kept in memory.
+/// Returns an error if reading fails, if a line does not consist of two numbers, or if any
+/// probability is not in `(0.0, 1.0]`.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::horvitz_thompson::estimate_from_reader;
+/// use std::io::Cursor;
+///
+/// let records = "1.0, 0.5\n2.0 0.25\n";
+/// let est = estimate_from_reader(Cursor::new(records))?;
+///
+/// assert!((est - 10.0).abs() < 1e-12);
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+pub fn estimate_from_reader<R>(reader: R) -> Result<f64, SamplingError>
+where
+    R: BufRead,
+{
+    let mut total = 0.0;
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let mut fields = line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|field| !field.is_empty());
+
+        let Some(y_field) = fields.next() else {
+            continue;
+        };
+        let (Ok(y), Some(Ok(p)), None) = (
+            y_field.parse::<f64>(),
+            fields.next().map(str::parse::<f64>),
+            fields.next(),
+        ) else {
+            return Err(InputError::InvalidRecord(i + 1).into());
+        };
+
+        InputError::check_nan(y)
+            .and(InputError::check_nan(p))
+            .and(InputError::check_range_f64(p, 0.0, 1.0))
+            .and(InputError::check_valid_f64(p, 0.0))?;
+        total += y / p;
+    }
+
+    Ok(total)
+}
+
+/// Returns the design weights `1 / p` of the sampled units.
+/// Returns an error if any probability is not in `(0.0, 1.0]`, as a zero probability would
+/// yield an infinite weight.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::horvitz_thompson::design_weights;
+///
+/// assert_eq!(design_weights(&[0.5, 0.25, 1.0]).unwrap(), vec![2.0, 4.0, 1.0]);
+/// assert!(design_weights(&[0.5, 0.0]).is_err());
+/// ```
+pub fn design_weights(probabilities: &[f64]) -> Result<Vec<f64>, SamplingError> {
+    Probabilities::check(probabilities)?;
+    probabilities
+        .iter()
+        .try_for_each(|&p| InputError::check_positive(p))?;
+
+    Ok(probabilities.iter().map(|&p| 1.0 / p).collect())
+}
+
+/// Estimator of a total using design `weights` directly, `sum w y`.
+/// With `w = 1 / p`, the estimator equals [`estimate`], but the weights may also be adjusted,
+/// e.g. by calibration or trimming.
+/// Returns an error if the lengths do not match, or if any weight is negative or not finite.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::horvitz_thompson::{estimate, estimate_with_weights};
+///
+/// let y = [0.0, 0.1, 0.2, 0.3, 0.4];
+/// let w = [5.0; 5];
+///
+/// let est = estimate_with_weights(&y, &w).unwrap(); // Should be about 5.0
+/// assert!((est - estimate(&y, &[0.2; 5]).unwrap()).abs() < 1e-12);
+/// ```
+#[inline]
+pub fn estimate_with_weights(y_values: &[f64], weights: &[f64]) -> Result<f64, SamplingError> {
+    InputError::check_lengths(y_values, weights)?;
+    weights.iter().try_for_each(|&w| {
+        InputError::check_nan(w).and(InputError::check_range_f64(w, 0.0, f64::MAX))
+    })?;
+
+    Ok(y_values
+        .iter()
+        .zip(weights.iter())
+        .fold(0.0, |acc, (&y, &w)| acc + y * w))
+}
+
+/// Horvitz-Thompson estimator of the population size, `sum 1 / p`.
+/// Returns an error if any probability is not in `(0.0, 1.0]`, see [`design_weights`].
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::horvitz_thompson::estimate_size;
+///
+/// let pi = [0.2, 0.25, 0.5, 0.5];
+/// let n = estimate_size(&pi).unwrap();
+/// assert!((n - 13.0).abs() < 1e-12);
+/// ```
+#[inline]
+pub fn estimate_size(probabilities: &[f64]) -> Result<f64, SamplingError> {
+    Ok(sum(&design_weights(probabilities)?))
+}
+
 /// Ratio estimator of total, using auxilliary variable `x_values`.
 #[inline]
 pub fn ratio(
@@ -51,12 +205,129 @@ pub fn ratio(
     Ok(estimate(y_values, probabilities)? / estimate(x_values, probabilities)? * x_total)
 }
 
-/// Horvitz-Thompson estimator of variance of total estimate
-pub fn variance(
+/// Design-weighted estimator of the population covariance matrix of the columns of `y_values`.
+/// Each row of `y_values` holds a sampled unit, weighted by the inverse of its probability.
+/// With `w` being the weights and `N = sum w`, element `(k, l)` is estimated by
+/// `sum w (y_k - m_k) (y_l - m_l) / (N - 1)`, where `m_k = sum w y_k / N`.
+/// The returned matrix is symmetric.
+/// Returns an error if `N` is at most `1.0`, e.g. for a sample of a single certainty unit.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::horvitz_thompson::weighted_covariance;
+/// use envisim_utils::Matrix;
+///
+/// let y = Matrix::new(&[1.0, 2.0, 3.0, 4.0, 0.0, 1.0, 0.0, 1.0], 4);
+/// let pi = [0.2; 4];
+/// let cov = weighted_covariance(&y, &pi).unwrap();
+///
+/// assert_eq!(cov.dim(), (2, 2));
+/// assert_eq!(cov[(0, 1)], cov[(1, 0)]);
+/// ```
+pub fn weighted_covariance(
+    y_values: &Matrix,
+    probabilities: &[f64],
+) -> Result<Matrix<'static>, SamplingError> {
+    let (sample_size, n_vars) = y_values.dim();
+    InputError::check_sizes(sample_size, probabilities.len())
+        .and(Probabilities::check(probabilities))
+        .and(
+            probabilities
+                .iter()
+                .try_for_each(|&p| InputError::check_positive(p)),
+        )?;
+
+    let weights: Vec<f64> = probabilities.iter().map(|&p| 1.0 / p).collect();
+    let wsum = sum(&weights);
+    InputError::check_range_f64(wsum, 1.0, f64::INFINITY)
+        .and(InputError::check_valid_f64(wsum, 1.0))?;
+    let means: Vec<f64> = (0..n_vars)
+        .map(|k| {
+            y_values
+                .col_iter(k)
+                .zip(weights.iter())
+                .fold(0.0, |acc, (&y, &w)| acc + w * y)
+                / wsum
+        })
+        .collect();
+
+    let mut covariance = Matrix::from_value(0.0, (n_vars, n_vars));
+
+    for k in 0..n_vars {
+        for l in k..n_vars {
+            let c = (0..sample_size).fold(0.0, |acc, i| {
+                acc + weights[i] * (y_values[(i, k)] - means[k]) * (y_values[(i, l)] - means[l])
+            }) / (wsum - 1.0);
+            covariance[(k, l)] = c;
+            covariance[(l, k)] = c;
+        }
+    }
+
+    Ok(covariance)
+}
+
+/// The tolerance used when checking the diagonal of second order probabilities in [`variance`]
+/// and [`syg_variance`], see [`variance_with_eps`] and [`syg_variance_with_eps`] for a custom
+/// tolerance.
+pub const SECOND_ORDER_EPS: f64 = 1e-9;
+
+/// Returns an error if the diagonal of the second order probabilities differs from the first
+/// order `probabilities` by more than `eps`, or if the dimensions do not match.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::horvitz_thompson::check_second_order_consistency;
+/// use envisim_utils::Matrix;
+///
+/// let pi = [0.4, 0.6];
+/// let pi2 = Matrix::new(&[0.4, 0.2, 0.2, 0.6], 2);
+/// assert!(check_second_order_consistency(&pi, &pi2, 1e-12).is_ok());
+/// let pi2 = Matrix::new(&[0.16, 0.2, 0.2, 0.36], 2);
+/// assert!(check_second_order_consistency(&pi, &pi2, 1e-12).is_err());
+/// ```
+pub fn check_second_order_consistency<M>(
+    probabilities: &[f64],
+    probabilities_second_order: &M,
+    eps: f64,
+) -> Result<(), SamplingError>
+where
+    M: MatrixLike + ?Sized,
+{
+    let size = probabilities.len();
+    InputError::check_sizes(size, probabilities_second_order.nrow())
+        .and(InputError::check_sizes(
+            size,
+            probabilities_second_order.ncol(),
+        ))
+        .and(Probabilities::check_eps(eps).map(|_| ()))?;
+
+    for (i, &p) in probabilities.iter().enumerate() {
+        let d = probabilities_second_order[(i, i)];
+
+        if (d - p).abs() > eps {
+            return Err(InputError::InconsistentDiagonal(i, d, p).into());
+        }
+    }
+
+    Ok(())
+}
+
+/// The threshold at or below which a second order probability of two sampled units is
+/// considered zero in [`variance`], [`syg_variance`] and [`variance_contributions`], as the
+/// estimators divide by the second order probabilities.
+pub const JOINT_PROBABILITY_EPS: f64 = 1e-12;
+
+// Checks the input of the estimators using second order probabilities
+#[inline]
+fn check_second_order_input<M>(
     y_values: &[f64],
     probabilities: &[f64],
-    probabilities_second_order: &Matrix,
-) -> Result<f64, SamplingError> {
+    probabilities_second_order: &M,
+    eps: Option<f64>,
+) -> Result<(), SamplingError>
+where
+    M: MatrixLike + ?Sized,
+{
     let sample_size = y_values.len();
     InputError::check_lengths(y_values, probabilities)
         .and(InputError::check_sizes(
@@ -69,6 +340,78 @@ pub fn variance(
         ))
         .and(Probabilities::check(probabilities))
         .and(Probabilities::check(probabilities_second_order.data()))?;
+    if let Some(eps) = eps {
+        check_second_order_consistency(probabilities, probabilities_second_order, eps)?;
+    }
+
+    // The estimators divide by the joint probabilities
+    for i in 0..sample_size {
+        for j in (i + 1)..sample_size {
+            if probabilities_second_order[(i, j)] <= JOINT_PROBABILITY_EPS {
+                return Err(InputError::ZeroJointProbability(i, j).into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Horvitz-Thompson estimator of variance of total estimate.
+/// The second order probabilities can be provided as a [`envisim_utils::Matrix`] or as a
+/// [`envisim_utils::SymMatrix`].
+/// Returns an error if the diagonal of the second order probabilities differs from the
+/// `probabilities` by more than [`SECOND_ORDER_EPS`], see [`check_second_order_consistency`].
+/// Returns [`InputError::ZeroJointProbability`] if any second order probability of two sampled
+/// units is at most [`JOINT_PROBABILITY_EPS`], e.g. for systematic designs, as the estimator is
+/// then undefined.
+#[inline]
+pub fn variance<M>(
+    y_values: &[f64],
+    probabilities: &[f64],
+    probabilities_second_order: &M,
+) -> Result<f64, SamplingError>
+where
+    M: MatrixLike + ?Sized,
+{
+    variance_with_eps(
+        y_values,
+        probabilities,
+        probabilities_second_order,
+        Some(SECOND_ORDER_EPS),
+    )
+}
+
+/// Horvitz-Thompson estimator of variance of total estimate, see [`variance`], where the diagonal
+/// of the second order probabilities is checked against the `probabilities` with tolerance
+/// `eps`.
+/// If `eps` is `None`, the check is skipped, e.g. for performance, or for empirical second order
+/// probabilities, see [`crate::estimate_second_order`], whose diagonal only approximates the
+/// `probabilities`.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::horvitz_thompson::*;
+/// use envisim_utils::Matrix;
+///
+/// let y = [1.0, 2.0, 4.0];
+/// let p = [0.4, 0.5, 0.6];
+/// let m = Matrix::new(&[0.41, 0.18, 0.22, 0.18, 0.49, 0.32, 0.22, 0.32, 0.6], 3);
+///
+/// assert!(variance(&y, &p, &m).is_err());
+/// assert!(variance_with_eps(&y, &p, &m, Some(0.05)).is_ok());
+/// assert!(variance_with_eps(&y, &p, &m, None).is_ok());
+/// ```
+pub fn variance_with_eps<M>(
+    y_values: &[f64],
+    probabilities: &[f64],
+    probabilities_second_order: &M,
+    eps: Option<f64>,
+) -> Result<f64, SamplingError>
+where
+    M: MatrixLike + ?Sized,
+{
+    let sample_size = y_values.len();
+    check_second_order_input(y_values, probabilities, probabilities_second_order, eps)?;
 
     let mut variance: f64 = 0.0;
 
@@ -85,24 +428,46 @@ pub fn variance(
     Ok(variance)
 }
 
-/// Sen-Yates-Grundy estimator of variance of total estimate of fixed sized sample
-pub fn syg_variance(
+/// Sen-Yates-Grundy estimator of variance of total estimate of fixed sized sample.
+/// The second order probabilities can be provided as a [`envisim_utils::Matrix`] or as a
+/// [`envisim_utils::SymMatrix`].
+/// Returns an error if the diagonal of the second order probabilities differs from the
+/// `probabilities` by more than [`SECOND_ORDER_EPS`], see [`check_second_order_consistency`].
+/// Returns [`InputError::ZeroJointProbability`] if any second order probability of two sampled
+/// units is at most [`JOINT_PROBABILITY_EPS`], e.g. for systematic designs, as the estimator is
+/// then undefined.
+#[inline]
+pub fn syg_variance<M>(
     y_values: &[f64],
     probabilities: &[f64],
-    probabilities_second_order: &Matrix,
-) -> Result<f64, SamplingError> {
+    probabilities_second_order: &M,
+) -> Result<f64, SamplingError>
+where
+    M: MatrixLike + ?Sized,
+{
+    syg_variance_with_eps(
+        y_values,
+        probabilities,
+        probabilities_second_order,
+        Some(SECOND_ORDER_EPS),
+    )
+}
+
+/// Sen-Yates-Grundy estimator of variance of total estimate of fixed sized sample, see
+/// [`syg_variance`], where the diagonal of the second order probabilities is checked against the
+/// `probabilities` with tolerance `eps`.
+/// If `eps` is `None`, the check is skipped, see [`variance_with_eps`].
+pub fn syg_variance_with_eps<M>(
+    y_values: &[f64],
+    probabilities: &[f64],
+    probabilities_second_order: &M,
+    eps: Option<f64>,
+) -> Result<f64, SamplingError>
+where
+    M: MatrixLike + ?Sized,
+{
     let sample_size = y_values.len();
-    InputError::check_lengths(y_values, probabilities)
-        .and(InputError::check_sizes(
-            sample_size,
-            probabilities_second_order.nrow(),
-        ))
-        .and(InputError::check_sizes(
-            sample_size,
-            probabilities_second_order.ncol(),
-        ))
-        .and(Probabilities::check(probabilities))
-        .and(Probabilities::check(probabilities_second_order.data()))?;
+    check_second_order_input(y_values, probabilities, probabilities_second_order, eps)?;
 
     let mut variance: f64 = 0.0;
 
@@ -118,6 +483,152 @@ pub fn syg_variance(
     Ok(variance)
 }
 
+/// Returns the contribution of each sampled unit to the Sen-Yates-Grundy estimator of variance,
+/// see [`syg_variance`].
+/// The term of each pair of units is split equally between the two units, such that the
+/// contributions sum to the variance estimate.
+/// Units with large contributions drive the variance estimate, and may be influential.
+/// Returns an error if the diagonal of the second order probabilities does not equal the
+/// `probabilities`, see [`check_second_order_consistency`], or if any second order probability
+/// is at most [`JOINT_PROBABILITY_EPS`].
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::horvitz_thompson::*;
+/// use envisim_utils::Matrix;
+///
+/// let y = [1.0, 2.0, 4.0];
+/// let p = [0.4, 0.5, 0.6];
+/// let m = Matrix::new(&[0.4, 0.18, 0.22, 0.18, 0.5, 0.32, 0.22, 0.32, 0.6], 3);
+/// let contributions = variance_contributions(&y, &p, &m)?;
+/// let total: f64 = contributions.iter().sum();
+///
+/// assert!((total - syg_variance(&y, &p, &m)?).abs() < 1e-9);
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+pub fn variance_contributions<M>(
+    y_values: &[f64],
+    probabilities: &[f64],
+    probabilities_second_order: &M,
+) -> Result<Vec<f64>, SamplingError>
+where
+    M: MatrixLike + ?Sized,
+{
+    let sample_size = y_values.len();
+    check_second_order_input(
+        y_values,
+        probabilities,
+        probabilities_second_order,
+        Some(SECOND_ORDER_EPS),
+    )?;
+
+    let mut contributions = vec![0.0; sample_size];
+
+    for i in 0..sample_size {
+        let y_pi = y_values[i] / probabilities[i];
+
+        for j in (i + 1)..sample_size {
+            let term = (y_pi - y_values[j] / probabilities[j]).powi(2)
+                * (1.0 - probabilities[i] * probabilities[j] / probabilities_second_order[(i, j)]);
+            contributions[i] -= 0.5 * term;
+            contributions[j] -= 0.5 * term;
+        }
+    }
+
+    Ok(contributions)
+}
+
+/// Sen-Yates-Grundy estimator of variance of total estimate of a sampford sample.
+/// The second order probabilities are computed for the `sample` by
+/// [`envisim_samplr::unequal::sampford_second_order`], using the probabilities of `options`, and
+/// `y_values[k]` is the value of unit `sample[k]`.
+/// Returns an error if `y_values` and `sample` have different lengths, if `sample` contains
+/// duplicates, or if the probabilities of `options` do not sum to an integer.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::horvitz_thompson::sampford_variance;
+/// use envisim_samplr::SampleOptions;
+///
+/// let p = [0.2, 0.25, 0.35, 0.4, 0.5, 0.5, 0.55, 0.65, 0.7, 0.9];
+/// let y = [1.0, 2.0, 4.0, 3.0, 0.5];
+/// let v = sampford_variance(&y, &[0, 2, 3, 7, 9], &SampleOptions::new(&p)?)?;
+///
+/// assert!(v > 0.0);
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+pub fn sampford_variance(
+    y_values: &[f64],
+    sample: &[usize],
+    options: &SampleOptions,
+) -> Result<f64, SamplingError> {
+    InputError::check_lengths(y_values, sample)?;
+    let mut sorted = sample.to_vec();
+    sorted.sort_unstable();
+    if sorted.windows(2).any(|w| w[0] == w[1]) {
+        return Err(InputError::NotUnique.into());
+    }
+
+    let second_order = sampford_second_order(options, sample)?;
+    let probabilities: Vec<f64> = (0..sample.len()).map(|k| second_order[(k, k)]).collect();
+    syg_variance(y_values, &probabilities, &second_order)
+}
+
+/// Returns the Hájek approximation of the second order probabilities of a high entropy fixed
+/// size design, given the `probabilities` of the sampled units,
+/// `pi_ij = pi_i pi_j (1 - (1 - pi_i) (1 - pi_j) / d)`, where `d = sum (1 - pi_k)` over the
+/// sample estimates `sum pi_k (1 - pi_k)` over the population.
+/// The approximations are clamped to the valid range `[max(0, pi_i + pi_j - 1), min(pi_i, pi_j)]`,
+/// and the diagonal holds the `probabilities`.
+/// The matrix can be used with [`syg_variance`] when the exact second order probabilities of the
+/// design are unavailable, e.g. for [`envisim_samplr::unequal::pareto`].
+///
+/// **Note:** the result is an approximation, which is only accurate for high entropy designs,
+/// and should not be used for low entropy designs, such as systematic designs.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::horvitz_thompson::{hajek_second_order, syg_variance};
+///
+/// let y = [1.0, 2.0, 4.0, 3.0];
+/// let p = [0.4, 0.5, 0.6, 0.5];
+/// let v = syg_variance(&y, &p, &hajek_second_order(&p)?)?;
+///
+/// assert!(v > 0.0);
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+///
+/// # References
+/// Hájek, J. (1964).
+/// Asymptotic theory of rejective sampling with varying probabilities from a finite population.
+/// The Annals of Mathematical Statistics, 35(4), 1491-1523.
+/// <https://doi.org/10.1214/aoms/1177700375>
+pub fn hajek_second_order(probabilities: &[f64]) -> Result<SymMatrix, SamplingError> {
+    InputError::check_empty(probabilities).and(Probabilities::check(probabilities))?;
+    probabilities
+        .iter()
+        .try_for_each(|&p| InputError::check_positive(p))?;
+
+    let d = probabilities.iter().fold(0.0, |acc, &p| acc + (1.0 - p));
+    let mut result = SymMatrix::from_value(0.0, probabilities.len());
+
+    for (j, &pj) in probabilities.iter().enumerate() {
+        result[(j, j)] = pj;
+
+        for (i, &pi) in probabilities.iter().enumerate().take(j) {
+            let correction = if d > 0.0 {
+                (1.0 - pi) * (1.0 - pj) / d
+            } else {
+                0.0
+            };
+            result[(i, j)] =
+                (pi * pj * (1.0 - correction)).clamp((pi + pj - 1.0).max(0.0), pi.min(pj));
+        }
+    }
+
+    Ok(result)
+}
+
 /// Deville estimator of variance of total estimate
 pub fn deville_variance(y_values: &[f64], probabilities: &[f64]) -> Result<f64, SamplingError> {
     InputError::check_lengths(y_values, probabilities).and(Probabilities::check(probabilities))?;
@@ -135,17 +646,245 @@ pub fn deville_variance(y_values: &[f64], probabilities: &[f64]) -> Result<f64,
         .iter()
         .zip(q.iter())
         .fold(0.0, |acc, (&a, &b)| acc + a * b);
-    let s1mp_del = s1mp / del;
+    let del_s1mp = del / s1mp;
     let sak2 = q.iter().fold(0.0, |acc, &a| acc + a.powi(2)) / s1mp.powi(2);
 
     let dsum = y_pi
         .iter()
         .zip(q.iter())
-        .fold(0.0, |acc, (&a, &b)| acc + (a - s1mp_del).powi(2) * b);
+        .fold(0.0, |acc, (&a, &b)| acc + (a - del_s1mp).powi(2) * b);
 
     Ok(1.0 / (1.0 - sak2) * dsum)
 }
 
+/// Generalized bootstrap estimator of variance of total estimate, using `reps` bootstrap
+/// replicates drawn by a [`SmallRng`] seeded by `seed`.
+///
+/// In each replicate, the Horvitz-Thompson estimate is recomputed with the design weights
+/// `1/p` multiplied by random bootstrap weights `a = 1 + s e`, where `e` is `-1` or `1` with equal
+/// probability, and the bootstrap variance is the variance of the replicates.
+/// The scales `s`, with `s^2 = (1 - p) / (1 - sum((1 - p)^2) / sum(1 - p)^2)`, match the second
+/// moments of the bootstrap weights to those of the design weights, as approximated by a
+/// conditional poisson design of fixed size.
+/// As for [`deville_variance`], the `y/p` are centered by their `1 - p` weighted mean, hence the
+/// expectation of the bootstrap variance, over the bootstrap weights, equals the
+/// [`deville_variance`], and the estimator is valid for high entropy fixed size designs with
+/// unequal probabilities.
+/// Returns an error if `reps` is less than 2.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::generalized_bootstrap;
+/// use std::num::NonZeroU32;
+///
+/// let y = [1.0, 2.0, 4.0, 3.0, 0.5];
+/// let p = [0.2, 0.35, 0.4, 0.65, 0.9];
+/// let v = generalized_bootstrap(&y, &p, NonZeroU32::new(1000).unwrap(), 4242)?;
+///
+/// assert!(v > 0.0);
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+///
+/// # References
+/// Bertail, P., & Combris, P. (1997).
+/// Bootstrap généralisé d'un sondage.
+/// Annales d'Économie et de Statistique, 46, 49-83.
+///
+/// Beaumont, J.-F., & Patak, Z. (2012).
+/// On the generalized bootstrap for sample surveys with special attention to Poisson sampling.
+/// International Statistical Review, 80(1), 127-148.
+/// <https://doi.org/10.1111/j.1751-5823.2011.00166.x>
+pub fn generalized_bootstrap(
+    y_values: &[f64],
+    probabilities: &[f64],
+    reps: NonZeroU32,
+    seed: u64,
+) -> Result<f64, SamplingError> {
+    InputError::check_lengths(y_values, probabilities)
+        .and(Probabilities::check(probabilities))
+        .and(InputError::check_range_usize(
+            reps.get() as usize,
+            2,
+            usize::MAX,
+        ))?;
+
+    let q: Vec<f64> = probabilities.iter().map(|&p| 1.0 - p).collect();
+    let s1mp = sum(&q);
+    let sak2 = q.iter().fold(0.0, |acc, &a| acc + a.powi(2)) / s1mp.powi(2);
+
+    let y_pi: Vec<f64> = y_values
+        .iter()
+        .zip(probabilities.iter())
+        .map(|(&y, &p)| y / p)
+        .collect();
+    let del_s1mp = y_pi
+        .iter()
+        .zip(q.iter())
+        .fold(0.0, |acc, (&a, &b)| acc + a * b)
+        / s1mp;
+    let scaled: Vec<f64> = y_pi
+        .iter()
+        .zip(q.iter())
+        .map(|(&a, &b)| (a - del_s1mp) * (b / (1.0 - sak2)).sqrt())
+        .collect();
+
+    // The centered estimate, sum of a (y/p - del), is offset from the HT estimate by a constant,
+    // which does not affect the variance
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let replicates: Vec<f64> = (0..reps.get())
+        .map(|_| {
+            scaled.iter().fold(
+                0.0,
+                |acc, &z| {
+                    if rng.gen::<bool>() {
+                        acc + z
+                    } else {
+                        acc - z
+                    }
+                },
+            )
+        })
+        .collect();
+
+    Ok(utils::variance(&replicates))
+}
+
+/// Deville estimator of variance of the [`ratio`] estimator of total.
+/// The ratio is linearized by the residuals `e = y - R x`, where `R` is the estimated ratio, and
+/// the variance is estimated by [`deville_variance`] of the residuals, scaled by `(X / X_ht)^2`,
+/// where `X` is `x_total` and `X_ht` is the Horvitz-Thompson estimate of `X`.
+/// Only first order probabilities are used.
+/// Returns an error if the lengths do not match, if `x_total` is negative, or if the estimate of
+/// `X` is zero.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::horvitz_thompson::ratio_variance_deville;
+///
+/// let y = [2.0, 4.0, 8.0];
+/// let x = [1.0, 2.0, 4.0];
+/// let pi = [0.4, 0.5, 0.6];
+///
+/// // y is proportional to x, thus the residuals are zero
+/// let v = ratio_variance_deville(&y, &x, &pi, 10.0).unwrap();
+/// assert!(v.abs() < 1e-12);
+/// ```
+pub fn ratio_variance_deville(
+    y_values: &[f64],
+    x_values: &[f64],
+    probabilities: &[f64],
+    x_total: f64,
+) -> Result<f64, SamplingError> {
+    InputError::check_lengths(x_values, y_values).and(InputError::check_range_f64(
+        x_total,
+        0.0,
+        f64::INFINITY,
+    ))?;
+    let x_estimate = estimate(x_values, probabilities)?;
+    InputError::check_valid_f64(x_estimate, 0.0)?;
+    let r = estimate(y_values, probabilities)? / x_estimate;
+
+    let residuals: Vec<f64> = y_values
+        .iter()
+        .zip(x_values.iter())
+        .map(|(&y, &x)| y - r * x)
+        .collect();
+
+    Ok(deville_variance(&residuals, probabilities)? * (x_total / x_estimate).powi(2))
+}
+
+/// Sen-Yates-Grundy estimator of variance of the ratio `R = Y / X` of two domain totals, e.g.
+/// the proportion of the total revenue that is in a region.
+/// The values are given for all sampled units, and are zero outside of the respective domains,
+/// e.g. `numerator_y` is the revenue of the units in the region, and `denominator_x` is the
+/// revenue of all units.
+/// The ratio is linearized by the residuals `e = (y - R x) / X`, where `R` and `X` are the
+/// Horvitz-Thompson estimates, and the variance is estimated by [`syg_variance`] of the
+/// residuals.
+/// Returns an error if the lengths of `numerator_y` and `denominator_x` do not match, if the
+/// estimate of `X` is zero, or as for [`syg_variance`].
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::horvitz_thompson::domain_ratio_variance;
+/// use envisim_utils::Matrix;
+///
+/// let y = [2.0, 0.0, 8.0];
+/// let x = [1.0, 2.0, 4.0];
+/// let p = [0.4, 0.5, 0.6];
+/// let m = Matrix::new(&[0.4, 0.18, 0.22, 0.18, 0.5, 0.32, 0.22, 0.32, 0.6], 3);
+/// let v = domain_ratio_variance(&y, &x, &p, &m)?;
+///
+/// assert!(v > 0.0);
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+pub fn domain_ratio_variance<M>(
+    numerator_y: &[f64],
+    denominator_x: &[f64],
+    probabilities: &[f64],
+    probabilities_second_order: &M,
+) -> Result<f64, SamplingError>
+where
+    M: MatrixLike + ?Sized,
+{
+    InputError::check_lengths(denominator_x, numerator_y)?;
+    let x_total = estimate(denominator_x, probabilities)?;
+    InputError::check_valid_f64(x_total, 0.0)?;
+    let r = estimate(numerator_y, probabilities)? / x_total;
+
+    let residuals: Vec<f64> = numerator_y
+        .iter()
+        .zip(denominator_x.iter())
+        .map(|(&y, &x)| (y - r * x) / x_total)
+        .collect();
+
+    syg_variance(&residuals, probabilities, probabilities_second_order)
+}
+
+/// Hartley-Rao estimator of variance of total estimate, for high entropy fixed size designs,
+/// e.g. randomized systematic pps sampling.
+/// Only first order probabilities are used.
+/// The population sum of squared probabilities is estimated by the sum of the sample
+/// probabilities.
+/// Returns an error if the sample contains fewer than two units.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::horvitz_thompson::hartley_rao_variance;
+///
+/// let y = [1.0, 2.0, 4.0];
+/// let pi = [0.4, 0.5, 0.6];
+/// let v = hartley_rao_variance(&y, &pi).unwrap();
+/// assert!((v - 6.4375).abs() < 1e-9);
+/// ```
+///
+/// # References
+/// Hartley, H. O., & Rao, J. N. K. (1962).
+/// Sampling with unequal probabilities and without replacement.
+/// The Annals of Mathematical Statistics, 33(2), 350-374.
+/// <https://doi.org/10.1214/aoms/1177704564>
+pub fn hartley_rao_variance(y_values: &[f64], probabilities: &[f64]) -> Result<f64, SamplingError> {
+    let sample_size = y_values.len();
+    InputError::check_lengths(y_values, probabilities)
+        .and(InputError::check_range_usize(sample_size, 2, usize::MAX))
+        .and(Probabilities::check(probabilities))?;
+
+    let n = usize_to_f64(sample_size);
+    let p2_mean = sum(probabilities) / n;
+    let mut variance: f64 = 0.0;
+
+    for i in 0..sample_size {
+        let y_pi = y_values[i] / probabilities[i];
+
+        for j in (i + 1)..sample_size {
+            variance += (1.0 - probabilities[i] - probabilities[j] + p2_mean)
+                * (y_pi - y_values[j] / probabilities[j]).powi(2);
+        }
+    }
+
+    Ok(variance / (n - 1.0))
+}
+
 /// Local mean estimator of variance of total estimate.
 ///
 /// # References
@@ -177,7 +916,7 @@ pub fn local_mean_variance(
 
     for i in 0..sample_size {
         searcher
-            .find_neighbours_of_iter(&tree, &mut auxilliaries.row_iter(i))
+            .find_neighbours_of_iter(&tree, auxilliaries.row_iter(i))
             .unwrap();
         let len = usize_to_f64(searcher.neighbours().len());
         variance += len / (len - 1.0)
@@ -191,3 +930,87 @@ pub fn local_mean_variance(
 
     Ok(variance)
 }
+
+/// A variance estimator of the Horvitz-Thompson estimator of a total, allowing code to be
+/// generic over the estimator, or to select the estimator at runtime, e.g. as a
+/// `Box<dyn VarianceEstimator>`.
+/// The implementations call the corresponding free functions of this module.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::horvitz_thompson::*;
+///
+/// let use_deville = true; // e.g. read from a config
+/// let estimator: Box<dyn VarianceEstimator> = if use_deville {
+///     Box::new(Deville)
+/// } else {
+///     Box::new(Hajek)
+/// };
+///
+/// let y = [1.0, 2.0, 4.0, 3.0];
+/// let p = [0.4, 0.5, 0.6, 0.5];
+/// assert_eq!(estimator.variance(&y, &p)?, deville_variance(&y, &p)?);
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+pub trait VarianceEstimator {
+    /// Returns the estimated variance, given the `y_values` and `probabilities` of the sampled
+    /// units
+    fn variance(&self, y_values: &[f64], probabilities: &[f64]) -> Result<f64, SamplingError>;
+}
+
+/// The Deville estimator, see [`deville_variance`]
+#[derive(Clone, Copy, Debug)]
+pub struct Deville;
+
+impl VarianceEstimator for Deville {
+    #[inline]
+    fn variance(&self, y_values: &[f64], probabilities: &[f64]) -> Result<f64, SamplingError> {
+        deville_variance(y_values, probabilities)
+    }
+}
+
+/// The Sen-Yates-Grundy estimator using the Hájek approximation of the second order
+/// probabilities, see [`hajek_second_order`]
+#[derive(Clone, Copy, Debug)]
+pub struct Hajek;
+
+impl VarianceEstimator for Hajek {
+    #[inline]
+    fn variance(&self, y_values: &[f64], probabilities: &[f64]) -> Result<f64, SamplingError> {
+        syg_variance(y_values, probabilities, &hajek_second_order(probabilities)?)
+    }
+}
+
+/// The Sen-Yates-Grundy estimator, with the second order probabilities of the sampled units,
+/// see [`syg_variance`]
+#[derive(Clone, Debug)]
+pub struct SenYatesGrundy<M: MatrixLike>(pub M);
+
+impl<M: MatrixLike> VarianceEstimator for SenYatesGrundy<M> {
+    #[inline]
+    fn variance(&self, y_values: &[f64], probabilities: &[f64]) -> Result<f64, SamplingError> {
+        syg_variance(y_values, probabilities, &self.0)
+    }
+}
+
+/// The local mean estimator, with the auxiliaries of the sampled units, see
+/// [`local_mean_variance`]
+#[derive(Clone, Copy, Debug)]
+pub struct LocalMean<'a> {
+    /// The auxiliaries of the sampled units
+    pub auxiliaries: &'a Matrix<'a>,
+    /// The number of neighbours used by [`local_mean_variance`]
+    pub n_neighbours: NonZeroUsize,
+}
+
+impl VarianceEstimator for LocalMean<'_> {
+    #[inline]
+    fn variance(&self, y_values: &[f64], probabilities: &[f64]) -> Result<f64, SamplingError> {
+        local_mean_variance(
+            y_values,
+            probabilities,
+            &TreeBuilder::new(self.auxiliaries),
+            self.n_neighbours,
+        )
+    }
+}
@@ -12,7 +12,26 @@
 
 //! Design-based estimators for with or without replacement designs.
 
+pub mod balanced;
+pub mod calibration;
+pub mod conditional;
+pub mod enumeration;
 pub mod hansen_hurwitz;
 pub mod horvitz_thompson;
 pub mod nearest_neighbour;
+pub mod optimal;
+pub mod post_stratification;
+pub mod regression;
+pub mod simulation;
 pub mod spatial_balance;
+pub mod systematic;
+pub mod weights;
+
+pub use conditional::conditional_probabilities;
+pub use enumeration::{enumerate_design, exact_variance};
+pub use horvitz_thompson::{generalized_bootstrap, hajek_second_order, weighted_covariance};
+pub use optimal::optimal_probabilities;
+pub use regression::two_phase_regression;
+pub use simulation::{estimate_second_order, monte_carlo_variance, simulate};
+pub use systematic::systematic_periodicity_warning;
+pub use weights::{effective_sample_size, trim_weights, weighted_histogram};
@@ -45,7 +45,7 @@ pub fn nearest_neighbour(
 
     for i in 0..population_size {
         searcher
-            .find_neighbours_of_iter(&tree, &mut auxilliaries.row_iter(i))
+            .find_neighbours_of_iter(&tree, auxilliaries.row_iter(i))
             .unwrap();
         let part = 1.0 / usize_to_f64(searcher.neighbours().len());
 
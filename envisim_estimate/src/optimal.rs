@@ -0,0 +1,67 @@
+// Copyright (C) 2024 Wilmer Prentius, Anton Grafström.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU Affero General Public License as published by the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Inclusion probabilities that are optimal under a superpopulation model
+
+use envisim_samplr::SamplingError;
+use envisim_utils::{InputError, Probabilities};
+
+/// Returns the inclusion probabilities, with expected sample size `target_n`, that minimize the
+/// anticipated variance of the Horvitz-Thompson estimator of the total, under a model where
+/// `y_proxy` are the anticipated values of the units, and `sizes` are the anticipated standard
+/// deviations of the units around their anticipated values.
+/// The anticipated variance, `sum (mu^2 + sigma^2) (1 / pi - 1)` for a poisson design, is
+/// minimized by probabilities proportional to `sqrt(mu^2 + sigma^2)`.
+/// Units whose probability would exceed `1.0` are given probability `1.0`, and the remaining
+/// sample size is redistributed, see [`Probabilities::from_size_fn`].
+/// Hence, if the standard deviations are zero, or proportional to the anticipated values, the
+/// probabilities are proportional to `|y_proxy|`.
+/// Returns an error if the lengths do not match, if any standard deviation is negative, if any
+/// value is not finite, or if `target_n` is zero or larger than the number of units with a
+/// positive probability.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::optimal_probabilities;
+///
+/// let y = [1.0, -2.0, 3.0, 4.0];
+/// let p = optimal_probabilities(&[0.0; 4], &y, 2)?;
+///
+/// assert!((p[1] - 0.4).abs() < 1e-12);
+/// assert!((p[3] - 0.8).abs() < 1e-12);
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+///
+/// # References
+/// Särndal, C.-E., Swensson, B., & Wretman, J. (1992).
+/// Model assisted survey sampling.
+/// Springer-Verlag.
+pub fn optimal_probabilities(
+    sizes: &[f64],
+    y_proxy: &[f64],
+    target_n: usize,
+) -> Result<Vec<f64>, SamplingError> {
+    InputError::check_lengths(sizes, y_proxy)?;
+    sizes.iter().try_for_each(|&s| {
+        InputError::check_nan(s).and(InputError::check_range_f64(s, 0.0, f64::MAX))
+    })?;
+    y_proxy.iter().try_for_each(|&y| {
+        InputError::check_nan(y).and(InputError::check_range_f64(y.abs(), 0.0, f64::MAX))
+    })?;
+
+    Ok(Probabilities::from_size_fn(
+        sizes.len(),
+        |i| y_proxy[i].hypot(sizes[i]),
+        target_n,
+        1e-12,
+    )?)
+}
@@ -0,0 +1,135 @@
+// Copyright (C) 2024 Wilmer Prentius, Anton Grafström.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU Affero General Public License as published by the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Post-stratification estimators
+
+use crate::horvitz_thompson::deville_variance;
+use envisim_samplr::SamplingError;
+use envisim_utils::{InputError, Probabilities};
+
+// Returns the estimated sizes and totals of the post-strata
+fn stratum_estimates(
+    y_values: &[f64],
+    probabilities: &[f64],
+    strata: &[usize],
+    strata_totals: &[f64],
+) -> Result<(Vec<f64>, Vec<f64>), SamplingError> {
+    InputError::check_lengths(y_values, probabilities)
+        .and(InputError::check_lengths(strata, probabilities))
+        .and(InputError::check_empty(strata_totals))
+        .and(Probabilities::check(probabilities))?;
+    probabilities
+        .iter()
+        .try_for_each(|&p| InputError::check_positive(p))?;
+    strata
+        .iter()
+        .try_for_each(|&h| InputError::check_range_usize(h, 0, strata_totals.len() - 1))?;
+    strata_totals.iter().try_for_each(|&t| {
+        InputError::check_nan(t).and(InputError::check_range_f64(t, 0.0, f64::MAX))
+    })?;
+
+    let mut sizes = vec![0.0; strata_totals.len()];
+    let mut totals = vec![0.0; strata_totals.len()];
+
+    for ((&y, &p), &h) in y_values.iter().zip(probabilities.iter()).zip(strata.iter()) {
+        sizes[h] += 1.0 / p;
+        totals[h] += y / p;
+    }
+
+    if let Some(h) = (0..sizes.len()).find(|&h| sizes[h] == 0.0 && strata_totals[h] > 0.0) {
+        return Err(InputError::Missing(format!("sample units in post-stratum {h}")).into());
+    }
+
+    Ok((sizes, totals))
+}
+
+/// Post-stratified estimator of a total, `sum N_h Y_h / N_h'`, where `N_h` is the known size of
+/// post-stratum `h`, and `Y_h`, `N_h'` are the Horvitz-Thompson estimates of the total and the
+/// size of post-stratum `h`.
+/// Element `i` of `strata` is the post-stratum of sampled unit `i`, indexing `strata_totals`,
+/// which holds the known population sizes of the post-strata.
+/// Returns an error if the lengths do not match, if any probability is not in `(0.0, 1.0]`, if
+/// any post-stratum is out of range, if any post-stratum size is negative, or if the sample
+/// holds no units of a post-stratum of positive size.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::post_stratification::estimate;
+///
+/// let y = [1.0, 2.0, 10.0];
+/// let p = [0.5, 0.25, 0.5];
+/// let strata = [0, 0, 1];
+///
+/// let est = estimate(&y, &p, &strata, &[5.0, 4.0]).unwrap();
+/// assert!((est - (5.0 * 10.0 / 6.0 + 40.0)).abs() < 1e-12);
+/// ```
+///
+/// # References
+/// Särndal, C.-E., Swensson, B., & Wretman, J. (1992).
+/// Model assisted survey sampling.
+/// Springer-Verlag.
+pub fn estimate(
+    y_values: &[f64],
+    probabilities: &[f64],
+    strata: &[usize],
+    strata_totals: &[f64],
+) -> Result<f64, SamplingError> {
+    let (sizes, totals) = stratum_estimates(y_values, probabilities, strata, strata_totals)?;
+
+    Ok((0..sizes.len())
+        .filter(|&h| sizes[h] > 0.0)
+        .fold(0.0, |acc, h| acc + strata_totals[h] * totals[h] / sizes[h]))
+}
+
+/// Approximate variance estimator of the post-stratified estimator [`estimate`].
+/// The post-stratified estimator is a generalized regression (GREG) estimator, with the
+/// post-stratum indicators as auxiliary variables, hence the variance is estimated by the
+/// variance estimator of a total of the g-weighted residuals, `g_i e_i`.
+/// The residual `e_i` is the deviation of `y_i` from the estimated mean of its post-stratum,
+/// `Y_h / N_h'`, and the g-weight is `g_i = N_h / N_h'`.
+/// The variance of the total of the residuals is estimated by
+/// [`crate::horvitz_thompson::deville_variance`], which is valid for high entropy fixed size
+/// designs.
+/// Returns an error under the same conditions as [`estimate`].
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::post_stratification::variance;
+///
+/// let y = [1.0, 2.0, 10.0, 12.0];
+/// let p = [0.5, 0.25, 0.5, 0.5];
+/// let strata = [0, 0, 1, 1];
+///
+/// let v = variance(&y, &p, &strata, &[5.0, 4.0]).unwrap();
+/// assert!(v > 0.0);
+/// ```
+///
+/// # References
+/// Särndal, C.-E., Swensson, B., & Wretman, J. (1992).
+/// Model assisted survey sampling.
+/// Springer-Verlag.
+pub fn variance(
+    y_values: &[f64],
+    probabilities: &[f64],
+    strata: &[usize],
+    strata_totals: &[f64],
+) -> Result<f64, SamplingError> {
+    let (sizes, totals) = stratum_estimates(y_values, probabilities, strata, strata_totals)?;
+
+    let residuals: Vec<f64> = y_values
+        .iter()
+        .zip(strata.iter())
+        .map(|(&y, &h)| strata_totals[h] / sizes[h] * (y - totals[h] / sizes[h]))
+        .collect();
+
+    deville_variance(&residuals, probabilities)
+}
@@ -0,0 +1,185 @@
+// Copyright (C) 2024 Wilmer Prentius, Anton Grafström.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU Affero General Public License as published by the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Regression estimators
+
+use envisim_samplr::SamplingError;
+use envisim_utils::{InputError, Matrix, Probabilities};
+
+/// The relative tolerance below which a pivot is considered zero in [`solve`]
+const SOLVE_EPS: f64 = 1e-10;
+
+/// Solves the system `a * x = b`, where `a` is a square matrix, by gaussian elimination with
+/// partial pivoting.
+/// Returns [`InputError::Singular`] if a pivot is smaller than [`SOLVE_EPS`] relative to the
+/// largest absolute value of its column in `a`, e.g. for (nearly) collinear auxiliaries.
+pub(crate) fn solve(a: &Matrix, b: &[f64]) -> Result<Vec<f64>, InputError> {
+    let dim = a.nrow();
+    let mut augmented = Matrix::from_value(0.0, (dim, dim + 1));
+
+    for i in 0..dim {
+        for j in 0..dim {
+            augmented[(i, j)] = a[(i, j)];
+        }
+        augmented[(i, dim)] = b[i];
+    }
+
+    for k in 0..dim {
+        let scale = (0..dim).fold(0.0f64, |acc, i| acc.max(a[(i, k)].abs()));
+        let pivot_row = (k..dim)
+            .max_by(|&i, &j| augmented[(i, k)].abs().total_cmp(&augmented[(j, k)].abs()))
+            .unwrap();
+
+        if augmented[(pivot_row, k)].abs() <= SOLVE_EPS * scale || scale == 0.0 {
+            return Err(InputError::Singular);
+        }
+
+        if pivot_row != k {
+            for j in k..=dim {
+                let tmp = augmented[(k, j)];
+                augmented[(k, j)] = augmented[(pivot_row, j)];
+                augmented[(pivot_row, j)] = tmp;
+            }
+        }
+
+        for i in (k + 1)..dim {
+            let factor = augmented[(i, k)] / augmented[(k, k)];
+            for j in k..=dim {
+                augmented[(i, j)] -= factor * augmented[(k, j)];
+            }
+        }
+    }
+
+    let mut x = vec![0.0; dim];
+
+    for i in (0..dim).rev() {
+        let rest: f64 = ((i + 1)..dim).map(|j| augmented[(i, j)] * x[j]).sum();
+        x[i] = (augmented[(i, dim)] - rest) / augmented[(i, i)];
+    }
+
+    Ok(x)
+}
+
+/// Two-phase generalized regression (GREG) estimator of the total of `y_values`.
+/// The first phase sample provides the auxiliary variables `x_values`, with one row per first
+/// phase unit, and the first phase inclusion probabilities `phase1_probabilities`.
+/// The second phase sample, `phase2_sample`, holds the row indices of the second phase units in
+/// `x_values`.
+/// The `y_values` and the (conditional) second phase inclusion probabilities
+/// `phase2_probabilities` are given for the second phase units, in the order of
+/// `phase2_sample`.
+///
+/// The regression coefficients are estimated on the second phase sample, using the weights
+/// `1 / (p1 * p2)`, and the estimator calibrates the second phase to the first phase
+/// Horvitz-Thompson estimates of the auxiliary totals.
+/// An intercept is not added automatically, but can be included as a column of ones in
+/// `x_values`.
+///
+/// Returns an error if the second phase sample is not a subset of the first phase sample, if
+/// the lengths do not match, if any probability is not in `(0.0, 1.0]`, or if the weighted
+/// gram matrix of the second phase sample is singular.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::regression::two_phase_regression;
+/// use envisim_utils::Matrix;
+///
+/// let x = Matrix::new(&[1.0, 1.0, 1.0, 1.0, 1.0, 2.0, 3.0, 4.0], 4);
+/// let p1 = [0.5; 4];
+/// let s2 = [0, 2];
+/// let y = [3.0, 7.0];
+/// let p2 = [0.5; 2];
+///
+/// // y = 1 + 2x holds exactly, hence the estimate equals sum (1 + 2x) / p1
+/// let est = two_phase_regression(&y, &x, &p1, &s2, &p2).unwrap();
+/// assert!((est - 48.0).abs() < 1e-9);
+/// ```
+///
+/// # References
+/// Särndal, C.-E., Swensson, B., & Wretman, J. (1992).
+/// Model assisted survey sampling.
+/// Springer-Verlag.
+pub fn two_phase_regression(
+    y_values: &[f64],
+    x_values: &Matrix,
+    phase1_probabilities: &[f64],
+    phase2_sample: &[usize],
+    phase2_probabilities: &[f64],
+) -> Result<f64, SamplingError> {
+    let phase1_size = x_values.nrow();
+    let n_vars = x_values.ncol();
+    InputError::check_empty(phase2_sample)
+        .and(InputError::check_sizes(
+            phase1_probabilities.len(),
+            phase1_size,
+        ))
+        .and(InputError::check_lengths(y_values, phase2_sample))
+        .and(InputError::check_lengths(
+            phase2_probabilities,
+            phase2_sample,
+        ))?;
+    Probabilities::check(phase1_probabilities).and(Probabilities::check(phase2_probabilities))?;
+    phase1_probabilities
+        .iter()
+        .chain(phase2_probabilities.iter())
+        .try_for_each(|&p| InputError::check_positive(p))?;
+
+    let mut in_phase2 = vec![false; phase1_size];
+    for &id in phase2_sample.iter() {
+        InputError::check_range_usize(id, 0, phase1_size.saturating_sub(1))?;
+        if in_phase2[id] {
+            return Err(SamplingError::Input(InputError::NotUnique));
+        }
+        in_phase2[id] = true;
+    }
+
+    // First phase HT estimate of the auxiliary totals
+    let mut x_total_1 = vec![0.0; n_vars];
+    for (id, &p1) in phase1_probabilities.iter().enumerate() {
+        for (t, x) in x_total_1.iter_mut().zip(x_values.row_iter(id)) {
+            *t += x / p1;
+        }
+    }
+
+    // Second phase weighted estimates
+    let mut x_total_2 = vec![0.0; n_vars];
+    let mut y_total_2 = 0.0;
+    let mut xy = vec![0.0; n_vars];
+    let mut gram = Matrix::from_value(0.0, (n_vars, n_vars));
+
+    for ((&id, &y), &p2) in phase2_sample
+        .iter()
+        .zip(y_values.iter())
+        .zip(phase2_probabilities.iter())
+    {
+        let w = 1.0 / (phase1_probabilities[id] * p2);
+        y_total_2 += w * y;
+
+        for i in 0..n_vars {
+            let xi = x_values[(id, i)];
+            x_total_2[i] += w * xi;
+            xy[i] += w * xi * y;
+
+            for j in 0..n_vars {
+                gram[(i, j)] += w * xi * x_values[(id, j)];
+            }
+        }
+    }
+
+    let beta = solve(&gram, &xy)?;
+
+    Ok(x_total_1
+        .iter()
+        .zip(x_total_2.iter())
+        .zip(beta.iter())
+        .fold(y_total_2, |acc, ((t1, t2), b)| acc + (t1 - t2) * b))
+}
@@ -0,0 +1,341 @@
+// Copyright (C) 2024 Wilmer Prentius, Anton Grafström.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU Affero General Public License as published by the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Simulation of design-based surveys
+
+use crate::horvitz_thompson;
+use envisim_samplr::{derive_rng, AntitheticRng, ProgressEvent, SampleOptions, SamplingError};
+use envisim_utils::pips::pips_from_slice;
+use envisim_utils::utils::{sum, variance};
+use envisim_utils::{InputError, SymMatrix};
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use std::num::NonZeroU32;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// The result of a simulated survey, see [`simulate`].
+/// Implements `Serialize` and `Deserialize` with the `serde` feature.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SimulationResult {
+    /// The indices of the sampled units
+    pub sample: Vec<usize>,
+    /// The inclusion probabilities of all units in the population
+    pub probabilities: Vec<f64>,
+    /// The Horvitz-Thompson estimate of the total
+    pub estimate: f64,
+    /// The Deville estimate of the variance of the estimated total
+    pub variance: f64,
+    /// The true population total
+    pub total: f64,
+    /// The relative error of the estimate, `(estimate - total) / total`.
+    /// Is `NaN` or infinite if `total` is `0.0`.
+    pub relative_error: f64,
+}
+
+/// Simulates a design-based survey of a population with values `y_values`.
+/// Draws a sample of size `n` using `design`, with inclusion probabilities proportional to
+/// `sizes`, using a [`SmallRng`] seeded by `seed`.
+/// The sample is used to estimate the population total by the Horvitz-Thompson estimator, and its
+/// variance by the Deville estimator.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::simulation::simulate;
+/// use envisim_samplr::unequal::pareto;
+///
+/// let y = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0];
+/// let sizes = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0];
+/// let result = simulate(&y, &sizes, 3, pareto, 4242)?;
+///
+/// assert_eq!(result.sample.len(), 3);
+/// assert_eq!(result.total, 55.0);
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+pub fn simulate<F>(
+    y_values: &[f64],
+    sizes: &[f64],
+    n: usize,
+    design: F,
+    seed: u64,
+) -> Result<SimulationResult, SamplingError>
+where
+    F: Fn(&mut SmallRng, &SampleOptions) -> Result<Vec<usize>, SamplingError>,
+{
+    InputError::check_lengths(y_values, sizes)?;
+    let probabilities = pips_from_slice(sizes, n)?.data().to_vec();
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let sample = design(&mut rng, &SampleOptions::new(&probabilities)?)?;
+
+    let y_sample: Vec<f64> = sample.iter().map(|&id| y_values[id]).collect();
+    let p_sample: Vec<f64> = sample.iter().map(|&id| probabilities[id]).collect();
+    let estimate = horvitz_thompson::estimate(&y_sample, &p_sample)?;
+    let variance = horvitz_thompson::deville_variance(&y_sample, &p_sample)?;
+    let total = sum(y_values);
+
+    Ok(SimulationResult {
+        sample,
+        probabilities,
+        estimate,
+        variance,
+        total,
+        relative_error: (estimate - total) / total,
+    })
+}
+
+/// Returns the Monte Carlo variance of the Horvitz-Thompson estimator of the total of
+/// `y_population` under `design`.
+/// Draws `reps` samples from the full population using `design` with `options`, and a
+/// [`SmallRng`] seeded by `seed`, and returns the empirical variance of the estimated totals.
+/// As this requires the values of the full population, it is meant for evaluation, e.g. of
+/// analytic variance estimators or of designs lacking them, and not for variance estimation in a
+/// survey.
+/// Reports a [`ProgressEvent::Replicate`] for each replicate, if a progress hook is set.
+/// Returns an error if `y_population` and the probabilities of `options` have different lengths,
+/// or if `reps` is less than 2.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::simulation::monte_carlo_variance;
+/// use envisim_samplr::pivotal_method::spm;
+/// use envisim_samplr::SampleOptions;
+/// use std::num::NonZeroU32;
+///
+/// let y = [1.0, 2.0, 3.0, 4.0];
+/// let p = [0.5; 4];
+/// let reps = NonZeroU32::new(1000).unwrap();
+/// let v = monte_carlo_variance(&y, spm, &SampleOptions::new(&p)?, reps, 4242)?;
+///
+/// assert!(v > 0.0);
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+pub fn monte_carlo_variance<F>(
+    y_population: &[f64],
+    design: F,
+    options: &SampleOptions,
+    reps: NonZeroU32,
+    seed: u64,
+) -> Result<f64, SamplingError>
+where
+    F: Fn(&mut SmallRng, &SampleOptions) -> Result<Vec<usize>, SamplingError>,
+{
+    let probabilities = options.probabilities();
+    InputError::check_lengths(y_population, probabilities)?;
+    InputError::check_range_usize(reps.get() as usize, 2, usize::MAX)?;
+
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let totals = (1..=reps.get())
+        .map(|replicate| {
+            let sample = design(&mut rng, options)?;
+            let y_sample: Vec<f64> = sample.iter().map(|&id| y_population[id]).collect();
+            let p_sample: Vec<f64> = sample.iter().map(|&id| probabilities[id]).collect();
+            let total = horvitz_thompson::estimate(&y_sample, &p_sample);
+            options.report(ProgressEvent::Replicate { replicate, reps });
+            total
+        })
+        .collect::<Result<Vec<f64>, SamplingError>>()?;
+
+    Ok(variance(&totals))
+}
+
+/// The random numbers driving the repetitions of a simulation, see [`monte_carlo_mean`].
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Variates {
+    /// Each repetition uses new random numbers
+    Independent,
+    /// Pairs of repetitions use antithetic random numbers, see [`AntitheticRng`]
+    Antithetic,
+}
+
+/// Returns the Monte Carlo mean of the `statistic` of the samples of `design`.
+/// Draws `reps` samples using `design` with `options`, driven by an [`AntitheticRng`] wrapping a
+/// [`SmallRng`] seeded by `seed`.
+/// With [`Variates::Antithetic`], consecutive repetitions form antithetic pairs, which reduces
+/// the simulation error for designs consuming their uniforms in a predictable order, e.g.
+/// [`envisim_samplr::poisson::sample`], [`envisim_samplr::systematic::sample`] and
+/// [`envisim_samplr::unequal::pareto`], and statistics that are monotone in the sample.
+/// Reports a [`ProgressEvent::Replicate`] for each replicate, if a progress hook is set.
+/// Returns an error if `reps` is odd when using antithetic variates.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::horvitz_thompson::estimate;
+/// use envisim_estimate::simulation::{monte_carlo_mean, Variates};
+/// use envisim_samplr::poisson::sample;
+/// use envisim_samplr::SampleOptions;
+/// use std::num::NonZeroU32;
+///
+/// let y = [1.0, 2.0, 3.0, 4.0];
+/// let p = [0.2, 0.4, 0.6, 0.8];
+/// let total = |s: &[usize]| {
+///     let ys: Vec<f64> = s.iter().map(|&i| y[i]).collect();
+///     let ps: Vec<f64> = s.iter().map(|&i| p[i]).collect();
+///     estimate(&ys, &ps)
+/// };
+/// let options = SampleOptions::new(&p)?;
+/// let reps = NonZeroU32::new(1000).unwrap();
+/// let mean = monte_carlo_mean(sample, &options, total, reps, 4242, Variates::Antithetic)?;
+///
+/// assert!((mean - 10.0).abs() < 1.0);
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+pub fn monte_carlo_mean<F, G>(
+    design: F,
+    options: &SampleOptions,
+    statistic: G,
+    reps: NonZeroU32,
+    seed: u64,
+    variates: Variates,
+) -> Result<f64, SamplingError>
+where
+    F: Fn(&mut AntitheticRng<SmallRng>, &SampleOptions) -> Result<Vec<usize>, SamplingError>,
+    G: Fn(&[usize]) -> Result<f64, SamplingError>,
+{
+    if variates == Variates::Antithetic && reps.get() % 2 == 1 {
+        return Err(InputError::NotEven(reps.get() as usize).into());
+    }
+
+    let mut rng = AntitheticRng::new(SmallRng::seed_from_u64(seed));
+    let mut total: f64 = 0.0;
+
+    for rep in 0..reps.get() {
+        if variates == Variates::Antithetic && rep % 2 == 1 {
+            rng.replay();
+        } else {
+            rng.new_pair();
+        }
+
+        total += statistic(&design(&mut rng, options)?)?;
+        options.report(ProgressEvent::Replicate {
+            replicate: rep + 1,
+            reps,
+        });
+    }
+
+    Ok(total / f64::from(reps.get()))
+}
+
+/// The number of independent rng streams used by [`estimate_second_order`]
+const SECOND_ORDER_STREAMS: u32 = 64;
+
+/// Returns the empirical second order inclusion probabilities of `design`, as the relative
+/// frequencies of the pairs of units in `reps` samples.
+/// The diagonal holds the empirical first order inclusion probabilities.
+/// The returned matrix refers to the whole population, and the rows and columns of the sampled
+/// units can be used by e.g. [`horvitz_thompson::syg_variance`], for designs lacking closed form
+/// second order probabilities.
+///
+/// The repetitions are split into 64 streams, each using a [`SmallRng`] derived from `seed` as in
+/// [`envisim_samplr::derive_rng`].
+/// With the `rayon` feature, the streams are drawn in parallel, and the result is identical to
+/// that of the sequential version.
+/// Reports a [`ProgressEvent::Replicate`] for each replicate, or for each stream with the `rayon`
+/// feature, if a progress hook is set.
+///
+/// **Note:** the accuracy depends on `reps`, and pairs with small joint probabilities may not be
+/// observed at all.
+/// For small `reps`, the matrix may thus violate the conditions of the SYG estimator, e.g. by
+/// zero joint probabilities.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::estimate_second_order;
+/// use envisim_samplr::unequal::brewer;
+/// use envisim_samplr::SampleOptions;
+/// use std::num::NonZeroU32;
+///
+/// let p = [0.2, 0.4, 0.6, 0.8];
+/// let reps = NonZeroU32::new(10000).unwrap();
+/// let pi2 = estimate_second_order(brewer, &SampleOptions::new(&p)?, reps, 4242)?;
+///
+/// assert!((pi2[(3, 3)] - 0.8).abs() < 0.05);
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+pub fn estimate_second_order<F>(
+    design: F,
+    options: &SampleOptions,
+    reps: NonZeroU32,
+    seed: u64,
+) -> Result<SymMatrix, SamplingError>
+where
+    F: Fn(&mut SmallRng, &SampleOptions) -> Result<Vec<usize>, SamplingError> + Sync,
+{
+    let population_size = options.probabilities().len();
+    // The pairs (i, j), with i <= j, are counted in the packed upper triangular layout of
+    // SymMatrix
+    let n_pairs = population_size * (population_size + 1) / 2;
+    let packed = |lo: usize, hi: usize| hi * (hi + 1) / 2 + lo;
+    let completed = AtomicU32::new(0);
+    let report = |replicates: u32| {
+        let replicate = completed.fetch_add(replicates, Ordering::Relaxed) + replicates;
+        options.report(ProgressEvent::Replicate { replicate, reps });
+    };
+
+    // Adds the pair counts of the samples of stream `stream_id` to `counts`
+    let stream = |counts: &mut [u32], stream_id: u32| -> Result<(), SamplingError> {
+        let mut rng = derive_rng(seed, u64::from(stream_id));
+        let stream_reps = reps.get() / SECOND_ORDER_STREAMS
+            + u32::from(stream_id < reps.get() % SECOND_ORDER_STREAMS);
+
+        for _ in 0..stream_reps {
+            let mut sample = design(&mut rng, options)?;
+            sample.sort_unstable();
+            for (b, &j) in sample.iter().enumerate() {
+                for &i in sample[..=b].iter() {
+                    counts[packed(i, j)] += 1;
+                }
+            }
+            #[cfg(not(feature = "rayon"))]
+            report(1);
+        }
+
+        // In parallel, the replicates are reported per stream
+        #[cfg(feature = "rayon")]
+        report(stream_reps);
+        Ok(())
+    };
+
+    // The streams are accumulated into one matrix per thread, rather than one per stream
+    #[cfg(feature = "rayon")]
+    let counts: Vec<u32> = {
+        use rayon::prelude::*;
+        (0..SECOND_ORDER_STREAMS)
+            .into_par_iter()
+            .try_fold(
+                || vec![0u32; n_pairs],
+                |mut counts, stream_id| stream(&mut counts, stream_id).map(|_| counts),
+            )
+            .try_reduce(
+                || vec![0u32; n_pairs],
+                |mut a, b| {
+                    a.iter_mut().zip(b.iter()).for_each(|(x, &y)| *x += y);
+                    Ok(a)
+                },
+            )?
+    };
+    #[cfg(not(feature = "rayon"))]
+    let counts: Vec<u32> = {
+        let mut counts = vec![0u32; n_pairs];
+        (0..SECOND_ORDER_STREAMS).try_for_each(|stream_id| stream(&mut counts, stream_id))?;
+        counts
+    };
+
+    let mut result = SymMatrix::from_value(0.0, population_size);
+    for j in 0..population_size {
+        for i in 0..=j {
+            result[(i, j)] = f64::from(counts[packed(i, j)]) / f64::from(reps.get());
+        }
+    }
+
+    Ok(result)
+}
@@ -64,7 +64,7 @@ pub fn voronoi(
 
     for (i, &p) in probabilities.iter().enumerate() {
         searcher
-            .find_neighbours_of_iter(&tree, &mut data.row_iter(i))
+            .find_neighbours_of_iter(&tree, data.row_iter(i))
             .unwrap();
         let partial_prob = p / usize_to_f64(searcher.neighbours().len());
         searcher.neighbours().iter().for_each(|&s| {
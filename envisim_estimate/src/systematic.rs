@@ -0,0 +1,79 @@
+// Copyright (C) 2024 Wilmer Prentius, Anton Grafström.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU Affero General Public License as published by the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Diagnostics for systematic sampling
+
+use envisim_utils::utils::usize_to_f64;
+
+/// Detects a periodic ordering of the frame, with a period matching the sampling interval of a
+/// systematic sample of size `n`.
+/// Such an ordering makes the systematic design select similar units, and may yield a very large
+/// variance.
+///
+/// With the sampling interval `k = N / n`, the autocorrelation `r_j` of the sizes in frame order
+/// is computed for the lags `j = 1, ..., k`.
+/// A risk is detected if `r_k` is significantly positive, i.e. larger than `2 / sqrt(N - k)`, and
+/// larger than the mean autocorrelation at the shorter lags `1, ..., k - 1`.
+/// The latter condition separates periodicity from e.g. a trend, under which systematic sampling
+/// is efficient.
+/// Returns the risk score `r_k - mean(r_1, ..., r_{k-1})` if a risk is detected, and `None`
+/// otherwise, or if the interval is shorter than 2 or longer than `N / 2`, or if the sizes are
+/// constant.
+/// Takes `O(N^2 / n)` time.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::systematic_periodicity_warning;
+///
+/// // A frame with period 4, sampled with interval 4
+/// let sizes: Vec<f64> = (0..40).map(|i| [1.0, 2.0, 3.0, 8.0][i % 4]).collect();
+///
+/// assert!(systematic_periodicity_warning(&sizes, 10).is_some());
+/// assert!(systematic_periodicity_warning(&sizes, 8).is_none());
+/// ```
+pub fn systematic_periodicity_warning(sizes: &[f64], n: usize) -> Option<f64> {
+    let population_size = sizes.len();
+
+    if n == 0 {
+        return None;
+    }
+
+    let interval = population_size / n;
+
+    if interval < 2 || population_size < 2 * interval {
+        return None;
+    }
+
+    let mean = sizes.iter().sum::<f64>() / usize_to_f64(population_size);
+    let deviations: Vec<f64> = sizes.iter().map(|&y| y - mean).collect();
+    let variance: f64 = deviations.iter().map(|d| d * d).sum();
+
+    if variance <= 0.0 || !variance.is_finite() {
+        return None;
+    }
+
+    let autocorrelation = |lag: usize| -> f64 {
+        deviations
+            .iter()
+            .zip(deviations[lag..].iter())
+            .map(|(a, b)| a * b)
+            .sum::<f64>()
+            / variance
+    };
+
+    let at_interval = autocorrelation(interval);
+    let shorter = (1..interval).map(autocorrelation).sum::<f64>() / usize_to_f64(interval - 1);
+    let score = at_interval - shorter;
+    let bound = 2.0 / usize_to_f64(population_size - interval).sqrt();
+
+    (at_interval > bound && score > 0.0).then_some(score)
+}
@@ -0,0 +1,175 @@
+// Copyright (C) 2024 Wilmer Prentius, Anton Grafström.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU Affero General Public License as published by the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Summaries and adjustments of design weights
+
+use envisim_samplr::SamplingError;
+use envisim_utils::utils::usize_to_f64;
+use envisim_utils::{InputError, Probabilities};
+
+#[inline]
+fn weights_check(probabilities: &[f64]) -> Result<(), InputError> {
+    InputError::check_empty(probabilities)
+        .and(Probabilities::check(probabilities))
+        .and(
+            probabilities
+                .iter()
+                .try_for_each(|&p| InputError::check_positive(p)),
+        )
+}
+
+/// Kish's effective sample size, `(sum w)^2 / sum w^2`, where the design weights `w` are the
+/// inverses of the inclusion `probabilities` of the sampled units.
+/// Returns an error if the slice is empty, or if any probability is not in `(0.0, 1.0]`.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::weights::effective_sample_size;
+///
+/// let pi = [0.2, 0.2, 0.4, 0.4];
+/// let n_eff = effective_sample_size(&pi).unwrap(); // 3.6
+/// assert!((n_eff - 3.6).abs() < 1e-12);
+/// ```
+///
+/// # References
+/// Kish, L. (1965).
+/// Survey sampling.
+/// John Wiley & Sons.
+pub fn effective_sample_size(probabilities: &[f64]) -> Result<f64, SamplingError> {
+    weights_check(probabilities)?;
+
+    let (wsum, w2sum) = probabilities
+        .iter()
+        .fold((0.0, 0.0), |(a, b), &p| (a + 1.0 / p, b + 1.0 / (p * p)));
+
+    Ok(wsum * wsum / w2sum)
+}
+
+/// Trims the design weights, the inverses of the inclusion `probabilities`, at `max_weight`.
+/// The trimmed weight mass is redistributed proportionally to the untrimmed weights, such that
+/// the sum of the weights (the estimated population size) is preserved.
+/// Trimming and redistribution is repeated until no weight exceeds `max_weight`.
+/// Returns the adjusted probabilities (the inverses of the adjusted weights), and the total
+/// trimmed weight mass.
+/// Returns an error if the slice is empty, if any probability is not in `(0.0, 1.0]`, or if
+/// `max_weight` is smaller than the mean weight, in which case the sum cannot be preserved.
+///
+/// **Note:** trimming introduces bias into estimators using the adjusted probabilities, in
+/// exchange for a (hopefully) reduced variance.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::weights::trim_weights;
+///
+/// let pi = [0.05, 0.5, 0.5, 0.5];
+/// let (adj, trimmed) = trim_weights(&pi, 10.0).unwrap();
+/// assert!((adj[0] - 0.1).abs() < 1e-12);
+/// assert!((trimmed - 10.0).abs() < 1e-12);
+/// assert!((adj.iter().map(|p| 1.0 / p).sum::<f64>() - 26.0).abs() < 1e-12);
+/// ```
+pub fn trim_weights(
+    probabilities: &[f64],
+    max_weight: f64,
+) -> Result<(Vec<f64>, f64), SamplingError> {
+    weights_check(probabilities)?;
+    InputError::check_nan(max_weight)?;
+
+    let mut weights: Vec<f64> = probabilities.iter().map(|&p| 1.0 / p).collect();
+    let total: f64 = weights.iter().sum();
+    let mean_weight = total / usize_to_f64(weights.len());
+    InputError::check_range_f64(max_weight, mean_weight, f64::INFINITY)?;
+
+    let mut trimmed_mass: f64 = 0.0;
+
+    loop {
+        let excess: f64 = weights
+            .iter_mut()
+            .filter(|w| **w > max_weight)
+            .fold(0.0, |acc, w| {
+                let e = *w - max_weight;
+                *w = max_weight;
+                acc + e
+            });
+
+        if excess <= 0.0 {
+            break;
+        }
+
+        trimmed_mass += excess;
+        let untrimmed: f64 = weights.iter().filter(|&&w| w < max_weight).sum();
+
+        if untrimmed <= 0.0 {
+            break;
+        }
+
+        let factor = 1.0 + excess / untrimmed;
+        weights
+            .iter_mut()
+            .filter(|w| **w < max_weight)
+            .for_each(|w| *w *= factor);
+    }
+
+    Ok((weights.iter().map(|&w| 1.0 / w).collect(), trimmed_mass))
+}
+
+/// Design-weighted histogram, the Horvitz-Thompson estimates of the number of population units
+/// in each bin defined by `breaks`.
+/// With `m + 1` breaks, bin `k` is the interval `[breaks[k], breaks[k + 1])`, except for the last
+/// bin, which also includes its upper break.
+/// Sampled units outside of the breaks are not counted.
+/// Returns an error if the lengths do not match, if any probability is not in `(0.0, 1.0]`, or if
+/// the breaks are fewer than two, or not strictly increasing.
+///
+/// # Examples
+/// ```
+/// use envisim_estimate::weighted_histogram;
+///
+/// let y = [0.5, 1.5, 1.2, 2.0];
+/// let pi = [0.5, 0.25, 0.2, 0.5];
+///
+/// let counts = weighted_histogram(&y, &pi, &[0.0, 1.0, 2.0]).unwrap();
+/// assert_eq!(counts, vec![2.0, 11.0]);
+/// ```
+pub fn weighted_histogram(
+    y_values: &[f64],
+    probabilities: &[f64],
+    breaks: &[f64],
+) -> Result<Vec<f64>, SamplingError> {
+    InputError::check_lengths(y_values, probabilities)
+        .and(Probabilities::check(probabilities))
+        .and(
+            probabilities
+                .iter()
+                .try_for_each(|&p| InputError::check_positive(p)),
+        )
+        .and(InputError::check_range_usize(breaks.len(), 2, usize::MAX))?;
+    breaks.iter().try_for_each(|&b| InputError::check_nan(b))?;
+    breaks.windows(2).try_for_each(|w| {
+        InputError::check_range_f64(w[1], w[0], f64::INFINITY)
+            .and(InputError::check_valid_f64(w[1], w[0]))
+    })?;
+
+    let n_bins = breaks.len() - 1;
+    let mut counts = vec![0.0; n_bins];
+
+    for (&y, &p) in y_values.iter().zip(probabilities.iter()) {
+        if y < breaks[0] || breaks[n_bins] < y || y.is_nan() {
+            continue;
+        }
+
+        // The number of breaks smaller than or equal to y, which is at least 1
+        let bin = breaks.partition_point(|&b| b <= y).min(n_bins) - 1;
+        counts[bin] += 1.0 / p;
+    }
+
+    Ok(counts)
+}
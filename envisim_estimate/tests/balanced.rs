@@ -0,0 +1,69 @@
+use envisim_estimate::balanced::*;
+use envisim_samplr::cube_method::cube;
+use envisim_samplr::{SampleOptions, SamplingError};
+use envisim_test_utils::*;
+use envisim_utils::{InputError, Matrix};
+
+#[test]
+fn test_balance_report() -> Result<(), SamplingError> {
+    // Balancing on the probabilities gives a fixed size
+    let p = PROB_10_U;
+    let x: Vec<f64> = (0..10).map(|i| f64::from(i) * 2.0 + 1.0).collect();
+    let balancing = Matrix::from_vec(p.iter().chain(x.iter()).copied().collect(), 10);
+    let totals = [5.0, x.iter().sum()];
+    let mut options = SampleOptions::new(&p)?;
+    options.balancing(&balancing)?;
+    let mut rng = seeded_rng();
+
+    for _ in 0..100 {
+        let s = cube(&mut rng, &options)?;
+        let report = balance_report(&s, &p, &balancing, &totals)?;
+        assert_delta!(report.estimates[0], 5.0, 1e-9);
+        assert_delta!(report.relative_imbalances[0], 0.0, 1e-9);
+        assert!(report.max_relative_imbalance() < 0.1);
+    }
+
+    let report = balance_report(&[0, 1], &[0.5; 4], &Matrix::new(&[1.0; 8], 4), &[4.0, 2.0])?;
+    assert_eq!(report.relative_imbalances, vec![0.0, 1.0]);
+    assert_eq!(report.max_relative_imbalance(), 1.0);
+    assert_eq!(report.imbalanced(0.1), vec![1]);
+
+    balance_report(&[0, 4], &[0.5; 4], &Matrix::new(&[1.0; 8], 4), &[4.0, 2.0]).unwrap_err();
+    balance_report(&[0, 1], &[0.5; 3], &Matrix::new(&[1.0; 8], 4), &[4.0, 2.0]).unwrap_err();
+    balance_report(&[0, 1], &[0.5; 4], &Matrix::new(&[1.0; 8], 4), &[4.0]).unwrap_err();
+    assert!(matches!(
+        balance_report(&[], &[], &Matrix::new(&[1.0], 1), &[1.0]),
+        Err(SamplingError::Input(InputError::IsEmpty))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_balancing_matrix() -> Result<(), SamplingError> {
+    let p = PROB_10_U;
+    let x = Matrix::new(&DATA_10_2, 10);
+    let balancing = balancing_matrix(&p, &x)?;
+
+    assert_eq!(balancing.dim(), (10, 3));
+    assert_fvec(&balancing.data()[0..10], &p);
+    assert_fvec(&balancing.data()[10..30], &DATA_10_2);
+
+    // The balancing matrix gives samples of fixed size
+    let mut options = SampleOptions::new(&p)?;
+    options.balancing(&balancing)?;
+    let mut rng = seeded_rng();
+
+    for _ in 0..100 {
+        assert_eq!(cube(&mut rng, &options)?.len(), 5);
+    }
+
+    assert!(matches!(
+        balancing_matrix(&p[1..], &x),
+        Err(SamplingError::Input(_))
+    ));
+    assert!(matches!(
+        balancing_matrix(&[], &x),
+        Err(SamplingError::Input(_))
+    ));
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use envisim_estimate::calibration::*;
+use envisim_samplr::SamplingError;
+use envisim_test_utils::*;
+use envisim_utils::Matrix;
+use std::num::NonZeroUsize;
+
+#[test]
+fn bounded_calibration() -> Result<(), SamplingError> {
+    let n = 10;
+    let x = Matrix::from_vec([vec![1.0; n], DATA_10_2[0..n].to_vec()].concat(), n);
+    let p = PROB_10_E;
+    let totals = [45.0, 26.0];
+    let max_iterations = NonZeroUsize::new(100).unwrap();
+
+    for (lower, upper) in [(0.0, 10.0), (0.5, 2.0), (0.8, 1.5)] {
+        let w = calibrate(&x, &p, &totals, lower, upper, max_iterations)?;
+
+        // The calibration equations hold
+        assert_delta!(w.iter().sum::<f64>(), totals[0], 1e-8);
+        assert_delta!(
+            w.iter()
+                .zip(DATA_10_2.iter())
+                .map(|(&w, &x)| w * x)
+                .sum::<f64>(),
+            totals[1],
+            1e-8
+        );
+
+        // The weights lie within the bounds
+        for (&w, &pi) in w.iter().zip(p.iter()) {
+            assert!(lower / pi <= w && w <= upper / pi);
+        }
+    }
+
+    // Calibrating on the Horvitz-Thompson estimates leaves the weights unchanged
+    let ht_totals = [
+        p.iter().map(|&pi| 1.0 / pi).sum::<f64>(),
+        p.iter().zip(DATA_10_2.iter()).map(|(&pi, &x)| x / pi).sum(),
+    ];
+    let w = calibrate(&x, &p, &ht_totals, 0.5, 2.0, max_iterations)?;
+    assert_fvec(&w, &p.iter().map(|&pi| 1.0 / pi).collect::<Vec<f64>>());
+
+    // No weights within the bounds satisfy the equations
+    assert!(matches!(
+        calibrate(&x, &p, &[100.0, 12.0], 0.8, 1.2, max_iterations),
+        Err(SamplingError::MaxIterations(_))
+    ));
+
+    calibrate(&x, &p, &totals, 1.0, 2.0, max_iterations).unwrap_err();
+    calibrate(&x, &p, &totals, 0.5, 1.0, max_iterations).unwrap_err();
+    calibrate(&x, &p[1..], &totals, 0.5, 2.0, max_iterations).unwrap_err();
+    calibrate(&x, &p, &totals[1..], 0.5, 2.0, max_iterations).unwrap_err();
+    Ok(())
+}
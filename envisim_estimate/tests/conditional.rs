@@ -0,0 +1,50 @@
+use envisim_estimate::conditional_probabilities;
+use envisim_estimate::enumeration::enumerate_poisson;
+use envisim_samplr::{SampleOptions, SamplingError};
+use envisim_test_utils::*;
+
+// Enumerates the conditional poisson design of size n, conditional on the selected units
+fn enumerated(p: &[f64], selected: &[usize], n: usize) -> Result<Vec<f64>, SamplingError> {
+    let samples = enumerate_poisson(&SampleOptions::new(p)?, 10)?;
+    let mut result = vec![0.0; p.len()];
+    let mut total = 0.0;
+
+    for (s, prob) in samples.iter() {
+        if s.len() == n && selected.iter().all(|id| s.contains(id)) {
+            total += prob;
+            s.iter().for_each(|&id| result[id] += prob);
+        }
+    }
+
+    Ok(result.iter().map(|&r| r / total).collect())
+}
+
+#[test]
+fn enumerated_conditionals() -> Result<(), SamplingError> {
+    let p = [0.2, 0.25, 0.35, 0.4, 0.5, 0.5, 0.55, 0.65, 0.6];
+    let options = SampleOptions::new(&p)?;
+
+    for selected in [vec![], vec![3], vec![0, 7], vec![1, 2, 8]] {
+        let q = conditional_probabilities(&options, &selected)?;
+        assert_fvec(&q, &enumerated(&p, &selected, 4)?);
+    }
+
+    // Units with probability zero or one
+    let p = [0.0, 1.0, 0.3, 0.5, 0.7, 0.5];
+    let options = SampleOptions::new(&p)?;
+    let q = conditional_probabilities(&options, &[4])?;
+    assert_fvec(&q, &enumerated(&p, &[4], 3)?);
+    assert_eq!(q[0], 0.0);
+    assert_eq!(q[1], 1.0);
+
+    // All units selected
+    let q = conditional_probabilities(&options, &[2, 4])?;
+    assert_fvec(&q, &[0.0, 1.0, 1.0, 0.0, 1.0, 0.0]);
+
+    conditional_probabilities(&options, &[6]).unwrap_err();
+    conditional_probabilities(&options, &[2, 2]).unwrap_err();
+    conditional_probabilities(&options, &[0]).unwrap_err();
+    conditional_probabilities(&options, &[2, 3, 4]).unwrap_err();
+    conditional_probabilities(&SampleOptions::new(&[0.5, 0.6])?, &[]).unwrap_err();
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use envisim_estimate::enumeration::*;
+use envisim_samplr::unequal::brewer;
+use envisim_samplr::SampleOptions;
+use envisim_test_utils::*;
+use std::num::NonZeroU32;
+
+fn inclusion_probabilities(samples: &[(Vec<usize>, f64)], population_size: usize) -> Vec<f64> {
+    let mut pi = vec![0.0; population_size];
+    for (s, prob) in samples.iter() {
+        s.iter().for_each(|&id| pi[id] += prob);
+    }
+    pi
+}
+
+#[test]
+fn poisson() {
+    let options = SampleOptions::new(&PROB_10_U).unwrap();
+    let samples = enumerate_poisson(&options, 10).unwrap();
+
+    assert_eq!(samples.len(), 1 << 10);
+    assert_delta!(samples.iter().map(|s| s.1).sum::<f64>(), 1.0, 1e-9);
+    assert_fvec(&inclusion_probabilities(&samples, 10), &PROB_10_U);
+
+    // Certainty units reduce the sample space
+    let options = SampleOptions::new(&[1.0, 0.5, 0.0]).unwrap();
+    assert_eq!(enumerate_poisson(&options, 10).unwrap().len(), 2);
+
+    enumerate_poisson(&SampleOptions::new(&PROB_10_U).unwrap(), 9).unwrap_err();
+    enumerate_poisson(&SampleOptions::new(&[0.5; 21]).unwrap(), 40).unwrap_err();
+}
+
+#[test]
+fn empirical() {
+    let options = SampleOptions::new(&PROB_10_U).unwrap();
+    let samples =
+        enumerate_design(brewer, &options, 10, NonZeroU32::new(50000).unwrap(), 4242).unwrap();
+
+    assert!(samples.iter().all(|s| s.0.len() == 5));
+    assert_delta!(samples.iter().map(|s| s.1).sum::<f64>(), 1.0, 1e-9);
+    assert_fvec_eps(&inclusion_probabilities(&samples, 10), &PROB_10_U, 1e-2);
+
+    enumerate_design(brewer, &options, 9, NonZeroU32::new(100).unwrap(), 4242).unwrap_err();
+}
+
+#[test]
+fn variance() {
+    let y: Vec<f64> = (0..10).map(|i| f64::from(i).sqrt() + 1.0).collect();
+    let options = SampleOptions::new(&PROB_10_U).unwrap();
+
+    let v = envisim_estimate::exact_variance(&y, |o| enumerate_poisson(o, 10), &options).unwrap();
+    let expected: f64 = y
+        .iter()
+        .zip(PROB_10_U.iter())
+        .map(|(y, p)| y * y * (1.0 - p) / p)
+        .sum();
+    assert_delta!(v, expected, 1e-9);
+
+    envisim_estimate::exact_variance(&y[1..], |o| enumerate_poisson(o, 10), &options).unwrap_err();
+    let p = [0.5; 21];
+    envisim_estimate::exact_variance(
+        &[1.0; 21],
+        |o| enumerate_poisson(o, 40),
+        &SampleOptions::new(&p).unwrap(),
+    )
+    .unwrap_err();
+}
@@ -0,0 +1,80 @@
+use envisim_estimate::hansen_hurwitz::*;
+use envisim_samplr::unequal::with_replacement;
+use envisim_samplr::SampleOptions;
+use envisim_test_utils::*;
+
+#[test]
+fn rao_blackwell_equal() {
+    let y = [1.0, 2.0, 3.0, 4.0, 5.0];
+    let p = [0.2; 5];
+
+    assert_delta!(rao_blackwell(&[3], &y, &p, 5).unwrap(), 20.0, 1e-9);
+    assert_delta!(rao_blackwell(&[0, 4, 0, 0], &y, &p, 5).unwrap(), 15.0, 1e-9);
+    assert_delta!(
+        rao_blackwell(&[0, 1, 2, 3, 4, 4], &y, &p, 5).unwrap(),
+        15.0,
+        1e-9
+    );
+
+    rao_blackwell(&[], &y, &p, 5).unwrap_err();
+    rao_blackwell(&[5], &y, &p, 5).unwrap_err();
+    rao_blackwell(&[0], &y, &p, 6).unwrap_err();
+    rao_blackwell(&[0], &y, &[0.0, 0.25, 0.25, 0.25, 0.25], 5).unwrap_err();
+}
+
+#[test]
+fn rao_blackwell_variance() {
+    let y = [1.0, 4.0, 2.0, 9.0, 3.0, 7.0];
+    let p = [0.1, 0.3, 0.05, 0.25, 0.1, 0.2];
+    let total: f64 = y.iter().sum();
+    let options = SampleOptions::new(&p).unwrap();
+    let mut rng = seeded_rng();
+    let m = 4;
+    let reps = 20000;
+
+    let (mut hh_sum, mut hh_sq, mut rb_sum, mut rb_sq) = (0.0, 0.0, 0.0, 0.0);
+    for _ in 0..reps {
+        let s = with_replacement(&mut rng, &options, m).unwrap();
+        let hh = s.iter().map(|&id| y[id] / p[id]).sum::<f64>() / 4.0;
+        let rb = rao_blackwell(&s, &y, &p, 6).unwrap();
+        hh_sum += hh;
+        hh_sq += (hh - total).powi(2);
+        rb_sum += rb;
+        rb_sq += (rb - total).powi(2);
+    }
+
+    let reps = f64::from(reps);
+    assert_delta!(hh_sum / reps, total, 0.5);
+    assert_delta!(rb_sum / reps, total, 0.5);
+    assert!(rb_sq < hh_sq);
+}
+
+#[test]
+fn rao_blackwell_exact() {
+    // Conditional expectation of the HH estimator, by enumeration of all samples of 3 draws
+    let y = [1.0, 4.0, 2.0, 9.0];
+    let p = [0.1, 0.3, 0.2, 0.4];
+    let mut groups = std::collections::HashMap::<Vec<usize>, (f64, f64)>::new();
+
+    for a in 0..4 {
+        for b in 0..4 {
+            for c in 0..4 {
+                let s = [a, b, c];
+                let prob = p[a] * p[b] * p[c];
+                let hh = s.iter().map(|&id| y[id] / p[id]).sum::<f64>() / 3.0;
+                let mut d = s.to_vec();
+                d.sort_unstable();
+                d.dedup();
+                let entry = groups.entry(d).or_insert((0.0, 0.0));
+                entry.0 += prob * hh;
+                entry.1 += prob;
+            }
+        }
+    }
+
+    for (d, (e, prob)) in groups.iter() {
+        let mut s = d.clone();
+        s.resize(3, d[0]);
+        assert_delta!(rao_blackwell(&s, &y, &p, 4).unwrap(), e / prob, 1e-9);
+    }
+}
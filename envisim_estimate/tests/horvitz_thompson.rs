@@ -0,0 +1,478 @@
+use envisim_estimate::horvitz_thompson::*;
+use envisim_estimate::simulation::monte_carlo_variance;
+use envisim_samplr::unequal::{brewer, sampford};
+use envisim_samplr::SampleOptions;
+use envisim_test_utils::*;
+use envisim_utils::{Matrix, SymMatrix};
+use std::num::NonZeroU32;
+
+#[test]
+fn variance_sym_matrix() {
+    let y = [1.0, 2.0, 4.0];
+    let p = [0.4, 0.5, 0.6];
+    let m = Matrix::new(&[0.4, 0.18, 0.22, 0.18, 0.5, 0.32, 0.22, 0.32, 0.6], 3);
+    let s = SymMatrix::try_from_matrix(&m, 0.0).unwrap();
+
+    assert_delta!(
+        variance(&y, &p, &m).unwrap(),
+        variance(&y, &p, &s).unwrap(),
+        EPS
+    );
+    assert_delta!(
+        syg_variance(&y, &p, &m).unwrap(),
+        syg_variance(&y, &p, &s).unwrap(),
+        EPS
+    );
+}
+
+#[test]
+fn variance_inconsistent_diagonal() {
+    let y = [1.0, 2.0, 4.0];
+    let p = [0.4, 0.5, 0.6];
+    let m = Matrix::new(&[0.4, 0.18, 0.22, 0.18, 0.25, 0.32, 0.22, 0.32, 0.6], 3);
+
+    check_second_order_consistency(&p, &m, EPS).unwrap_err();
+    check_second_order_consistency(&p, &m, 0.3).unwrap();
+    variance(&y, &p, &m).unwrap_err();
+    syg_variance(&y, &p, &m).unwrap_err();
+
+    // A custom tolerance, or no check at all
+    variance_with_eps(&y, &p, &m, Some(EPS)).unwrap_err();
+    syg_variance_with_eps(&y, &p, &m, Some(EPS)).unwrap_err();
+    for eps in [Some(0.3), None] {
+        variance_with_eps(&y, &p, &m, eps).unwrap();
+        syg_variance_with_eps(&y, &p, &m, eps).unwrap();
+    }
+}
+
+#[test]
+fn covariance() {
+    let x = [1.0, 2.0, 4.0, 7.0];
+    let y = Matrix::new(
+        &x.iter()
+            .copied()
+            .chain(x.iter().map(|v| 3.0 * v + 1.0))
+            .collect::<Vec<f64>>(),
+        4,
+    );
+    let p = [0.5, 0.25, 0.5, 0.4];
+    let cov = weighted_covariance(&y, &p).unwrap();
+
+    assert_eq!(cov.dim(), (2, 2));
+    assert_delta!(cov[(0, 1)], cov[(1, 0)], EPS);
+    assert_delta!(cov[(0, 1)], 3.0 * cov[(0, 0)], 1e-9);
+    assert_delta!(cov[(1, 1)], 9.0 * cov[(0, 0)], 1e-9);
+    // Rank deficient
+    assert_delta!(cov[(0, 0)] * cov[(1, 1)] - cov[(0, 1)].powi(2), 0.0, 1e-9);
+
+    // Equal weights 2.0, sum of squares 21.0
+    let cov = weighted_covariance(&Matrix::new(&x, 4), &[0.5; 4]).unwrap();
+    assert_delta!(cov[(0, 0)], 42.0 / 7.0, 1e-9);
+
+    weighted_covariance(&y, &p[1..]).map(drop).unwrap_err();
+    // The weights sum to at most 1.0
+    weighted_covariance(&Matrix::new(&[1.0], 1), &[1.0])
+        .map(drop)
+        .unwrap_err();
+}
+
+#[test]
+fn deville_srs() {
+    // Equal probabilities, reduces to the srswor estimator
+    let y = [1.0, 2.0, 4.0, 3.0, 0.5];
+    let p = [0.25; 5];
+    let srs = 16.0 * 0.75 * 5.0 * 2.05;
+    assert_delta!(deville_variance(&y, &p).unwrap(), srs, 1e-9);
+}
+
+#[test]
+fn hartley_rao() {
+    let y = [1.0, 2.0, 4.0, 3.0, 0.5];
+    let p = [0.25; 5];
+
+    // Equal probabilities, both reduce to the srswor estimator
+    let srs = 16.0 * 0.75 * 5.0 * 2.05;
+    assert_delta!(hartley_rao_variance(&y, &p).unwrap(), srs, 1e-9);
+    assert_delta!(deville_variance(&y, &p).unwrap(), srs, 1e-9);
+
+    let p = [0.4, 0.5, 0.6];
+    assert_delta!(hartley_rao_variance(&y[0..3], &p).unwrap(), 6.4375, 1e-9);
+
+    hartley_rao_variance(&y[0..1], &p[0..1]).unwrap_err();
+    hartley_rao_variance(&y[0..2], &p).unwrap_err();
+}
+
+#[test]
+fn size() {
+    assert_delta!(estimate_size(&[0.2; 5]).unwrap(), 25.0, EPS);
+    assert_delta!(estimate_size(&[]).unwrap(), 0.0, EPS);
+    estimate_size(&[0.2, 1.2]).unwrap_err();
+    estimate_size(&[0.2, 0.0]).unwrap_err();
+
+    // Unbiased for a pips design
+    let sizes: Vec<f64> = (1..=20).map(f64::from).collect();
+    let p = envisim_utils::pips::pips_from_slice(&sizes, 5).unwrap();
+    let options = SampleOptions::new(p.data()).unwrap();
+    let mut rng = seeded_rng();
+    let reps = 20000;
+    let mean = (0..reps)
+        .map(|_| {
+            let s = brewer(&mut rng, &options).unwrap();
+            let ps: Vec<f64> = s.iter().map(|&i| p[i]).collect();
+            estimate_size(&ps).unwrap()
+        })
+        .sum::<f64>()
+        / f64::from(reps);
+    assert_delta!(mean, 20.0, 0.5);
+}
+
+#[test]
+fn with_weights() {
+    let y = [1.0, 2.0, 4.0];
+    let p = [0.4, 0.5, 0.8];
+    let w: Vec<f64> = p.iter().map(|v| 1.0 / v).collect();
+
+    assert_delta!(
+        estimate_with_weights(&y, &w).unwrap(),
+        estimate(&y, &p).unwrap(),
+        EPS
+    );
+    assert_delta!(
+        estimate_with_weights(&y, &[1.0, 0.0, 2.0]).unwrap(),
+        9.0,
+        EPS
+    );
+    estimate_with_weights(&y, &w[0..2]).unwrap_err();
+    estimate_with_weights(&y, &[1.0, -1.0, 1.0]).unwrap_err();
+    estimate_with_weights(&y, &[1.0, f64::INFINITY, 1.0]).unwrap_err();
+    estimate_with_weights(&y, &[1.0, f64::NAN, 1.0]).unwrap_err();
+}
+
+#[test]
+fn sampford_syg() {
+    let y: Vec<f64> = (1..=10).map(|i| f64::from(i * i % 7)).collect();
+    let p = PROB_10_U;
+    let options = SampleOptions::new(&p).unwrap();
+
+    // The estimator is unbiased for the variance
+    let mc_reps = NonZeroU32::new(50000).unwrap();
+    let reference = monte_carlo_variance(&y, sampford, &options, mc_reps, 1).unwrap();
+    let mut rng = seeded_rng();
+    let reps = 20000;
+    let mean = (0..reps)
+        .map(|_| {
+            let s = sampford(&mut rng, &options).unwrap();
+            let ys: Vec<f64> = s.iter().map(|&i| y[i]).collect();
+            sampford_variance(&ys, &s, &options).unwrap()
+        })
+        .sum::<f64>()
+        / f64::from(reps);
+    assert_delta!(mean / reference, 1.0, 0.05);
+
+    sampford_variance(&y[0..4], &[0, 1, 2, 3, 4], &options).unwrap_err();
+    sampford_variance(&y[0..5], &[0, 1, 2, 3, 3], &options).unwrap_err();
+    sampford_variance(&y[0..5], &[0, 1, 2, 3, 10], &options).unwrap_err();
+}
+
+#[test]
+fn contributions() {
+    let y = [1.0, 2.0, 4.0];
+    let p = [0.4, 0.5, 0.6];
+    let m = Matrix::new(&[0.4, 0.18, 0.22, 0.18, 0.5, 0.32, 0.22, 0.32, 0.6], 3);
+    let c = variance_contributions(&y, &p, &m).unwrap();
+
+    assert_eq!(c.len(), 3);
+    assert_delta!(
+        c.iter().sum::<f64>(),
+        syg_variance(&y, &p, &m).unwrap(),
+        1e-9
+    );
+    assert_delta!(c[0], 1.0 / 8.0 + 625.0 / 792.0, 1e-9);
+    assert_delta!(c[1], 1.0 / 8.0 - 2.0 / 9.0, 1e-9);
+    assert_delta!(c[2], 625.0 / 792.0 - 2.0 / 9.0, 1e-9);
+
+    let s = SymMatrix::try_from_matrix(&m, 0.0).unwrap();
+    assert_eq!(c, variance_contributions(&y, &p, &s).unwrap());
+    variance_contributions(&y[0..2], &p, &m).unwrap_err();
+    let m = Matrix::new(&[0.4, 0.18, 0.22, 0.18, 0.25, 0.32, 0.22, 0.32, 0.6], 3);
+    variance_contributions(&y, &p, &m).unwrap_err();
+}
+
+#[test]
+fn ratio_deville() {
+    let x: Vec<f64> = (1..=40).map(f64::from).collect();
+    let y: Vec<f64> = x
+        .iter()
+        .map(|&x| 3.0 * x + 4.0 * (x * 1.7).sin() * x.sqrt())
+        .collect();
+    let x_total: f64 = x.iter().sum();
+    let p = envisim_utils::pips::pips_from_slice(&x, 10).unwrap();
+    let options = SampleOptions::new(p.data()).unwrap();
+
+    let mut rng = seeded_rng();
+    let reps = 20000;
+    let (mut sum, mut sum_sq, mut sum_var) = (0.0, 0.0, 0.0);
+    for _ in 0..reps {
+        let s = brewer(&mut rng, &options).unwrap();
+        let ys: Vec<f64> = s.iter().map(|&i| y[i]).collect();
+        let xs: Vec<f64> = s.iter().map(|&i| x[i]).collect();
+        let ps: Vec<f64> = s.iter().map(|&i| p[i]).collect();
+        let est = ratio(&ys, &xs, &ps, x_total).unwrap();
+        sum += est;
+        sum_sq += est * est;
+        sum_var += ratio_variance_deville(&ys, &xs, &ps, x_total).unwrap();
+    }
+
+    let reps = f64::from(reps);
+    let mc_variance = (sum_sq - sum * sum / reps) / (reps - 1.0);
+    assert_delta!(sum_var / reps / mc_variance, 1.0, 0.1);
+
+    ratio_variance_deville(&[1.0], &[1.0, 2.0], &[0.5, 0.5], 3.0).unwrap_err();
+    ratio_variance_deville(&[1.0, 2.0], &[1.0, 2.0], &[0.5, 0.5], -1.0).unwrap_err();
+    ratio_variance_deville(&[1.0, 2.0], &[0.0, 0.0], &[0.5, 0.5], 3.0).unwrap_err();
+
+    // The variance scales with (X / X_ht)^2
+    let (ys, xs, ps) = ([1.0, 3.0, 2.0], [1.0, 2.0, 4.0], [0.5; 3]);
+    assert_delta!(
+        ratio_variance_deville(&ys, &xs, &ps, 28.0).unwrap(),
+        4.0 * ratio_variance_deville(&ys, &xs, &ps, 14.0).unwrap(),
+        1e-9
+    );
+}
+
+#[test]
+fn indexed() {
+    let pi = PROB_10_E;
+    let s = [1, 4, 9];
+    let y = [1.0, 2.0, 4.0];
+    let ps: Vec<f64> = s.iter().map(|&i| pi[i]).collect();
+    assert_delta!(
+        estimate_indexed(&s, &y, &pi).unwrap(),
+        estimate(&y, &ps).unwrap(),
+        EPS
+    );
+    assert_delta!(estimate_indexed(&[], &[], &pi).unwrap(), 0.0, EPS);
+
+    estimate_indexed(&s, &y[1..], &pi).unwrap_err();
+    estimate_indexed(&[1, 10], &y[1..], &pi).unwrap_err();
+}
+
+#[test]
+fn generalized_bootstrap_deville() {
+    let y = [1.0, 2.0, 4.0, 3.0, 0.5, 2.5];
+    let p = [0.2, 0.35, 0.4, 0.65, 0.9, 0.5];
+    let deville = deville_variance(&y, &p).unwrap();
+
+    let reps = NonZeroU32::new(100000).unwrap();
+    let v = generalized_bootstrap(&y, &p, reps, 4242).unwrap();
+    assert_delta!(v / deville, 1.0, 2e-2);
+    assert_eq!(v, generalized_bootstrap(&y, &p, reps, 4242).unwrap());
+
+    generalized_bootstrap(&y[1..], &p, reps, 4242).unwrap_err();
+    generalized_bootstrap(&y, &p, NonZeroU32::new(1).unwrap(), 4242).unwrap_err();
+}
+
+#[test]
+fn zero_joint_probabilities() {
+    use envisim_samplr::SamplingError;
+    use envisim_utils::InputError;
+
+    let y = [1.0, 2.0, 4.0];
+    let p = [0.4, 0.5, 0.6];
+    let m = Matrix::new(&[0.4, 0.0, 0.22, 0.0, 0.5, 0.32, 0.22, 0.32, 0.6], 3);
+
+    for result in [
+        variance(&y, &p, &m),
+        syg_variance(&y, &p, &m),
+        variance_contributions(&y, &p, &m).map(|c| c[0]),
+    ] {
+        assert!(matches!(
+            result,
+            Err(SamplingError::Input(InputError::ZeroJointProbability(0, 1)))
+        ));
+    }
+
+    // A tiny positive joint probability makes the estimators blow up
+    let m = Matrix::new(&[0.4, 1e-300, 0.22, 1e-300, 0.5, 0.32, 0.22, 0.32, 0.6], 3);
+    for result in [
+        variance(&y, &p, &m),
+        syg_variance(&y, &p, &m),
+        variance_contributions(&y, &p, &m).map(|c| c[0]),
+    ] {
+        assert!(matches!(
+            result,
+            Err(SamplingError::Input(InputError::ZeroJointProbability(0, 1)))
+        ));
+    }
+
+    // Units 0 and 1 of a systematic design can never be selected together
+    let p = [0.5; 4];
+    let options = SampleOptions::new(&p).unwrap();
+    let pi2 = envisim_samplr::systematic::second_order(&options, &[0, 1, 2]).unwrap();
+    syg_variance(&[1.0, 2.0, 3.0], &[0.5; 3], &pi2).unwrap_err();
+}
+
+#[test]
+fn from_reader() {
+    use envisim_samplr::SamplingError;
+    use envisim_utils::InputError;
+    use std::io::Cursor;
+
+    let y = [1.0, 2.0, 4.0, 3.0];
+    let p = [0.2, 0.35, 0.4, 1.0];
+    let records = "1.0 0.2\n2.0,0.35\n\n4.0, 0.4\n  3.0\t1.0\n";
+    assert_delta!(
+        estimate_from_reader(Cursor::new(records)).unwrap(),
+        estimate(&y, &p).unwrap(),
+        EPS
+    );
+    assert_delta!(estimate_from_reader(Cursor::new("")).unwrap(), 0.0, EPS);
+
+    assert!(matches!(
+        estimate_from_reader(Cursor::new("1.0 0.2\n2.0\n")),
+        Err(SamplingError::Input(InputError::InvalidRecord(2)))
+    ));
+    assert!(matches!(
+        estimate_from_reader(Cursor::new("1.0 0.2 0.3\n")),
+        Err(SamplingError::Input(InputError::InvalidRecord(1)))
+    ));
+    assert!(matches!(
+        estimate_from_reader(Cursor::new("1.0 a\n")),
+        Err(SamplingError::Input(InputError::InvalidRecord(1)))
+    ));
+    estimate_from_reader(Cursor::new("1.0 0.0\n")).unwrap_err();
+    estimate_from_reader(Cursor::new("1.0 1.5\n")).unwrap_err();
+    assert!(matches!(
+        estimate_from_reader(Cursor::new(vec![0xffu8, b'\n'])),
+        Err(SamplingError::Io(_))
+    ));
+}
+
+#[test]
+fn hajek_syg() {
+    let y: Vec<f64> = (0..100)
+        .map(|i| f64::from(i % 7) + f64::from(i) * 0.1)
+        .collect();
+    let p: Vec<f64> = (0..100).map(|i| 0.1 + 0.2 * f64::from(i % 2)).collect();
+    let options = SampleOptions::new(&p).unwrap();
+
+    // The approximation gives an approximately unbiased estimator for high entropy designs, close
+    // to the Deville estimator
+    let mc_reps = NonZeroU32::new(50000).unwrap();
+    let reference = monte_carlo_variance(&y, sampford, &options, mc_reps, 1).unwrap();
+    let mut rng = seeded_rng();
+    let reps = 2000;
+    let (mut hajek, mut deville) = (0.0, 0.0);
+
+    for _ in 0..reps {
+        let s = sampford(&mut rng, &options).unwrap();
+        let ys: Vec<f64> = s.iter().map(|&i| y[i]).collect();
+        let ps: Vec<f64> = s.iter().map(|&i| p[i]).collect();
+        hajek += syg_variance(&ys, &ps, &hajek_second_order(&ps).unwrap()).unwrap();
+        deville += deville_variance(&ys, &ps).unwrap();
+    }
+
+    assert_delta!(hajek / f64::from(reps) / reference, 1.0, 0.05);
+    assert_delta!(hajek / deville, 1.0, 0.02);
+
+    // The approximations are valid second order probabilities
+    let ps = [0.2, 0.9, 0.95, 0.5];
+    let pi2 = hajek_second_order(&ps).unwrap();
+    for i in 0..4 {
+        assert_eq!(pi2[(i, i)], ps[i]);
+        for j in 0..i {
+            assert!(pi2[(i, j)] >= (ps[i] + ps[j] - 1.0).max(0.0));
+            assert!(pi2[(i, j)] <= ps[i].min(ps[j]));
+        }
+    }
+
+    hajek_second_order(&[]).unwrap_err();
+    hajek_second_order(&[0.5, 0.0]).unwrap_err();
+    hajek_second_order(&[0.5, 1.5]).unwrap_err();
+}
+
+#[test]
+fn weights() {
+    let w = design_weights(&PROB_10_U).unwrap();
+    assert_fvec(
+        &w,
+        &PROB_10_U.iter().map(|&p| 1.0 / p).collect::<Vec<f64>>(),
+    );
+    assert!(design_weights(&[]).unwrap().is_empty());
+
+    design_weights(&[0.5, 0.0]).unwrap_err();
+    design_weights(&[0.5, 1.5]).unwrap_err();
+    design_weights(&[0.5, f64::NAN]).unwrap_err();
+}
+
+#[test]
+fn variance_estimator_dyn() {
+    use envisim_utils::kd_tree::TreeBuilder;
+    use std::num::NonZeroUsize;
+
+    let y = [1.0, 2.0, 4.0, 3.0, 0.5];
+    let p = [0.2, 0.35, 0.4, 0.65, 0.9];
+    let pi2 = hajek_second_order(&p).unwrap();
+    let aux = Matrix::new(&DATA_10_2[0..5], 5);
+    let n_neighbours = NonZeroUsize::new(3).unwrap();
+
+    let estimators: Vec<(Box<dyn VarianceEstimator>, f64)> = vec![
+        (Box::new(Deville), deville_variance(&y, &p).unwrap()),
+        (Box::new(Hajek), syg_variance(&y, &p, &pi2).unwrap()),
+        (
+            Box::new(SenYatesGrundy(pi2.clone())),
+            syg_variance(&y, &p, &pi2).unwrap(),
+        ),
+        (
+            Box::new(LocalMean {
+                auxiliaries: &aux,
+                n_neighbours,
+            }),
+            local_mean_variance(&y, &p, &TreeBuilder::new(&aux), n_neighbours).unwrap(),
+        ),
+    ];
+
+    for (estimator, expected) in estimators.iter() {
+        assert_delta!(estimator.variance(&y, &p).unwrap(), *expected, EPS);
+        estimator.variance(&y[1..], &p).unwrap_err();
+    }
+}
+
+#[test]
+fn domain_ratio() {
+    // The proportion of the total of x that is in the domain of every third unit
+    let x: Vec<f64> = (1..=30)
+        .map(|i| f64::from(i) + 5.0 * f64::from(i % 4))
+        .collect();
+    let y: Vec<f64> = x
+        .iter()
+        .enumerate()
+        .map(|(i, &x)| if i % 3 == 0 { x } else { 0.0 })
+        .collect();
+    let p = envisim_utils::pips::pips_from_slice(&x, 8).unwrap();
+    let options = SampleOptions::new(p.data()).unwrap();
+
+    let mut rng = seeded_rng();
+    let reps = 10000;
+    let (mut sum, mut sum_sq, mut sum_var) = (0.0, 0.0, 0.0);
+    for _ in 0..reps {
+        let s = sampford(&mut rng, &options).unwrap();
+        let ys: Vec<f64> = s.iter().map(|&i| y[i]).collect();
+        let xs: Vec<f64> = s.iter().map(|&i| x[i]).collect();
+        let ps: Vec<f64> = s.iter().map(|&i| p[i]).collect();
+        let pi2 = envisim_samplr::unequal::sampford_second_order(&options, &s).unwrap();
+        let est = estimate(&ys, &ps).unwrap() / estimate(&xs, &ps).unwrap();
+        sum += est;
+        sum_sq += est * est;
+        sum_var += domain_ratio_variance(&ys, &xs, &ps, &pi2).unwrap();
+    }
+
+    let reps = f64::from(reps);
+    let mc_variance = (sum_sq - sum * sum / reps) / (reps - 1.0);
+    assert_delta!(sum_var / reps / mc_variance, 1.0, 0.1);
+
+    let p = [0.4, 0.5, 0.6];
+    let m = Matrix::new(&[0.4, 0.18, 0.22, 0.18, 0.5, 0.32, 0.22, 0.32, 0.6], 3);
+    domain_ratio_variance(&[1.0, 2.0], &[1.0, 2.0, 3.0], &p, &m).unwrap_err();
+    domain_ratio_variance(&[0.0; 3], &[0.0; 3], &p, &m).unwrap_err();
+}
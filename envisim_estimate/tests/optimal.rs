@@ -0,0 +1,29 @@
+use envisim_estimate::optimal_probabilities;
+use envisim_test_utils::*;
+
+#[test]
+fn proportional_model() {
+    let y: Vec<f64> = DATA_10_2[0..10].iter().map(|&x| x - 0.3).collect();
+    let abs_y: Vec<f64> = y.iter().map(|y| y.abs()).collect();
+    let total: f64 = abs_y.iter().sum();
+    let expected: Vec<f64> = abs_y.iter().map(|&y| 3.0 * y / total).collect();
+
+    // Without residual variation, the probabilities are proportional to |y|
+    let p = optimal_probabilities(&[0.0; 10], &y, 3).unwrap();
+    assert_fvec(&p, &expected);
+
+    // With standard deviations proportional to |y|, the probabilities are proportional to |y|
+    let sd: Vec<f64> = abs_y.iter().map(|&y| 0.5 * y).collect();
+    let p = optimal_probabilities(&sd, &y, 3).unwrap();
+    assert_fvec(&p, &expected);
+
+    // Large units are given probability 1.0
+    let p = optimal_probabilities(&[1.0, 0.0, 0.0, 0.0], &[1.0, 1.0, 2.0, 100.0], 2).unwrap();
+    let t = 2.0f64.sqrt() + 3.0;
+    assert_fvec(&p, &[2.0f64.sqrt() / t, 1.0 / t, 2.0 / t, 1.0]);
+
+    optimal_probabilities(&[0.0; 9], &y, 3).unwrap_err();
+    optimal_probabilities(&[-1.0; 10], &y, 3).unwrap_err();
+    optimal_probabilities(&[0.0; 10], &[f64::NAN; 10], 3).unwrap_err();
+    optimal_probabilities(&[0.0; 10], &y, 11).unwrap_err();
+}
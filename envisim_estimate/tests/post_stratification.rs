@@ -0,0 +1,71 @@
+use envisim_estimate::horvitz_thompson;
+use envisim_estimate::post_stratification::*;
+use envisim_samplr::unequal::brewer;
+use envisim_samplr::{SampleOptions, SamplingError};
+use envisim_test_utils::*;
+use envisim_utils::utils::usize_to_f64;
+use rand::Rng;
+
+#[test]
+fn constant_strata() -> Result<(), SamplingError> {
+    // If y is constant within the post-strata, the estimate is exact
+    let y = [1.0, 1.0, 5.0, 5.0, 5.0];
+    let p = [0.2, 0.5, 0.25, 0.4, 0.8];
+    let strata = [0, 0, 1, 1, 1];
+    let totals = [10.0, 20.0];
+
+    assert_delta!(estimate(&y, &p, &strata, &totals)?, 110.0, EPS);
+    assert_delta!(variance(&y, &p, &strata, &totals)?, 0.0, EPS);
+
+    // Empty post-strata are allowed, if their size is zero
+    assert_delta!(estimate(&y, &p, &strata, &[10.0, 20.0, 0.0])?, 110.0, EPS);
+
+    estimate(&y, &p, &strata, &[10.0, 20.0, 1.0]).unwrap_err();
+    estimate(&y, &p, &strata, &[10.0]).unwrap_err();
+    estimate(&y, &p, &strata, &[10.0, -1.0]).unwrap_err();
+    estimate(&y[1..], &p, &strata, &totals).unwrap_err();
+    estimate(&y, &p, &strata[1..], &totals).unwrap_err();
+    variance(&y, &[0.0, 0.5, 0.25, 0.4, 0.8], &strata, &totals).unwrap_err();
+    Ok(())
+}
+
+#[test]
+fn two_strata() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let population_strata: Vec<usize> = (0..100).map(|i| usize::from(i >= 40)).collect();
+    let y_population: Vec<f64> = population_strata
+        .iter()
+        .map(|&h| if h == 0 { 10.0 } else { 30.0 } + rng.gen::<f64>() * 4.0)
+        .collect();
+    let total: f64 = y_population.iter().sum();
+    let p = [0.3; 100];
+    let options = SampleOptions::new(&p)?;
+
+    let reps = 10000;
+    let mut estimates = Vec::<f64>::with_capacity(reps);
+    let mut variances = Vec::<f64>::with_capacity(reps);
+    let mut ht_estimates = Vec::<f64>::with_capacity(reps);
+
+    for _ in 0..reps {
+        let s = brewer(&mut rng, &options)?;
+        let y: Vec<f64> = s.iter().map(|&i| y_population[i]).collect();
+        let strata: Vec<usize> = s.iter().map(|&i| population_strata[i]).collect();
+        estimates.push(estimate(&y, &p[0..30], &strata, &[40.0, 60.0])?);
+        variances.push(variance(&y, &p[0..30], &strata, &[40.0, 60.0])?);
+        ht_estimates.push(horvitz_thompson::estimate(&y, &p[0..30])?);
+    }
+
+    let mean = |v: &[f64]| v.iter().sum::<f64>() / usize_to_f64(v.len());
+    let mc_variance = |v: &[f64]| {
+        let m = mean(v);
+        v.iter().map(|&e| (e - m).powi(2)).sum::<f64>() / usize_to_f64(v.len())
+    };
+
+    // The estimator is approximately unbiased
+    assert_delta!(mean(&estimates) / total, 1.0, 1e-3);
+    // The variance estimator is approximately unbiased
+    assert_delta!(mean(&variances) / mc_variance(&estimates), 1.0, 0.1);
+    // Post-stratification improves on the Horvitz-Thompson estimator
+    assert!(mc_variance(&estimates) < 0.1 * mc_variance(&ht_estimates));
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use envisim_estimate::regression::*;
+use envisim_samplr::SamplingError;
+use envisim_test_utils::*;
+use envisim_utils::{InputError, Matrix};
+
+#[test]
+fn two_phase_exact() {
+    // y = 2 - x1 + 3 x2
+    let x = Matrix::new(
+        &[
+            1.0, 1.0, 1.0, 1.0, 1.0, 1.0, //
+            0.5, 1.0, 2.0, 3.0, 1.5, 0.0, //
+            2.0, 0.0, 1.0, 4.0, 3.0, 1.0,
+        ],
+        6,
+    );
+    let p1 = [0.2, 0.4, 0.5, 0.25, 0.8, 0.5];
+    let s2 = [4, 0, 2, 5];
+    let y: Vec<f64> = s2
+        .iter()
+        .map(|&i| 2.0 - x[(i, 1)] + 3.0 * x[(i, 2)])
+        .collect();
+    let p2 = [0.5, 0.6, 0.7, 0.8];
+
+    let expected: f64 = (0..6)
+        .map(|i| (2.0 - x[(i, 1)] + 3.0 * x[(i, 2)]) / p1[i])
+        .sum();
+    assert_delta!(
+        two_phase_regression(&y, &x, &p1, &s2, &p2).unwrap(),
+        expected,
+        1e-9
+    );
+}
+
+#[test]
+fn two_phase_intercept() {
+    // With only an intercept, the estimator is the estimated first phase size times the
+    // weighted second phase mean
+    let x = Matrix::new(&[1.0; 5], 5);
+    let p1 = [0.5, 0.5, 0.25, 0.25, 1.0];
+    let s2 = [1, 2, 4];
+    let y = [2.0, 4.0, 6.0];
+    let p2 = [0.5, 0.5, 1.0];
+
+    let w = [4.0, 8.0, 1.0];
+    let mean = (2.0 * w[0] + 4.0 * w[1] + 6.0 * w[2]) / 13.0;
+    assert_delta!(
+        two_phase_regression(&y, &x, &p1, &s2, &p2).unwrap(),
+        13.0 * mean,
+        1e-9
+    );
+}
+
+#[test]
+fn two_phase_errors() {
+    let x = Matrix::new(&[1.0, 1.0, 1.0, 1.0, 2.0, 3.0], 3);
+    let p1 = [0.5; 3];
+
+    // Not a subset
+    two_phase_regression(&[1.0, 2.0], &x, &p1, &[0, 3], &[0.5; 2]).unwrap_err();
+    // Duplicates
+    two_phase_regression(&[1.0, 2.0], &x, &p1, &[0, 0], &[0.5; 2]).unwrap_err();
+    // Lengths
+    two_phase_regression(&[1.0], &x, &p1, &[0, 1], &[0.5; 2]).unwrap_err();
+    two_phase_regression(&[1.0, 2.0], &x, &p1, &[0, 1], &[0.5; 3]).unwrap_err();
+    two_phase_regression(&[1.0, 2.0], &x, &[0.5; 2], &[0, 1], &[0.5; 2]).unwrap_err();
+    // Probabilities
+    two_phase_regression(&[1.0, 2.0], &x, &p1, &[0, 1], &[0.0, 0.5]).unwrap_err();
+    // Singular
+    two_phase_regression(&[1.0], &x, &p1, &[0], &[0.5]).unwrap_err();
+
+    // Nearly collinear
+    let x = Matrix::new(&[1.0, 1.0, 1.0, 1.0, 1.0 + 1e-9, 1.0 + 2e-9], 3);
+    let err = two_phase_regression(&[1.0, 2.0, 4.0], &x, &p1, &[0, 1, 2], &[0.5; 3]).unwrap_err();
+    assert!(matches!(err, SamplingError::Input(InputError::Singular)));
+}
@@ -0,0 +1,45 @@
+#![cfg(feature = "serde")]
+
+use envisim_estimate::balanced::{balance_report, BalanceReport};
+use envisim_estimate::simulation::{simulate, SimulationResult};
+use envisim_samplr::unequal::pareto;
+use envisim_samplr::SamplingError;
+use envisim_test_utils::*;
+use envisim_utils::Matrix;
+
+#[test]
+fn simulation_result() -> Result<(), SamplingError> {
+    let y: Vec<f64> = (1..=10).map(|i| f64::from(i).sqrt()).collect();
+    let sizes: Vec<f64> = (1..=10).map(f64::from).collect();
+    let result = simulate(&y, &sizes, 3, pareto, 4242)?;
+
+    let json = serde_json::to_string(&result).unwrap();
+    let parsed: SimulationResult = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(parsed.sample, result.sample);
+    assert_eq!(parsed.probabilities, result.probabilities);
+    assert_eq!(parsed.estimate.to_bits(), result.estimate.to_bits());
+    assert_eq!(parsed.variance.to_bits(), result.variance.to_bits());
+    assert_eq!(parsed.total.to_bits(), result.total.to_bits());
+    assert_eq!(
+        parsed.relative_error.to_bits(),
+        result.relative_error.to_bits()
+    );
+    Ok(())
+}
+
+#[test]
+fn balance_report_round_trip() -> Result<(), SamplingError> {
+    let p = PROB_10_U;
+    let balancing = Matrix::from_ref(&DATA_10_2, 10);
+    let totals = [4.1, 5.3];
+    let report = balance_report(&[0, 3, 5, 8, 9], &p, &balancing, &totals)?;
+
+    let json = serde_json::to_string(&report).unwrap();
+    let parsed: BalanceReport = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(parsed.estimates, report.estimates);
+    assert_eq!(parsed.totals, report.totals);
+    assert_eq!(parsed.relative_imbalances, report.relative_imbalances);
+    Ok(())
+}
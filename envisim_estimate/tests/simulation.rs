@@ -0,0 +1,176 @@
+use envisim_estimate::simulation::*;
+use envisim_samplr::poisson;
+use envisim_samplr::unequal::brewer;
+use envisim_samplr::{SampleOptions, SamplingError};
+use envisim_test_utils::*;
+use envisim_utils::utils::usize_to_f64;
+use envisim_utils::InputError;
+use std::num::NonZeroU32;
+
+#[test]
+fn simulate_brewer() {
+    let y: Vec<f64> = (1..=10).map(|i| f64::from(i) * 2.0 + 1.0).collect();
+    let sizes: Vec<f64> = (1..=10).map(f64::from).collect();
+
+    let a = simulate(&y, &sizes, 4, brewer, 1).unwrap();
+    let b = simulate(&y, &sizes, 4, brewer, 1).unwrap();
+    assert_eq!(a.sample, b.sample);
+    assert_eq!(a.sample.len(), 4);
+    assert_delta!(a.total, 120.0, EPS);
+    assert_delta!(a.relative_error, (a.estimate - 120.0) / 120.0, EPS);
+
+    let iterations = 20000;
+    let mean = (0..iterations)
+        .map(|seed| simulate(&y, &sizes, 4, brewer, seed).unwrap().estimate)
+        .sum::<f64>()
+        / 20000.0;
+    assert_delta!(mean / 120.0, 1.0, 1e-2);
+
+    simulate(&y, &sizes[1..], 4, brewer, 1).unwrap_err();
+}
+
+#[test]
+fn simulate_zero_total() {
+    let y: Vec<f64> = (1..=10).map(|i| f64::from(i) - 5.5).collect();
+    let sizes: Vec<f64> = (1..=10).map(f64::from).collect();
+
+    let result = simulate(&y, &sizes, 4, brewer, 1).unwrap();
+    assert_eq!(result.total, 0.0);
+    assert!(!result.relative_error.is_finite());
+}
+
+#[test]
+fn monte_carlo_variance_poisson() {
+    let y: Vec<f64> = (1..=10).map(f64::from).collect();
+    let p = PROB_10_U;
+    let options = SampleOptions::new(&p).unwrap();
+
+    // The variance of the HT estimator under poisson sampling is sum y^2 (1 - p) / p
+    let exact: f64 = y
+        .iter()
+        .zip(p.iter())
+        .map(|(yi, pi)| yi * yi * (1.0 - pi) / pi)
+        .sum();
+    let reps = NonZeroU32::new(20000).unwrap();
+    let v = monte_carlo_variance(&y, poisson::sample, &options, reps, 1).unwrap();
+    assert_delta!(v / exact, 1.0, 0.05);
+    assert_eq!(
+        v,
+        monte_carlo_variance(&y, poisson::sample, &options, reps, 1).unwrap()
+    );
+
+    monte_carlo_variance(&y[1..], poisson::sample, &options, reps, 1).unwrap_err();
+    let reps = NonZeroU32::new(1).unwrap();
+    monte_carlo_variance(&y, poisson::sample, &options, reps, 1).unwrap_err();
+}
+
+#[test]
+fn monte_carlo_mean_antithetic() {
+    use envisim_estimate::horvitz_thompson::estimate;
+    use envisim_utils::utils::variance;
+
+    let y: Vec<f64> = (1..=10).map(f64::from).collect();
+    let p = PROB_10_U;
+    let options = SampleOptions::new(&p).unwrap();
+    let total = |s: &[usize]| {
+        let ys: Vec<f64> = s.iter().map(|&i| y[i]).collect();
+        let ps: Vec<f64> = s.iter().map(|&i| p[i]).collect();
+        estimate(&ys, &ps)
+    };
+    let reps = NonZeroU32::new(100).unwrap();
+    let means = |variates: Variates| -> Vec<f64> {
+        (0..200)
+            .map(|seed| {
+                monte_carlo_mean(poisson::sample, &options, total, reps, seed, variates).unwrap()
+            })
+            .collect()
+    };
+
+    // Both are unbiased, but the antithetic means have a smaller simulation variance
+    let independent = means(Variates::Independent);
+    let antithetic = means(Variates::Antithetic);
+    assert_delta!(independent.iter().sum::<f64>() / 200.0 / 55.0, 1.0, 0.02);
+    assert_delta!(antithetic.iter().sum::<f64>() / 200.0 / 55.0, 1.0, 0.02);
+    assert!(variance(&antithetic) < 0.5 * variance(&independent));
+
+    let odd = NonZeroU32::new(3).unwrap();
+    assert!(matches!(
+        monte_carlo_mean(
+            poisson::sample,
+            &options,
+            total,
+            odd,
+            1,
+            Variates::Antithetic
+        ),
+        Err(SamplingError::Input(InputError::NotEven(3)))
+    ));
+}
+
+#[test]
+fn second_order_poisson() {
+    let p = [0.2, 0.3, 0.5, 0.6, 0.7, 0.9];
+    let options = SampleOptions::new(&p).unwrap();
+
+    let reps = NonZeroU32::new(40000).unwrap();
+    let a = estimate_second_order(poisson::sample, &options, reps, 4242).unwrap();
+    let b = estimate_second_order(poisson::sample, &options, reps, 4242).unwrap();
+
+    for i in 0..p.len() {
+        for j in 0..p.len() {
+            let exact = if i == j { p[i] } else { p[i] * p[j] };
+            assert_eq!(a[(i, j)], b[(i, j)]);
+            assert_delta!(a[(i, j)], exact, 1e-2);
+        }
+    }
+
+    // The pairs are counted irrespective of the order of the sample
+    let reps = NonZeroU32::new(1000).unwrap();
+    let mut options = SampleOptions::new(&PROB_10_U).unwrap();
+    let sorted = estimate_second_order(brewer, &options, reps, 4242).unwrap();
+    options.sorted_output(false).unwrap();
+    let unsorted = estimate_second_order(brewer, &options, reps, 4242).unwrap();
+    assert_eq!(sorted.data(), unsorted.data());
+}
+
+#[test]
+fn replicate_progress() {
+    use envisim_samplr::ProgressEvent;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    let y: Vec<f64> = (1..=10).map(f64::from).collect();
+    let count = AtomicU32::new(0);
+    let completed = AtomicU32::new(0);
+    let hook = |event: ProgressEvent| {
+        if let ProgressEvent::Replicate { replicate, reps } = event {
+            assert!(replicate <= reps.get());
+            count.fetch_add(1, Ordering::Relaxed);
+            completed.fetch_max(replicate, Ordering::Relaxed);
+        }
+    };
+    let mut options = SampleOptions::new(&PROB_10_U).unwrap();
+    options.progress(&hook).unwrap();
+    let reps = NonZeroU32::new(100).unwrap();
+
+    monte_carlo_variance(&y, poisson::sample, &options, reps, 1).unwrap();
+    assert_eq!(count.swap(0, Ordering::Relaxed), 100);
+    assert_eq!(completed.swap(0, Ordering::Relaxed), 100);
+
+    let statistic = |s: &[usize]| Ok(usize_to_f64(s.len()));
+    monte_carlo_mean(
+        poisson::sample,
+        &options,
+        statistic,
+        reps,
+        1,
+        Variates::Independent,
+    )
+    .unwrap();
+    assert_eq!(count.swap(0, Ordering::Relaxed), 100);
+    assert_eq!(completed.swap(0, Ordering::Relaxed), 100);
+
+    // With the rayon feature, the replicates are reported per stream
+    estimate_second_order(poisson::sample, &options, reps, 1).unwrap();
+    assert!((1..=100).contains(&count.load(Ordering::Relaxed)));
+    assert_eq!(completed.load(Ordering::Relaxed), 100);
+}
@@ -15,8 +15,8 @@ fn test_voronoi() -> Result<(), SamplingError> {
 fn test_local() -> Result<(), SamplingError> {
     let data = Matrix::new(&DATA_10_2, 10);
     let sb = local(&[0], &PROB_10_E, &(&data).into())?;
-    assert_delta!(sb, 0.9734661634680257247254);
+    assert_delta!(sb, 0.973_466_163_468_025_7);
     let sb = local(&[0, 1], &PROB_10_E, &(&data).into())?;
-    assert_delta!(sb, 1.251849435249984709984);
+    assert_delta!(sb, 1.251_849_435_249_984_7);
     Ok(())
 }
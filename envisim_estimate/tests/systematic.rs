@@ -0,0 +1,31 @@
+use envisim_estimate::systematic_periodicity_warning;
+
+#[test]
+fn test_periodicity_warning() {
+    // A seasonal frame, with period 12 and some noise
+    let sizes: Vec<f64> = (0..120)
+        .map(|i| {
+            let i = f64::from(i);
+            10.0 + 5.0 * (i * std::f64::consts::PI / 6.0).sin() + 0.5 * (i * 7.3).sin()
+        })
+        .collect();
+
+    // The interval 120 / 10 = 12 matches the period
+    let score = systematic_periodicity_warning(&sizes, 10).unwrap();
+    assert!(score > 0.9);
+
+    // The interval 120 / 20 = 6 is half the period
+    assert_eq!(systematic_periodicity_warning(&sizes, 20), None);
+}
+
+#[test]
+fn test_periodicity_warning_trend() {
+    // A trend is not periodic
+    let sizes: Vec<f64> = (0..100).map(f64::from).collect();
+    assert_eq!(systematic_periodicity_warning(&sizes, 10), None);
+
+    assert_eq!(systematic_periodicity_warning(&[1.0; 100], 10), None);
+    assert_eq!(systematic_periodicity_warning(&sizes, 0), None);
+    assert_eq!(systematic_periodicity_warning(&sizes, 100), None);
+    assert_eq!(systematic_periodicity_warning(&sizes, 1), None);
+}
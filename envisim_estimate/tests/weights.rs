@@ -0,0 +1,52 @@
+use envisim_estimate::weights::*;
+use envisim_test_utils::*;
+
+#[test]
+fn effective_size() {
+    assert_delta!(effective_sample_size(&[0.2; 5]).unwrap(), 5.0, EPS);
+    assert_delta!(
+        effective_sample_size(&[0.2, 0.2, 0.4, 0.4]).unwrap(),
+        3.6,
+        EPS
+    );
+    effective_sample_size(&[]).unwrap_err();
+    effective_sample_size(&[0.2, 0.0]).unwrap_err();
+}
+
+#[test]
+fn trim() {
+    let pi = [0.02, 0.04, 0.1, 0.2, 0.25, 0.5, 0.5, 1.0];
+    let total: f64 = pi.iter().map(|p| 1.0 / p).sum();
+    let (adj, trimmed) = trim_weights(&pi, 15.0).unwrap();
+
+    assert_delta!(adj.iter().map(|p| 1.0 / p).sum::<f64>(), total, 1e-9);
+    assert!(adj.iter().all(|&p| 1.0 / p <= 15.0 + 1e-9));
+    assert!(trimmed >= 35.0 + 10.0);
+    assert_fvec(&trim_weights(&pi, 100.0).unwrap().0, &pi);
+
+    trim_weights(&pi, 1.0).unwrap_err();
+}
+
+#[test]
+fn histogram() {
+    // Weights 2 and 5 in the first bin, 4 in the second
+    let y = [0.0, 0.9, 1.0, 3.0, -1.0];
+    let pi = [0.5, 0.2, 0.25, 0.1, 0.1];
+    assert_fvec(
+        &weighted_histogram(&y, &pi, &[0.0, 1.0, 2.0]).unwrap(),
+        &[7.0, 4.0],
+    );
+    // The last bin includes its upper break
+    assert_fvec(
+        &weighted_histogram(&y, &pi, &[0.0, 1.0, 3.0]).unwrap(),
+        &[7.0, 14.0],
+    );
+    assert_fvec(&weighted_histogram(&[], &[], &[0.0, 1.0]).unwrap(), &[0.0]);
+
+    weighted_histogram(&y, &pi, &[0.0]).unwrap_err();
+    weighted_histogram(&y, &pi, &[0.0, 1.0, 1.0]).unwrap_err();
+    weighted_histogram(&y, &pi, &[0.0, 2.0, 1.0]).unwrap_err();
+    weighted_histogram(&y, &pi, &[0.0, f64::NAN]).unwrap_err();
+    weighted_histogram(&y, &pi[1..], &[0.0, 1.0]).unwrap_err();
+    weighted_histogram(&y, &[0.5, 0.2, 0.25, 0.1, 0.0], &[0.0, 1.0]).unwrap_err();
+}
@@ -25,14 +25,36 @@ pub enum InputError {
     InvalidValueUsize(usize, usize),
     // 0 must be integer
     NotInteger(f64),
+    // 0 must be even
+    NotEven(usize),
     // 0 must be 1
     InvalidSize(usize, usize),
+    // slice has length 1, but 0 was expected
+    LengthMismatch { expected: usize, found: usize },
     // empty slice
     IsEmpty,
     // slice contains duplicates
     NotUnique,
     // Missing input
     Missing(String),
+    // unit 0 (size 1) has larger probability 2 than unit 3 (size 4) with probability 5
+    NotMonotone(usize, f64, f64, usize, f64, f64),
+    // element (0, 1) differs from element (1, 0)
+    NotSymmetric(usize, usize),
+    // diagonal element (0, 0) is 1, but the probability of unit 0 is 2
+    InconsistentDiagonal(usize, f64, f64),
+    // matrix is singular
+    Singular,
+    // element (0, 1) of the second order probabilities is zero, or near zero
+    ZeroJointProbability(usize, usize),
+    // line 0 is not a valid record
+    InvalidRecord(usize),
+    // 0 units have probability 1, but the sample size is 1
+    TooManyCertaintyUnits(usize, usize),
+    // split does not preserve the probability 1 of unit 0
+    SplitNotPreserving(usize, f64),
+    // split did not decide any unit
+    SplitUndecided,
 }
 
 impl InputError {
@@ -86,9 +108,17 @@ impl InputError {
 
         Ok(())
     }
+    /// Returns [`InputError::LengthMismatch`] if `a` does not have the same length as `b`.
     #[inline]
     pub fn check_lengths<TA, TB>(a: &[TA], b: &[TB]) -> Result<(), InputError> {
-        InputError::check_sizes(a.len(), b.len())
+        if a.len() != b.len() {
+            return Err(InputError::LengthMismatch {
+                expected: b.len(),
+                found: a.len(),
+            });
+        }
+
+        Ok(())
     }
     #[inline]
     pub fn check_sizes(a: usize, b: usize) -> Result<(), InputError> {
@@ -169,9 +199,15 @@ impl std::fmt::Display for InputError {
             InputError::NotInteger(x) => {
                 write!(f, "value {x} must be integer")
             }
+            InputError::NotEven(x) => {
+                write!(f, "value {x} must be even")
+            }
             InputError::InvalidSize(a, b) => {
                 write!(f, "invalid size: {a} must be {b}")
             }
+            InputError::LengthMismatch { expected, found } => {
+                write!(f, "length mismatch: expected {expected}, found {found}")
+            }
             InputError::IsEmpty => {
                 write!(f, "slice is empty")
             }
@@ -181,6 +217,55 @@ impl std::fmt::Display for InputError {
             InputError::Missing(ref txt) => {
                 write!(f, "missing input {txt}")
             }
+            InputError::NotMonotone(i, si, pi, j, sj, pj) => {
+                write!(
+                    f,
+                    "not monotone: unit {i} (size {si}) has probability {pi}, \
+                    but larger unit {j} (size {sj}) has probability {pj}"
+                )
+            }
+            InputError::NotSymmetric(i, j) => {
+                write!(
+                    f,
+                    "not symmetric: element ({i}, {j}) differs from ({j}, {i})"
+                )
+            }
+            InputError::InconsistentDiagonal(i, d, p) => {
+                write!(
+                    f,
+                    "inconsistent second order probabilities: diagonal element ({i}, {i}) \
+                    is {d}, but the probability of unit {i} is {p}"
+                )
+            }
+            InputError::Singular => {
+                write!(f, "matrix is singular")
+            }
+            InputError::ZeroJointProbability(i, j) => {
+                write!(
+                    f,
+                    "zero (or near zero) second order probability: units {i} and {j} can never be selected \
+                    together, hence the estimator is inapplicable to the design"
+                )
+            }
+            InputError::InvalidRecord(line) => {
+                write!(f, "line {line} is not a valid record")
+            }
+            InputError::TooManyCertaintyUnits(count, n) => {
+                write!(
+                    f,
+                    "too many certainty units: {count} units have probability 1, \
+                    but the sample size is {n}"
+                )
+            }
+            InputError::SplitNotPreserving(id, p) => {
+                write!(
+                    f,
+                    "invalid split: the split does not preserve the probability {p} of unit {id}"
+                )
+            }
+            InputError::SplitUndecided => {
+                write!(f, "invalid split: the split did not decide any unit")
+            }
         }
     }
 }
@@ -25,5 +25,6 @@ mod split_methods;
 
 pub use node::*;
 pub use searcher::*;
+pub use split_methods::median;
 pub use split_methods::midpoint_slide;
 pub use split_methods::FindSplit;
@@ -15,21 +15,33 @@ use super::split_methods::{midpoint_slide, FindSplit};
 use crate::Matrix;
 use std::num::NonZeroUsize;
 
+/// A distance between two units, given their coordinates.
+/// The distance may capture state, e.g. a radius, weights or a projection.
+pub type Distance<'a> = &'a (dyn Fn(&[f64], &[f64]) -> f64 + Sync);
+
 pub struct TreeBuilder<'a> {
     data: &'a Matrix<'a>,
     bucket_size: NonZeroUsize,
     split_method: FindSplit,
+    distance: Option<Distance<'a>>,
+    prune: bool,
 }
 
 impl<'a> TreeBuilder<'a> {
+    /// Creates a builder of k-d trees of `data`, with a bucket size of 40, the
+    /// [`midpoint_slide`] splitting method, and the squared euclidean distance.
     #[inline]
     pub fn new(data: &'a Matrix) -> TreeBuilder<'a> {
         Self {
             data,
             bucket_size: unsafe { NonZeroUsize::new_unchecked(40) },
             split_method: midpoint_slide,
+            distance: None,
+            prune: true,
         }
     }
+    /// Sets the largest number of units in a leaf (default 40).
+    /// A smaller bucket size yields a deeper tree, with fewer distance computations per leaf.
     #[inline]
     pub fn bucket_size(&mut self, bucket_size: NonZeroUsize) -> Result<&mut Self, NodeError> {
         self.bucket_size = bucket_size;
@@ -40,12 +52,50 @@ impl<'a> TreeBuilder<'a> {
         self.bucket_size = NonZeroUsize::new(bucket_size).ok_or(NodeError::InvalidBucketSize)?;
         Ok(self)
     }
+    /// Sets the splitting method (default [`midpoint_slide`]), e.g. [`super::median`].
+    /// The splitting method affects the balance of the tree and the query performance, but not
+    /// the neighbours found.
+    /// On clustered data, [`midpoint_slide`] avoids the skinny cells of [`super::median`].
     #[inline]
     pub fn split_method(&mut self, split_method: FindSplit) -> Result<&mut Self, NodeError> {
         self.split_method = split_method;
         Ok(self)
     }
+    /// Sets a custom distance (default the squared euclidean distance), used when comparing
+    /// neighbours, e.g. the great-circle distance between units with latitude and longitude as
+    /// coordinates.
+    ///
+    /// The search skips the far side of a split, if the distance from the unit to the split is
+    /// larger than the distance to the neighbours found.
+    /// The distance to the split is computed as the distance from the unit to its projection onto
+    /// the split, i.e. the unit with the coordinate of the split dimension set to the value of
+    /// the split.
+    ///
+    /// **Note:** the pruning is correct only if this projection is a lower bound of the distance
+    /// to any unit on the far side of the split, e.g. for distances increasing in the absolute
+    /// coordinate differences.
+    /// Otherwise, e.g. for non-metric dissimilarities, or for the great-circle distance split on
+    /// longitude, the search may miss neighbours, and pruning should be turned off by
+    /// [`TreeBuilder::prune`].
+    #[inline]
+    pub fn distance(&mut self, distance: Distance<'a>) -> Result<&mut Self, NodeError> {
+        self.distance = Some(distance);
+        Ok(self)
+    }
+    /// Sets whether the search prunes branches that cannot contain any neighbours (default
+    /// `true`).
+    /// If `false`, all units of the tree are visited by each search, i.e. a brute force search,
+    /// which finds the correct neighbours for any distance.
+    #[inline]
+    pub fn prune(&mut self, prune: bool) -> Result<&mut Self, NodeError> {
+        self.prune = prune;
+        Ok(self)
+    }
     /// Creates a new k-d tree of the indices in untis, given a data matrix and a splitting method.
+    ///
+    /// Units with coincident coordinates are supported.
+    /// Each split moves at least one unit to each child, and units that cannot be separated by any
+    /// split are kept in the same leaf, which thus may contain more units than the bucket size.
     #[inline]
     pub fn build(&self, units: &mut [usize]) -> Result<Node<'a>, NodeError> {
         units
@@ -84,7 +134,7 @@ enum NodeKind<'a> {
 impl<'a> NodeKind<'a> {
     #[cfg(test)]
     #[inline]
-    fn unwrap_branch(&self) -> &Box<NodeBranch<'a>> {
+    fn unwrap_branch(&self) -> &NodeBranch<'a> {
         match self {
             NodeKind::Branch(ref branch) => branch,
             _ => panic!(),
@@ -92,7 +142,7 @@ impl<'a> NodeKind<'a> {
     }
     #[cfg(test)]
     #[inline]
-    fn unwrap_leaf(&self) -> &Box<NodeLeaf> {
+    fn unwrap_leaf(&self) -> &NodeLeaf {
         match self {
             NodeKind::Leaf(ref leaf) => leaf,
             _ => panic!(),
@@ -106,6 +156,8 @@ pub struct Node<'a> {
 
     // Common
     data: &'a Matrix<'a>,
+    distance: Option<Distance<'a>>,
+    prune: bool,
 }
 
 impl<'a> Node<'a> {
@@ -138,14 +190,15 @@ impl<'a> Node<'a> {
             data,
             bucket_size,
             split_method,
+            ..
         } = options;
         if units.len() <= bucket_size.get() {
-            return Node::new_leaf(data, units);
+            return Node::new_leaf(options, units);
         }
 
         let split = match split_method(&borders, data, &mut *units) {
             Some(s) => s,
-            None => return Self::new_leaf(data, units),
+            None => return Self::new_leaf(options, units),
         };
 
         assert!(split.dimension < data.ncol());
@@ -164,24 +217,34 @@ impl<'a> Node<'a> {
                 right_child: Box::new(Self::create(options, &mut units[split.unit..], r_borders)),
             })),
             data,
+            distance: options.distance,
+            prune: options.prune,
         }
     }
 
     #[inline]
-    fn new_leaf(data: &'a Matrix, units: &mut [usize]) -> Self {
+    fn new_leaf(options: &TreeBuilder<'a>, units: &mut [usize]) -> Self {
         Node {
             kind: NodeKind::Leaf(Box::new(NodeLeaf {
                 units: units.to_vec(),
             })),
-            data,
+            data: options.data,
+            distance: options.distance,
+            prune: options.prune,
         }
     }
 
     /// Returns a reference to the data matrix
     #[inline]
-    pub fn data(&self) -> &Matrix {
+    pub fn data(&self) -> &Matrix<'_> {
         self.data
     }
+    /// Returns the custom distance of the tree, or `None` if the squared euclidean distance is
+    /// used
+    #[inline]
+    pub fn distance(&self) -> Option<Distance<'a>> {
+        self.distance
+    }
 
     /// Tries to insert a unit into the tree.
     /// Returns error if the index does not exist in the data matrix.
@@ -240,7 +303,9 @@ impl<'a> Node<'a> {
         S: TreeSearcher,
     {
         match self.kind {
-            NodeKind::Leaf(ref leaf) => searcher.add_neighbours_from_node(&leaf.units, self.data),
+            NodeKind::Leaf(ref leaf) => {
+                searcher.add_neighbours_from_node(&leaf.units, self.data, self.distance)
+            }
 
             NodeKind::Branch(ref branch) => {
                 let unit_value = searcher.unit()[branch.dimension];
@@ -255,9 +320,17 @@ impl<'a> Node<'a> {
 
                 first_node.find_neighbours(searcher);
 
-                if !searcher.is_satisfied()
-                    || distance.powi(2) <= searcher.max_distance().unwrap_or(f64::INFINITY)
-                {
+                if !self.prune || !searcher.is_satisfied() {
+                    second_node.find_neighbours(searcher);
+                    return;
+                }
+
+                let split_distance = match self.distance {
+                    Some(d) => searcher.distance_to_split(branch.dimension, branch.value, d),
+                    None => distance.powi(2),
+                };
+
+                if split_distance <= searcher.max_distance().unwrap_or(f64::INFINITY) {
                     second_node.find_neighbours(searcher);
                 }
             }
@@ -375,7 +448,7 @@ mod tests {
             .try_bucket_size(2)?
             .build(&mut [0, 1, 2, 3])?;
 
-        assert_eq!(t.insert_unit(4).unwrap(), true);
+        assert!(t.insert_unit(4).unwrap());
         assert!(t
             .kind
             .unwrap_branch()
@@ -384,9 +457,9 @@ mod tests {
             .unwrap_leaf()
             .units
             .contains(&4));
-        assert_eq!(t.insert_unit(4).unwrap(), false);
+        assert!(!t.insert_unit(4).unwrap());
 
-        assert_eq!(t.remove_unit(1).unwrap(), true);
+        assert!(t.remove_unit(1).unwrap());
         assert!(!t
             .kind
             .unwrap_branch()
@@ -395,10 +468,10 @@ mod tests {
             .unwrap_leaf()
             .units
             .contains(&1));
-        assert_eq!(t.remove_unit(1).unwrap(), false);
+        assert!(!t.remove_unit(1).unwrap());
 
-        assert!(t.insert_unit(10).is_err());
-        assert!(t.remove_unit(10).is_err());
+        t.insert_unit(10).unwrap_err();
+        t.remove_unit(10).unwrap_err();
 
         Ok(())
     }
@@ -10,7 +10,7 @@
 // You should have received a copy of the GNU Affero General Public License along with this
 // program. If not, see <https://www.gnu.org/licenses/>.
 
-use super::Node;
+use super::{Distance, Node};
 use crate::{InputError, Matrix, Probabilities};
 use std::cmp::Ordering;
 use std::num::NonZeroUsize;
@@ -19,7 +19,13 @@ pub(super) trait TreeSearcher {
     fn unit(&self) -> &[f64];
     fn max_distance(&self) -> Option<f64>;
     fn is_satisfied(&self) -> bool;
-    fn add_neighbours_from_node(&mut self, ids: &[usize], data: &Matrix);
+    fn add_neighbours_from_node(
+        &mut self,
+        ids: &[usize],
+        data: &Matrix,
+        distance: Option<Distance>,
+    );
+    fn distance_to_split(&mut self, dimension: usize, value: f64, distance: Distance) -> f64;
 }
 
 /// A struct used for searching the nearest neighbour of a unit.
@@ -29,6 +35,8 @@ pub struct Searcher {
     neighbours: Vec<usize>,
     distances: Vec<f64>,
     n_neighbours: NonZeroUsize,
+    // Coordinates of a compared unit, used by custom distances
+    buffer: Vec<f64>,
 }
 
 impl TreeSearcher for Searcher {
@@ -44,18 +52,23 @@ impl TreeSearcher for Searcher {
     fn is_satisfied(&self) -> bool {
         self.neighbours.len() >= self.n_neighbours.get()
     }
-    fn add_neighbours_from_node(&mut self, ids: &[usize], data: &Matrix) {
+    fn add_neighbours_from_node(
+        &mut self,
+        ids: &[usize],
+        data: &Matrix,
+        distance: Option<Distance>,
+    ) {
         if ids.is_empty() {
             return;
         }
 
         if self.n_neighbours.get() == 1 {
-            self.assess_units_1(ids, data);
+            self.assess_units_1(ids, data, distance, |_| true);
             return;
         }
 
         let original_length = self.neighbours.len();
-        self.assess_units(ids, data);
+        self.assess_units(ids, data, distance);
 
         // If store size hasn't changed, we haven't added any new units
         if self.neighbours.len() == original_length {
@@ -65,6 +78,12 @@ impl TreeSearcher for Searcher {
         self.sort_neighbours();
         self.truncate_neighbours();
     }
+    #[inline]
+    fn distance_to_split(&mut self, dimension: usize, value: f64, distance: Distance) -> f64 {
+        self.buffer.copy_from_slice(&self.unit);
+        self.buffer[dimension] = value;
+        distance(&self.unit, &self.buffer)
+    }
 }
 
 impl Searcher {
@@ -79,6 +98,7 @@ impl Searcher {
             neighbours: Vec::<usize>::with_capacity(data.nrow()),
             distances: vec![0.0f64; data.nrow()],
             n_neighbours,
+            buffer: vec![0.0f64; data.ncol()],
         }
     }
     /// Constructs a new k-d tree searcher, for finding closest neighbour
@@ -122,6 +142,31 @@ impl Searcher {
         self.reset();
         Ok(())
     }
+    /// Finds the nearest neighbour, amongst the units not flagged by `excluded`, of a unit
+    /// positioned at the vector constructed by the iterator.
+    /// Excluded units are skipped during the search, and branches are pruned by the distance to
+    /// the closest non-excluded unit found, so no list of excluded neighbours is built.
+    /// Returns the nearest neighbour, or `None` if all units in the tree are excluded.
+    /// In case of ties, all equidistant neighbours are available through
+    /// [`Searcher::neighbours`].
+    pub fn find_nearest_excluding<'a, I>(
+        &mut self,
+        node: &Node,
+        iter: I,
+        excluded: &[bool],
+    ) -> Result<Option<usize>, InputError>
+    where
+        I: ExactSizeIterator<Item = &'a f64>,
+    {
+        InputError::check_sizes(excluded.len(), node.data().nrow())?;
+        self.set_unit_from_iter(iter, usize::MAX)?;
+        let mut tree_searcher = TreeSearcherExcluding {
+            searcher: self,
+            excluded,
+        };
+        node.find_neighbours(&mut tree_searcher);
+        Ok(self.neighbours.first().copied())
+    }
     /// Set the number of neighbours to search for.
     #[inline]
     pub fn set_n_neighbours(&mut self, n: NonZeroUsize) {
@@ -145,15 +190,36 @@ impl Searcher {
         self.distances[idx] = distance;
     }
     #[inline]
-    fn assess_units_1(&mut self, ids: &[usize], data: &Matrix) {
+    fn distance_to_unit(&mut self, id: usize, data: &Matrix, distance: Option<Distance>) -> f64 {
+        match distance {
+            Some(d) => {
+                self.buffer
+                    .iter_mut()
+                    .zip(data.row_iter(id))
+                    .for_each(|(a, &b)| *a = b);
+                d(&self.buffer, &self.unit)
+            }
+            None => data.distance_to_row(id, &self.unit),
+        }
+    }
+    #[inline]
+    fn assess_units_1<F>(
+        &mut self,
+        ids: &[usize],
+        data: &Matrix,
+        distance: Option<Distance>,
+        include: F,
+    ) where
+        F: Fn(usize) -> bool,
+    {
         let mut current_max = self.max_distance().unwrap_or(f64::INFINITY);
 
         ids.iter().for_each(|&id| {
-            if self.unit_id == id {
+            if self.unit_id == id || !include(id) {
                 return;
             }
 
-            let distance = data.distance_to_row(id, &self.unit);
+            let distance = self.distance_to_unit(id, data, distance);
 
             if distance < current_max {
                 self.reset();
@@ -165,7 +231,7 @@ impl Searcher {
         });
     }
     #[inline]
-    fn assess_units(&mut self, ids: &[usize], data: &Matrix) {
+    fn assess_units(&mut self, ids: &[usize], data: &Matrix, distance: Option<Distance>) {
         // The case of when the store isn't filled yet
         // node_max will store the max _added_ distance from the node
         let mut node_max: f64 = self.max_distance().unwrap_or(0.0);
@@ -175,7 +241,7 @@ impl Searcher {
                 return;
             }
 
-            let distance = data.distance_to_row(id, &self.unit);
+            let distance = self.distance_to_unit(id, data, distance);
 
             // We should add a unit only in two circumstances:
             // - if the unit is closer than the current largest dist
@@ -189,7 +255,7 @@ impl Searcher {
             }
         });
     }
-    /// Get the squared euclidean distance to the `k`th neighbour
+    /// Get the distance to the `k`th neighbour, by default the squared euclidean distance
     #[inline]
     pub fn distance_k(&self, k: usize) -> f64 {
         self.distances[self.neighbours[k]]
@@ -216,6 +282,36 @@ impl Searcher {
     }
 }
 
+pub(super) struct TreeSearcherExcluding<'a> {
+    searcher: &'a mut Searcher,
+    excluded: &'a [bool],
+}
+
+impl<'a> TreeSearcher for TreeSearcherExcluding<'a> {
+    fn unit(&self) -> &[f64] {
+        self.searcher.unit()
+    }
+    fn max_distance(&self) -> Option<f64> {
+        self.searcher.max_distance()
+    }
+    fn is_satisfied(&self) -> bool {
+        !self.searcher.neighbours.is_empty()
+    }
+    fn add_neighbours_from_node(
+        &mut self,
+        ids: &[usize],
+        data: &Matrix,
+        distance: Option<Distance>,
+    ) {
+        let excluded = self.excluded;
+        self.searcher
+            .assess_units_1(ids, data, distance, |id| !excluded[id]);
+    }
+    fn distance_to_split(&mut self, dimension: usize, value: f64, distance: Distance) -> f64 {
+        self.searcher.distance_to_split(dimension, value, distance)
+    }
+}
+
 pub struct SearcherWeighted {
     searcher: Searcher,
     weights: Vec<f64>,
@@ -304,7 +400,7 @@ impl SearcherWeighted {
         self.searcher.neighbours.clear();
     }
 
-    /// Get the squared euclidean distance to the `k`th neighbour
+    /// Get the distance to the `k`th neighbour, by default the squared euclidean distance
     #[inline]
     pub fn distance_k(&self, k: usize) -> f64 {
         self.searcher.distance_k(k)
@@ -359,7 +455,7 @@ impl<'a> TreeSearcherWeighted<'a> {
         weight
     }
 
-    fn assess_units(&mut self, ids: &[usize], data: &Matrix) {
+    fn assess_units(&mut self, ids: &[usize], data: &Matrix, distance: Option<Distance>) {
         // The case of when the store isn't filled yet
         // node_max will store the max _added_ distance from the node
         let mut node_max: f64 = self.max_distance().unwrap_or(0.0);
@@ -369,7 +465,7 @@ impl<'a> TreeSearcherWeighted<'a> {
                 return;
             }
 
-            let distance = data.distance_to_row(id, &self.base().unit);
+            let distance = self.base_mut().distance_to_unit(id, data, distance);
 
             // We should add a unit only in two circumstances:
             // - if the unit is closer than the current largest dist
@@ -419,13 +515,18 @@ impl<'a> TreeSearcher for TreeSearcherWeighted<'a> {
     fn is_satisfied(&self) -> bool {
         self.total_weight >= 1.0
     }
-    fn add_neighbours_from_node(&mut self, ids: &[usize], data: &Matrix) {
+    fn add_neighbours_from_node(
+        &mut self,
+        ids: &[usize],
+        data: &Matrix,
+        distance: Option<Distance>,
+    ) {
         if ids.is_empty() {
             return;
         }
 
         let original_length = self.base().neighbours.len();
-        self.assess_units(ids, data);
+        self.assess_units(ids, data, distance);
 
         // If store size hasn't changed, we haven't added any new units
         if self.base().neighbours.len() == original_length {
@@ -435,4 +536,8 @@ impl<'a> TreeSearcher for TreeSearcherWeighted<'a> {
         self.base_mut().sort_neighbours();
         self.truncate_neighbours();
     }
+    fn distance_to_split(&mut self, dimension: usize, value: f64, distance: Distance) -> f64 {
+        self.base_mut()
+            .distance_to_split(dimension, value, distance)
+    }
 }
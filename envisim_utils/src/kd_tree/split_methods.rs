@@ -24,7 +24,7 @@ pub type FindSplit = fn(&[(f64, f64)], &Matrix, &mut [usize]) -> Option<Split>;
 
 /// The midpoint slide splitting method.
 /// Returns a split, where units `[0..unit)` have values < `value`, and units [unit,..) have values
-/// > `value`.
+/// \> `value`.
 /// If `leq` is `true`, the first group also contains equal elements, otherwise the right group
 /// contains equal elements.
 ///
@@ -90,6 +90,63 @@ pub fn midpoint_slide(borders: &[(f64, f64)], data: &Matrix, units: &mut [usize]
 
     None
 }
+/// The median splitting method.
+/// Splits the dimension with the largest spread at the median value of the units, yielding a
+/// balanced tree.
+/// Returns a split as [`midpoint_slide`].
+/// Units equal to the median are placed in the right group, unless the median equals the
+/// smallest value, in which case they are placed in the left group.
+/// On clustered data, the median may split dense clusters, giving skinny cells, in which case
+/// [`midpoint_slide`] may be preferred.
+///
+/// Returns `None` if no such split exists
+pub fn median(borders: &[(f64, f64)], data: &Matrix, units: &mut [usize]) -> Option<Split> {
+    assert_eq!(data.ncol(), borders.len());
+
+    if units.is_empty() {
+        return None;
+    }
+
+    let mut sorted_dims: Vec<(usize, f64)> =
+        borders.iter().map(|&b| b.1 - b.0).enumerate().collect();
+    sorted_dims.sort_unstable_by(|a, b| (b.1).partial_cmp(&a.1).unwrap());
+
+    let mid = units.len() / 2;
+
+    for dims in sorted_dims.iter() {
+        if dims.1 <= f64::EPSILON {
+            return None;
+        }
+
+        let dimension = dims.0;
+        units.select_nth_unstable_by(mid, |&a, &b| {
+            data[(a, dimension)].total_cmp(&data[(b, dimension)])
+        });
+
+        let mut split = Split {
+            unit: 0,
+            dimension,
+            value: data[(units[mid], dimension)],
+            leq: false,
+        };
+
+        midpoint_slide_sort(data, units, &mut split);
+
+        if split.unit == 0 {
+            split.leq = true;
+            midpoint_slide_sort(data, units, &mut split);
+
+            if split.unit == units.len() {
+                continue;
+            }
+        }
+
+        return Some(split);
+    }
+
+    None
+}
+
 /// Sorts the `units` in two ranges, such that all units with a value `< split.value` goes first.
 /// Returns the tuple `(left_max, right_min)`, where
 /// - `left_max` is the largest value in the `0..split_unit` set
@@ -134,7 +191,7 @@ mod tests {
     fn midpoint_slide() {
         let v = vec![0.0, 1.0, 2.0, 13.0];
         let m = Matrix::new(&v, 4);
-        let split = super::midpoint_slide(&vec![(0.0, 13.0)], &m, &mut vec![0, 1, 2, 3]).unwrap();
+        let split = super::midpoint_slide(&[(0.0, 13.0)], &m, &mut [0, 1, 2, 3]).unwrap();
         assert_eq!(split.unit, 3);
         assert_eq!(split.dimension, 0);
         assert_eq!(split.value, 6.5);
@@ -142,15 +199,39 @@ mod tests {
         let v = vec![0.0, 1.0, 2.0, 13.0, 0.0, 10.0, 20.0, 30.0];
         let m = Matrix::new(&v, 4);
         let split =
-            super::midpoint_slide(&vec![(0.0, 13.0), (0.0, 30.0)], &m, &mut vec![0, 1, 2, 3])
-                .unwrap();
+            super::midpoint_slide(&[(0.0, 13.0), (0.0, 30.0)], &m, &mut [0, 1, 2, 3]).unwrap();
         assert_eq!(split.unit, 2);
         assert_eq!(split.dimension, 1);
         assert_eq!(split.value, 15.0);
 
         let v = vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0];
         let m = Matrix::new(&v, 3);
-        let split = super::midpoint_slide(&vec![(0.0, 0.0), (1.0, 1.0)], &m, &mut vec![0, 1, 2]);
+        let split = super::midpoint_slide(&[(0.0, 0.0), (1.0, 1.0)], &m, &mut [0, 1, 2]);
+        assert!(split.is_none());
+    }
+
+    #[test]
+    fn median() {
+        let v = vec![0.0, 1.0, 2.0, 13.0];
+        let m = Matrix::new(&v, 4);
+        let mut units = [0, 1, 2, 3];
+        let split = super::median(&[(0.0, 13.0)], &m, &mut units).unwrap();
+        assert_eq!(split.unit, 2);
+        assert_eq!(split.value, 2.0);
+        assert!(!split.leq);
+        units[..2].sort_unstable();
+        assert_eq!(units[..2], [0, 1]);
+
+        // The median equals the smallest value
+        let v = vec![1.0, 1.0, 1.0, 2.0];
+        let m = Matrix::new(&v, 4);
+        let split = super::median(&[(1.0, 2.0)], &m, &mut [0, 1, 2, 3]).unwrap();
+        assert_eq!(split.unit, 3);
+        assert!(split.leq);
+
+        let v = vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0];
+        let m = Matrix::new(&v, 3);
+        let split = super::median(&[(0.0, 0.0), (1.0, 1.0)], &m, &mut [0, 1, 2]);
         assert!(split.is_none());
     }
 }
@@ -22,5 +22,6 @@ pub mod utils;
 
 pub use error::InputError;
 pub use indices::{Indices, IndicesError};
-pub use matrix::Matrix;
-pub use probabilities::Probabilities;
+pub use matrix::{Matrix, MatrixLike, SymMatrix};
+pub use probabilities::{Probabilities, ProbabilityDiagnostics};
+pub use utils::{symmetric_polynomials, weighted_symmetric_polynomials};
@@ -11,9 +11,12 @@
 // program. If not, see <https://www.gnu.org/licenses/>.
 
 //! Two different matrix containers are provided:
-//! - [`Matrix`], which is a mutable matrix owning it's own storage.
-//! - [`RefMatrix`], which provides matrix operations on a provided, immutable vector.
+//! - [`Matrix`], which either owns it's own storage, or references a provided, immutable vector.
+//! - [`SymMatrix`], a symmetric matrix storing only the upper triangle.
+//!
+//! Both implement [`MatrixLike`].
 
+use crate::error::InputError;
 use std::iter::{Skip, StepBy};
 use std::ops::{Index, IndexMut};
 use std::slice::Iter;
@@ -21,12 +24,24 @@ use std::slice::Iter;
 /// Matrix dimensions `(row, col)`
 type MatrixIndex = (usize, usize);
 
+/// Common interface of matrices indexable by `(row, col)`.
+pub trait MatrixLike: Index<MatrixIndex, Output = f64> {
+    /// Returns the number of rows in the matrix
+    fn nrow(&self) -> usize;
+    /// Returns the number of columns in the matrix
+    fn ncol(&self) -> usize;
+    /// Returns the underlying (stored) data
+    fn data(&self) -> &[f64];
+}
+
 #[allow(clippy::exhaustive_enums)]
+#[derive(Debug)]
 pub enum MatrixData<'a> {
     Mutable(Vec<f64>),
     Reference(&'a [f64]),
 }
 
+#[derive(Debug)]
 pub struct Matrix<'a> {
     data: MatrixData<'a>,
     rows: usize,
@@ -58,7 +73,7 @@ impl<'a> Matrix<'a> {
     #[inline]
     pub fn new(data: &[f64], rows: usize) -> Self {
         assert!(rows > 0);
-        assert!(data.len() % rows == 0);
+        assert!(data.len().is_multiple_of(rows));
         let cols = data.len() / rows;
         Self {
             data: MatrixData::Mutable(data.to_vec()),
@@ -70,7 +85,7 @@ impl<'a> Matrix<'a> {
     #[inline]
     pub fn from_vec(data: Vec<f64>, rows: usize) -> Self {
         assert!(rows > 0);
-        assert!(data.len() % rows == 0);
+        assert!(data.len().is_multiple_of(rows));
         let cols = data.len() / rows;
         Self {
             data: MatrixData::Mutable(data),
@@ -89,11 +104,68 @@ impl<'a> Matrix<'a> {
             cols,
         }
     }
+    /// Constructs a new mutable matrix of dimensions `(rows, cols)`, by moving the `data`.
+    /// The `data` is interpreted as column major, i.e. the first `rows` elements make up the first
+    /// column.
+    /// Returns an error if any dimension is zero, or if `data.len()` is not equal to `rows * cols`.
+    ///
+    /// # Examples
+    /// ```
+    /// use envisim_utils::Matrix;
+    ///
+    /// let m = Matrix::try_from_vec(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], (2, 3))?;
+    /// assert_eq!(m[(1, 0)], 2.0);
+    /// assert_eq!(m[(0, 2)], 5.0);
+    /// assert!(Matrix::try_from_vec(vec![1.0, 2.0, 3.0], (2, 2)).is_err());
+    /// # Ok::<(), envisim_utils::InputError>(())
+    /// ```
+    #[inline]
+    pub fn try_from_vec(data: Vec<f64>, (rows, cols): MatrixIndex) -> Result<Self, InputError> {
+        InputError::check_valid_usize(rows, 0)
+            .and(InputError::check_valid_usize(cols, 0))
+            .and(InputError::check_sizes(data.len(), rows * cols))?;
+
+        Ok(Self {
+            data: MatrixData::Mutable(data),
+            rows,
+            cols,
+        })
+    }
+    /// Constructs a new mutable matrix, filled with zeros
+    ///
+    /// # Examples
+    /// ```
+    /// use envisim_utils::Matrix;
+    ///
+    /// let m = Matrix::zeros((2, 3));
+    /// assert_eq!(m.dim(), (2, 3));
+    /// assert!(m.data().iter().all(|&x| x == 0.0));
+    /// ```
+    #[inline]
+    pub fn zeros(dim: MatrixIndex) -> Self {
+        Self::from_value(0.0, dim)
+    }
+    /// Constructs a new mutable identity matrix of dimensions `(n, n)`
+    ///
+    /// # Examples
+    /// ```
+    /// use envisim_utils::Matrix;
+    ///
+    /// let m = Matrix::identity(3);
+    /// assert_eq!(m[(1, 1)], 1.0);
+    /// assert_eq!(m[(1, 2)], 0.0);
+    /// ```
+    #[inline]
+    pub fn identity(n: usize) -> Self {
+        let mut m = Self::zeros((n, n));
+        (0..n).for_each(|i| m[(i, i)] = 1.0);
+        m
+    }
     /// Constructs a new reference matrix by `data`
     #[inline]
     pub fn from_ref(data: &'a [f64], rows: usize) -> Self {
         assert!(rows > 0);
-        assert!(data.len() % rows == 0);
+        assert!(data.len().is_multiple_of(rows));
         let cols = data.len() / rows;
         Self {
             data: MatrixData::Reference(data),
@@ -114,12 +186,12 @@ impl<'a> Matrix<'a> {
     #[inline]
     pub fn data_mut(&mut self) -> &mut [f64] {
         match self.data {
-            MatrixData::Mutable(ref mut v) => return v.as_mut_slice(),
+            MatrixData::Mutable(ref mut v) => v.as_mut_slice(),
             _ => {
                 self.to_mut();
-                return self.data_mut();
+                self.data_mut()
             }
-        };
+        }
     }
     /// Returns the number of rows in the matrix
     #[inline]
@@ -138,7 +210,7 @@ impl<'a> Matrix<'a> {
     }
     /// Returns an iterator on the row
     #[inline]
-    pub fn row_iter(&self, row: usize) -> MatrixIterator {
+    pub fn row_iter(&self, row: usize) -> MatrixIterator<'_> {
         assert!(row < self.nrow());
         MatrixIterator {
             iter: self.data().iter().skip(row).step_by(self.nrow()),
@@ -150,7 +222,7 @@ impl<'a> Matrix<'a> {
     /// Returns an iterator on the column
     #[allow(clippy::iter_skip_zero)]
     #[inline]
-    pub fn col_iter(&self, col: usize) -> MatrixIterator {
+    pub fn col_iter(&self, col: usize) -> MatrixIterator<'_> {
         assert!(col < self.ncol());
         MatrixIterator {
             iter: self.data()[(self.nrow() * col)..(self.nrow() * (col + 1))]
@@ -162,6 +234,34 @@ impl<'a> Matrix<'a> {
             count: 0,
         }
     }
+    /// Constructs a new mutable matrix by extracting the `rows` and `cols` of the matrix, in the
+    /// order given.
+    /// Returns an error if `rows` or `cols` is empty, or if any index is out of range.
+    ///
+    /// # Examples
+    /// ```
+    /// use envisim_utils::Matrix;
+    ///
+    /// let m = Matrix::new(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0], 3);
+    /// let s = m.submatrix(&[0, 2], &[1, 2])?;
+    /// assert_eq!(s.data(), &[4.0, 6.0, 7.0, 9.0]);
+    /// assert!(m.submatrix(&[0, 3], &[0]).is_err());
+    /// # Ok::<(), envisim_utils::InputError>(())
+    /// ```
+    pub fn submatrix(&self, rows: &[usize], cols: &[usize]) -> Result<Matrix<'static>, InputError> {
+        InputError::check_empty(rows).and(InputError::check_empty(cols))?;
+        rows.iter()
+            .try_for_each(|&r| InputError::check_range_usize(r, 0, self.rows - 1))?;
+        cols.iter()
+            .try_for_each(|&c| InputError::check_range_usize(c, 0, self.cols - 1))?;
+
+        let data: Vec<f64> = cols
+            .iter()
+            .flat_map(|&c| rows.iter().map(move |&r| self[(r, c)]))
+            .collect();
+
+        Ok(Matrix::from_vec(data, rows.len()))
+    }
     /// Resizes the matrix, without guaranteeing the preservation of any data.
     /// If the matrix is a reference matrix, the matrix is transformed to a mutable matrix.
     #[inline]
@@ -305,7 +405,7 @@ impl<'a> Matrix<'a> {
     }
     /// Performes the calculation of matrices self * mat
     #[inline]
-    pub fn mult(&self, mat: &Matrix) -> Matrix {
+    pub fn mult(&self, mat: &Matrix) -> Matrix<'_> {
         assert!(self.ncol() == mat.nrow());
         let mut prod = Vec::<f64>::with_capacity(self.nrow() * mat.ncol());
 
@@ -335,11 +435,25 @@ impl<'a> IndexMut<MatrixIndex> for Matrix<'a> {
             MatrixData::Mutable(ref mut v) => unsafe { v.get_unchecked_mut(idx) },
             _ => {
                 self.to_mut();
-                return self.index_mut(midx);
+                self.index_mut(midx)
             }
         }
     }
 }
+impl<'a> MatrixLike for Matrix<'a> {
+    #[inline]
+    fn nrow(&self) -> usize {
+        self.rows
+    }
+    #[inline]
+    fn ncol(&self) -> usize {
+        self.cols
+    }
+    #[inline]
+    fn data(&self) -> &[f64] {
+        Matrix::data(self)
+    }
+}
 impl<'a> Clone for Matrix<'a> {
     #[inline]
     fn clone(&self) -> Self {
@@ -356,6 +470,105 @@ impl<'a> Clone for Matrix<'a> {
     }
 }
 
+/// A symmetric square matrix, storing only the upper triangle (column major).
+/// Indexing by `(i, j)` and `(j, i)` refers to the same element.
+#[derive(Clone, Debug)]
+pub struct SymMatrix {
+    data: Vec<f64>,
+    dim: usize,
+}
+
+impl SymMatrix {
+    #[inline]
+    fn matrix_index(&self, (row, col): MatrixIndex) -> usize {
+        assert!(col < self.dim, "col {} larger than max {}", col, self.dim);
+        assert!(row < self.dim, "row {} larger than max {}", row, self.dim);
+        let (lo, hi) = if row <= col { (row, col) } else { (col, row) };
+        hi * (hi + 1) / 2 + lo
+    }
+    /// Constructs a new symmetric matrix of dimensions `(dim, dim)`, filled with `data`
+    #[inline]
+    pub fn from_value(data: f64, dim: usize) -> Self {
+        assert!(dim > 0);
+        Self {
+            data: vec![data; dim * (dim + 1) / 2],
+            dim,
+        }
+    }
+    /// Constructs a new symmetric matrix from a square matrix.
+    /// Returns an error if the matrix is not square, or if any pair of elements `(i, j)` and
+    /// `(j, i)` differ by more than `eps`.
+    /// The stored value is taken from the upper triangle.
+    ///
+    /// # Examples
+    /// ```
+    /// use envisim_utils::{Matrix, SymMatrix};
+    ///
+    /// let m = Matrix::new(&[1.0, 0.5, 0.5, 2.0], 2);
+    /// let s = SymMatrix::try_from_matrix(&m, 1e-12)?;
+    /// assert_eq!(s[(0, 1)], s[(1, 0)]);
+    /// assert!(SymMatrix::try_from_matrix(&Matrix::new(&[1.0, 0.4, 0.5, 2.0], 2), 1e-12).is_err());
+    /// # Ok::<(), envisim_utils::InputError>(())
+    /// ```
+    pub fn try_from_matrix(matrix: &Matrix, eps: f64) -> Result<Self, InputError> {
+        let dim = matrix.nrow();
+        InputError::check_sizes(matrix.ncol(), dim)?;
+        let mut data = Vec::<f64>::with_capacity(dim * (dim + 1) / 2);
+
+        for col in 0..dim {
+            for row in 0..=col {
+                if (matrix[(row, col)] - matrix[(col, row)]).abs() > eps {
+                    return Err(InputError::NotSymmetric(row, col));
+                }
+
+                data.push(matrix[(row, col)]);
+            }
+        }
+
+        Ok(Self { data, dim })
+    }
+    /// Returns the stored upper triangle (column major).
+    #[inline]
+    pub fn data(&self) -> &[f64] {
+        &self.data
+    }
+    /// Returns the number of rows (and columns) in the matrix
+    #[inline]
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+}
+
+impl Index<MatrixIndex> for SymMatrix {
+    type Output = f64;
+
+    #[inline]
+    fn index(&self, midx: MatrixIndex) -> &f64 {
+        unsafe { self.data.get_unchecked(self.matrix_index(midx)) }
+    }
+}
+impl IndexMut<MatrixIndex> for SymMatrix {
+    #[inline]
+    fn index_mut(&mut self, midx: MatrixIndex) -> &mut f64 {
+        let idx = self.matrix_index(midx);
+        unsafe { self.data.get_unchecked_mut(idx) }
+    }
+}
+impl MatrixLike for SymMatrix {
+    #[inline]
+    fn nrow(&self) -> usize {
+        self.dim
+    }
+    #[inline]
+    fn ncol(&self) -> usize {
+        self.dim
+    }
+    #[inline]
+    fn data(&self) -> &[f64] {
+        &self.data
+    }
+}
+
 pub struct MatrixIterator<'a> {
     iter: StepBy<Skip<Iter<'a, f64>>>,
     dim: MatrixIndex,
@@ -39,6 +39,20 @@ pub fn pps_from_slice(arr: &[f64]) -> Result<Probabilities, InputError> {
 /// Returns an error if any value is non-positive.
 ///
 /// The caluclations are done by iteratively rescaling the inclusion probabilities.
+/// Units whose probability would exceed `1.0` are given probability `1.0`, and the remaining
+/// sample size is redistributed by rescaling all other units by a common factor.
+/// Hence, the relative order of the sizes is preserved: if `arr[i] < arr[j]`, then the
+/// probability of `i` is at most that of `j`, the units given probability `1.0` are the largest
+/// units, and the probabilities of the remaining units are exactly proportional to size.
+///
+/// # Examples
+/// ```
+/// use envisim_utils::pips::pips_from_slice;
+///
+/// let p = pips_from_slice(&[1.0, 2.0, 3.0, 20.0], 2)?;
+/// assert_eq!(p.data(), &[1.0 / 6.0, 2.0 / 6.0, 3.0 / 6.0, 1.0]);
+/// # Ok::<(), envisim_utils::InputError>(())
+/// ```
 pub fn pips_from_slice(arr: &[f64], sample_size: usize) -> Result<Probabilities, InputError> {
     if arr.is_empty() {
         return Probabilities::new(0, 0.0);
@@ -52,36 +66,167 @@ pub fn pips_from_slice(arr: &[f64], sample_size: usize) -> Result<Probabilities,
         InputError::check_range_f64(x, 0.0, f64::INFINITY).and(InputError::check_valid_f64(x, 0.0))
     })?;
 
-    let mut n = usize_to_f64(sample_size);
-
-    let mut pips = Probabilities::new(arr.len(), 0.0)?;
-    let mut failed: bool = true;
+    let (pips, _) = pips_rescale(arr, usize_to_f64(sample_size), 0.0)?;
+    Probabilities::with_values(&pips)
+}
 
-    while failed && n > 0.0 {
-        failed = false;
-        let sum: f64 = arr
+/// Probabilities proportional to size, summing to `n`, by iterative rescaling.
+/// Units whose probability would be at least `1.0 - eps` are given probability `1.0`, and the
+/// remaining sample size is redistributed by rescaling all other units by a common factor, until
+/// no more units are given probability `1.0`.
+/// Also returns the number of units given probability `1.0`.
+/// Returns [`InputError::TooManyCertaintyUnits`] if more than `n` units are given probability
+/// `1.0`, as the design is then infeasible.
+/// This can only occur for a positive `eps`, as units with a rescaled size of at least `1.0`
+/// together have sizes at most the remaining sample size.
+/// The sizes are not validated, see [`pips_from_slice`] for a checked version.
+///
+/// # Examples
+/// ```
+/// use envisim_utils::pips::pips_rescale;
+///
+/// let (p, n_certain) = pips_rescale(&[1.0, 2.0, 3.0, 20.0], 2.0, 0.0)?;
+/// assert_eq!(p, vec![1.0 / 6.0, 2.0 / 6.0, 3.0 / 6.0, 1.0]);
+/// assert_eq!(n_certain, 1);
+/// # Ok::<(), envisim_utils::InputError>(())
+/// ```
+pub fn pips_rescale(sizes: &[f64], n: f64, eps: f64) -> Result<(Vec<f64>, usize), InputError> {
+    let mut probabilities = vec![0.0; sizes.len()];
+    let mut certain = vec![false; sizes.len()];
+    let mut remaining_n = n;
+    let mut n_certain: usize = 0;
+    let mut changed = true;
+
+    while changed && remaining_n > 0.0 {
+        changed = false;
+        let remaining_total: f64 = sizes
             .iter()
-            .enumerate()
-            .filter(|(i, _)| pips[*i] < 1.0)
-            .fold(0.0, |acc, (_, &x)| acc + x);
-        let curr_n = n;
-
-        arr.iter().enumerate().for_each(|(i, &x)| {
-            if pips[i] >= 1.0 {
-                return;
+            .zip(certain.iter())
+            .filter_map(|(&x, &c)| (!c).then_some(x))
+            .sum();
+
+        if remaining_total <= 0.0 {
+            break;
+        }
+
+        let scale = remaining_n / remaining_total;
+
+        for ((p, c), &x) in probabilities
+            .iter_mut()
+            .zip(certain.iter_mut())
+            .zip(sizes.iter())
+        {
+            if *c {
+                continue;
             }
 
-            let p = (x * curr_n) / sum;
-            pips[i] = p.min(1.0);
-
-            if p >= 1.0 {
-                n -= 1.0;
+            *p = x * scale;
 
-                if !failed && p > 1.0 {
-                    failed = true;
-                }
+            if *p >= 1.0 - eps {
+                *p = 1.0;
+                *c = true;
+                remaining_n -= 1.0;
+                n_certain += 1;
+                changed = true;
             }
-        });
+        }
+    }
+
+    if usize_to_f64(n_certain) > n {
+        return Err(InputError::TooManyCertaintyUnits(n_certain, n as usize));
+    }
+
+    Ok((probabilities, n_certain))
+}
+
+/// Inclusion probabilities proportional to size, with a minimum probability `floor`.
+/// Given an array of non-negative values, returns inclusion probabilities `min(max(c * x, floor),
+/// 1.0)`, where the constant `c` is chosen such that the probabilities sum to `sample_size`.
+/// Thus, every unit has probability at least `floor`, and the remaining sample size is
+/// distributed proportionally to size amongst the larger units.
+/// Returns an error if any value is negative, if `floor` is outside `[0.0, 1.0]`, if
+/// `floor * arr.len()` exceeds `sample_size`, or if `sample_size` cannot be reached, e.g. if it
+/// exceeds `arr.len()`.
+///
+/// # Examples
+/// ```
+/// use envisim_utils::pips::pips_from_slice_with_floor;
+///
+/// let p = pips_from_slice_with_floor(&[1.0, 1.0, 2.0, 4.0, 12.0], 2, 0.2, 1e-12)?;
+/// assert!(p.iter().all(|&x| x >= 0.2));
+/// assert!((p.iter().sum::<f64>() - 2.0).abs() < 1e-9);
+/// # Ok::<(), envisim_utils::InputError>(())
+/// ```
+pub fn pips_from_slice_with_floor(
+    arr: &[f64],
+    sample_size: usize,
+    floor: f64,
+    eps: f64,
+) -> Result<Probabilities, InputError> {
+    let n = usize_to_f64(sample_size);
+    let population_size = usize_to_f64(arr.len());
+
+    Probabilities::check_eps(eps)
+        .and(InputError::check_nan(floor))
+        .and(InputError::check_range_f64(floor, 0.0, 1.0))
+        .and(InputError::check_range_usize(sample_size, 0, arr.len()))
+        .and(InputError::check_range_f64(
+            floor * population_size,
+            0.0,
+            n + eps,
+        ))?;
+    arr.iter().try_for_each(|&x| {
+        InputError::check_nan(x).and(InputError::check_range_f64(x, 0.0, f64::INFINITY))
+    })?;
+    // Units of size zero can only be given the probability `floor`
+    let n_zero = usize_to_f64(arr.iter().filter(|&&x| x == 0.0).count());
+    InputError::check_range_f64(n, 0.0, population_size - n_zero * (1.0 - floor) + eps)?;
+
+    let clamped = |c: f64, x: f64| (c * x).clamp(floor, 1.0);
+    let total = |c: f64| arr.iter().fold(0.0, |acc, &x| acc + clamped(c, x));
+
+    // Find c by bisection, as the total is non-decreasing in c
+    let min_size = arr
+        .iter()
+        .filter(|&&x| x > 0.0)
+        .fold(f64::INFINITY, |acc, &x| acc.min(x));
+    let mut lo: f64 = 0.0;
+    let mut hi: f64 = if min_size.is_finite() {
+        1.0 / min_size
+    } else {
+        0.0
+    };
+
+    while hi - lo > f64::EPSILON * hi {
+        let mid = 0.5 * (lo + hi);
+
+        if total(mid) < n {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+
+    let mut pips = Probabilities::new(arr.len(), 0.0)?;
+    pips.eps = eps;
+    arr.iter()
+        .enumerate()
+        .for_each(|(i, &x)| pips[i] = clamped(hi, x));
+
+    // Remove the remaining bisection error by rescaling the units strictly between the bounds
+    let (fixed, free) = pips.iter().fold((0.0, 0.0), |(f, r), &p| {
+        if p <= floor + eps || 1.0 - eps <= p {
+            (f + p, r)
+        } else {
+            (f, r + p)
+        }
+    });
+
+    if free > 0.0 {
+        let factor = (n - fixed) / free;
+        pips.iter_mut()
+            .filter(|p| floor + eps < **p && **p < 1.0 - eps)
+            .for_each(|p| *p = (*p * factor).clamp(floor, 1.0));
     }
 
     Ok(pips)
@@ -11,9 +11,72 @@
 // program. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::error::InputError;
+use crate::pips::pips_rescale;
+use crate::utils::{mean, usize_to_f64};
 use std::ops::{Index, IndexMut};
 use std::slice::{Iter, IterMut};
 
+/// Diagnostics of a list of inclusion probabilities, see [`Probabilities::diagnose`].
+#[derive(Clone, Debug)]
+pub struct ProbabilityDiagnostics {
+    /// Indices of the (finite) negative probabilities
+    pub negative: Vec<usize>,
+    /// Indices of the (finite) probabilities larger than `1.0`
+    pub above_one: Vec<usize>,
+    /// Indices of the probabilities that are [`f64::NAN`]
+    pub nan: Vec<usize>,
+    /// Indices of the infinite probabilities
+    pub infinite: Vec<usize>,
+    /// The sum of the finite probabilities
+    pub sum: f64,
+    /// The distance of `sum` to the nearest integer
+    pub integer_distance: f64,
+    /// `true` if `integer_distance` is at most the `eps` used in the diagnosis
+    pub integer_sum: bool,
+}
+
+impl ProbabilityDiagnostics {
+    /// Returns `true` if no probability is invalid, i.e. if [`Probabilities::check`] succeeds
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.negative.is_empty()
+            && self.above_one.is_empty()
+            && self.nan.is_empty()
+            && self.infinite.is_empty()
+    }
+}
+
+impl std::fmt::Display for ProbabilityDiagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        // Lists at most 10 indices per category
+        let list = |ids: &[usize]| -> String {
+            let shown: Vec<String> = ids.iter().take(10).map(|id| id.to_string()).collect();
+            if ids.len() > 10 {
+                format!("{} [{}, ...]", ids.len(), shown.join(", "))
+            } else {
+                format!("{} [{}]", ids.len(), shown.join(", "))
+            }
+        };
+
+        writeln!(f, "negative: {}", list(&self.negative))?;
+        writeln!(f, "larger than 1: {}", list(&self.above_one))?;
+        writeln!(f, "nan: {}", list(&self.nan))?;
+        writeln!(f, "infinite: {}", list(&self.infinite))?;
+        write!(
+            f,
+            "sum: {} ({} from {}{})",
+            self.sum,
+            self.integer_distance,
+            self.sum.round(),
+            if self.integer_sum {
+                ""
+            } else {
+                ", not integer"
+            }
+        )
+    }
+}
+
 /// Container for handling inclusion probabilities.
 pub struct Probabilities {
     /// An epsilon, used for comparison of floats
@@ -63,6 +126,185 @@ impl Probabilities {
         })
     }
 
+    /// Returns the diagnostics of all `probabilities`, categorizing every invalid probability,
+    /// rather than failing on the first, as [`Probabilities::check`].
+    /// The sum is integer if it is within `eps` of the nearest integer.
+    ///
+    /// # Examples
+    /// ```
+    /// use envisim_utils::Probabilities;
+    ///
+    /// let d = Probabilities::diagnose(&[0.5, -0.1, 1.2, f64::NAN, 0.4], 1e-12);
+    /// assert!(!d.is_valid());
+    /// assert_eq!(d.negative, vec![1]);
+    /// assert_eq!(d.above_one, vec![2]);
+    /// assert_eq!(d.nan, vec![3]);
+    /// assert!(d.integer_sum);
+    /// assert!(d.to_string().contains("negative: 1 [1]"));
+    /// ```
+    pub fn diagnose(probabilities: &[f64], eps: f64) -> ProbabilityDiagnostics {
+        let mut diagnostics = ProbabilityDiagnostics {
+            negative: vec![],
+            above_one: vec![],
+            nan: vec![],
+            infinite: vec![],
+            sum: 0.0,
+            integer_distance: 0.0,
+            integer_sum: true,
+        };
+
+        for (i, &p) in probabilities.iter().enumerate() {
+            if p.is_nan() {
+                diagnostics.nan.push(i);
+            } else if p.is_infinite() {
+                diagnostics.infinite.push(i);
+            } else {
+                diagnostics.sum += p;
+
+                if p < 0.0 {
+                    diagnostics.negative.push(i);
+                } else if p > 1.0 {
+                    diagnostics.above_one.push(i);
+                }
+            }
+        }
+
+        diagnostics.integer_distance = (diagnostics.sum - diagnostics.sum.round()).abs();
+        diagnostics.integer_sum = diagnostics.integer_distance <= eps;
+        diagnostics
+    }
+
+    /// Returns inclusion probabilities proportional to the integer `counts`, e.g. the number of
+    /// employees, with sample size `n`.
+    /// Units are given probability 1.0 if their count is at least the remaining total count
+    /// divided by the remaining sample size, repeatedly, and the remaining units are given
+    /// probabilities proportional to their counts.
+    /// The certainty units are found by exact integer arithmetic, thus avoiding precision issues
+    /// in the sum, as in [`crate::pips::pips_from_slice`].
+    /// Returns error if `n` is zero, or larger than the number of units with a positive count
+    /// (which includes the case where the total count is zero).
+    ///
+    /// # Examples
+    /// ```
+    /// use envisim_utils::Probabilities;
+    ///
+    /// let p = Probabilities::from_counts(&[1, 1, 2, 16], 2)?;
+    /// assert_eq!(p, vec![0.25, 0.25, 0.5, 1.0]);
+    /// # Ok::<(), envisim_utils::InputError>(())
+    /// ```
+    pub fn from_counts(counts: &[u64], n: usize) -> Result<Vec<f64>, InputError> {
+        InputError::check_range_usize(n, 1, counts.iter().filter(|&&c| c > 0).count())?;
+
+        let mut certain = vec![false; counts.len()];
+        let mut remaining_n = n;
+        let mut remaining_total: u128 = counts.iter().map(|&c| u128::from(c)).sum();
+
+        loop {
+            let mut changed = false;
+
+            for (c, is_certain) in counts.iter().zip(certain.iter_mut()) {
+                let c = u128::from(*c);
+
+                if !*is_certain && remaining_n > 0 && c * remaining_n as u128 >= remaining_total {
+                    *is_certain = true;
+                    remaining_n -= 1;
+                    remaining_total -= c;
+                    changed = true;
+                }
+            }
+
+            if !changed || remaining_n == 0 {
+                break;
+            }
+        }
+
+        // The counts may exceed the range of usize_to_f64, hence they are converted by casting,
+        // where only the floating point precision is lost
+        let scale = usize_to_f64(remaining_n) / remaining_total.max(1) as f64;
+
+        Ok(counts
+            .iter()
+            .zip(certain.iter())
+            .map(|(&c, &is_certain)| {
+                if is_certain {
+                    1.0
+                } else {
+                    (c as f64 * scale).min(1.0)
+                }
+            })
+            .collect())
+    }
+
+    /// Returns inclusion probabilities proportional to size, with sample size `n`, where the size
+    /// of unit `i` is given by `size_fn(i)`, for `i` in `0..n_units`.
+    /// The size function is evaluated once per unit, e.g. computing the size from several columns
+    /// of a frame.
+    /// Units whose probability would exceed `1.0` (within `eps`) are given probability `1.0`,
+    /// and the remaining sample size is redistributed among the other units, as in
+    /// [`crate::pips::pips_from_slice`].
+    /// Units of size zero are given probability zero.
+    /// Returns error if any size is negative or not finite, or if `n` is zero or larger than the
+    /// number of units with a positive size.
+    /// Returns [`InputError::TooManyCertaintyUnits`] if more than `n` units would be given
+    /// probability `1.0`, e.g. for a tiny `n` and a large `eps`, see
+    /// [`crate::pips::pips_rescale`].
+    ///
+    /// # Examples
+    /// ```
+    /// use envisim_utils::utils::usize_to_f64;
+    /// use envisim_utils::Probabilities;
+    ///
+    /// let p = Probabilities::from_size_fn(4, |i| usize_to_f64(i * i), 2, 1e-12)?;
+    /// assert_eq!(p, vec![0.0, 0.2, 0.8, 1.0]);
+    /// # Ok::<(), envisim_utils::InputError>(())
+    /// ```
+    pub fn from_size_fn<F>(
+        n_units: usize,
+        size_fn: F,
+        n: usize,
+        eps: f64,
+    ) -> Result<Vec<f64>, InputError>
+    where
+        F: Fn(usize) -> f64,
+    {
+        Self::check_eps(eps)?;
+        let sizes: Vec<f64> = (0..n_units).map(size_fn).collect();
+        sizes.iter().try_for_each(|&x| {
+            InputError::check_nan(x).and(InputError::check_range_f64(x, 0.0, f64::MAX))
+        })?;
+        InputError::check_range_usize(n, 1, sizes.iter().filter(|&&x| x > 0.0).count())?;
+
+        Ok(pips_rescale(&sizes, usize_to_f64(n), eps)?.0)
+    }
+
+    /// Returns the coefficient of variation of the `probabilities`, `sd / mean`, where `sd` is the
+    /// population standard deviation (dividing by `N`).
+    /// The coefficient is zero for an equal probability design, and a large coefficient signals a
+    /// risk of a large design effect.
+    /// Returns error if `probabilities` is empty, or if any value is not a valid probability.
+    /// Returns [`f64::NAN`] if all probabilities are zero.
+    ///
+    /// # Examples
+    /// ```
+    /// use envisim_utils::Probabilities;
+    ///
+    /// assert_eq!(Probabilities::coefficient_of_variation(&[0.2; 4])?, 0.0);
+    /// let cv = Probabilities::coefficient_of_variation(&[0.1, 0.3])?;
+    /// assert!((cv - 0.5).abs() < 1e-12);
+    /// # Ok::<(), envisim_utils::InputError>(())
+    /// ```
+    pub fn coefficient_of_variation(probabilities: &[f64]) -> Result<f64, InputError> {
+        InputError::check_empty(probabilities).and(Self::check(probabilities))?;
+
+        let mean = mean(probabilities);
+        let variance = probabilities
+            .iter()
+            .fold(0.0, |acc, p| acc + (p - mean).powi(2))
+            / usize_to_f64(probabilities.len());
+
+        Ok(variance.sqrt() / mean)
+    }
+
     /// Returns error if the epsilon is outside the range (0.0..1.0)
     #[inline]
     pub fn check_eps(eps: f64) -> Result<f64, InputError> {
@@ -71,6 +313,65 @@ impl Probabilities {
             .map(|_| eps)
     }
 
+    /// Returns error if the probabilities are not (weakly) increasing with `sizes`, i.e. if any
+    /// unit has a probability more than `eps` larger than a unit of strictly larger size.
+    /// The error identifies the first violating pair, in order of size.
+    ///
+    /// # Examples
+    /// ```
+    /// use envisim_utils::Probabilities;
+    ///
+    /// let sizes = [1.0, 2.0, 3.0, 4.0];
+    /// assert!(Probabilities::check_monotone_with(&sizes, &[0.1, 0.2, 0.3, 0.4], 1e-12).is_ok());
+    /// assert!(Probabilities::check_monotone_with(&sizes, &[0.1, 0.3, 0.2, 0.4], 1e-12).is_err());
+    /// ```
+    pub fn check_monotone_with(
+        sizes: &[f64],
+        probabilities: &[f64],
+        eps: f64,
+    ) -> Result<(), InputError> {
+        InputError::check_lengths(sizes, probabilities)
+            .and(Self::check(probabilities))
+            .and(Self::check_eps(eps).map(|_| ()))?;
+        sizes.iter().try_for_each(|&s| InputError::check_nan(s))?;
+
+        let mut order: Vec<usize> = (0..sizes.len()).collect();
+        order.sort_by(|&a, &b| sizes[a].partial_cmp(&sizes[b]).unwrap().then(a.cmp(&b)));
+
+        // The unit with the largest probability amongst the units of strictly smaller size
+        let mut max_unit: Option<usize> = None;
+        let mut group_start: usize = 0;
+
+        for k in 0..order.len() {
+            let id = order[k];
+
+            if sizes[id] > sizes[order[group_start]] {
+                for &gid in order[group_start..k].iter() {
+                    if max_unit.is_none_or(|mid| probabilities[gid] > probabilities[mid]) {
+                        max_unit = Some(gid);
+                    }
+                }
+
+                group_start = k;
+            }
+
+            if let Some(mid) = max_unit {
+                if probabilities[id] + eps < probabilities[mid] {
+                    return Err(InputError::NotMonotone(
+                        mid,
+                        sizes[mid],
+                        probabilities[mid],
+                        id,
+                        sizes[id],
+                        probabilities[id],
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Returns a reference to the underlying list of probabilities.
     #[inline]
     pub fn data(&self) -> &[f64] {
@@ -118,13 +419,13 @@ impl Probabilities {
 
     /// Returns an iterator over the probabilities
     #[inline]
-    pub fn iter(&self) -> Iter<f64> {
+    pub fn iter(&self) -> Iter<'_, f64> {
         self.probabilities.iter()
     }
 
     /// Returns a mutable iterator over the probabilities
     #[inline]
-    pub fn iter_mut(&mut self) -> IterMut<f64> {
+    pub fn iter_mut(&mut self) -> IterMut<'_, f64> {
         self.probabilities.iter_mut()
     }
 }
@@ -93,3 +93,77 @@ where
 {
     rng.gen::<f64>() * (v0 + v1) < v1
 }
+
+/// Calculates the elementary symmetric polynomials `e_0, e_1, ..., e_max_order` of the values,
+/// where `e_k` is the sum over all subsets of size `k` of the products of the values in the
+/// subset, and `e_0 = 1`.
+/// Returns a vector of length `max_order + 1`, where `e_k = 0` for `k` larger than the number of
+/// values.
+///
+/// The polynomials are computed by the recursive expansion over the values,
+/// `e_k(x_1, ..., x_j) = e_k(x_1, ..., x_{j-1}) + x_j e_{k-1}(x_1, ..., x_{j-1})`,
+/// in `O(N max_order)` time.
+/// For non-negative values, e.g. the odds `p / (1 - p)` used by the sampford and conditional
+/// poisson designs, the recursion only adds non-negative terms.
+/// Hence, unlike the Newton-Girard identities, it is free of cancellation, and the intermediate
+/// values never exceed the results.
+/// Results exceeding [`f64::MAX`] overflow to infinity, which can be avoided by scaling the
+/// values by some `c`, as `e_k(c x) = c^k e_k(x)`.
+///
+/// # Examples
+/// ```
+/// use envisim_utils::symmetric_polynomials;
+/// assert_eq!(
+///     symmetric_polynomials(&[1.0, 2.0, 3.0], 4),
+///     vec![1.0, 6.0, 11.0, 6.0, 0.0],
+/// );
+/// ```
+pub fn symmetric_polynomials(values: &[f64], max_order: usize) -> Vec<f64> {
+    let mut e = vec![0.0; max_order + 1];
+    e[0] = 1.0;
+
+    for (j, &x) in values.iter().enumerate() {
+        // e_k of the first j + 1 values is zero for k > j + 1
+        for k in (1..=max_order.min(j + 1)).rev() {
+            e[k] += x * e[k - 1];
+        }
+    }
+
+    e
+}
+
+/// Calculates the elementary symmetric polynomials `e_0, e_1, ..., e_max_order` of the values, as
+/// [`symmetric_polynomials`], together with the weighted polynomials `f_0, f_1, ..., f_max_order`,
+/// where `f_k` is the sum over all subsets of size `k` of the sum of the `weights` of the subset,
+/// times the product of the values in the subset.
+/// Both are computed in a single pass of the recursive expansion, e.g. for the sampford design,
+/// whose sample probabilities are proportional to `(n - sum p) prod p / (1 - p)`.
+/// Panics if `values` and `weights` have different lengths.
+///
+/// # Examples
+/// ```
+/// use envisim_utils::weighted_symmetric_polynomials;
+///
+/// let (e, f) = weighted_symmetric_polynomials(&[1.0, 2.0], &[0.5, 0.25], 2);
+/// assert_eq!(e, vec![1.0, 3.0, 2.0]);
+/// assert_eq!(f, vec![0.0, 1.0, 1.5]);
+/// ```
+pub fn weighted_symmetric_polynomials(
+    values: &[f64],
+    weights: &[f64],
+    max_order: usize,
+) -> (Vec<f64>, Vec<f64>) {
+    assert_eq!(values.len(), weights.len());
+    let mut e = vec![0.0; max_order + 1];
+    let mut f = vec![0.0; max_order + 1];
+    e[0] = 1.0;
+
+    for (j, (&x, &w)) in values.iter().zip(weights.iter()).enumerate() {
+        for k in (1..=max_order.min(j + 1)).rev() {
+            f[k] += x * (f[k - 1] + w * e[k - 1]);
+            e[k] += x * e[k - 1];
+        }
+    }
+
+    (e, f)
+}
@@ -0,0 +1,15 @@
+use envisim_utils::InputError;
+
+#[test]
+fn length_mismatch() {
+    let err = InputError::check_lengths(&[0.0; 119], &[0.0; 120]).unwrap_err();
+    assert!(matches!(
+        err,
+        InputError::LengthMismatch {
+            expected: 120,
+            found: 119
+        }
+    ));
+    assert_eq!(err.to_string(), "length mismatch: expected 120, found 119");
+    InputError::check_lengths(&[0.0; 3], &[1usize; 3]).unwrap();
+}
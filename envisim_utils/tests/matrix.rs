@@ -1,5 +1,5 @@
 use envisim_test_utils::*;
-use envisim_utils::Matrix;
+use envisim_utils::{InputError, Matrix, SymMatrix};
 
 const DATA_4_2: [f64; 8] = [
     0.0, 1.0, 2.0, 3.0, //
@@ -44,19 +44,19 @@ fn operate_matrix() {
 #[test]
 fn distance_to_row() {
     let (mm, rm) = matrix_new();
-    assert_eq!(mm.distance_to_row(0, &vec![10.0, 10.0]), 100.0 + 0.0);
-    assert_eq!(rm.distance_to_row(1, &vec![10.0, 10.0]), 81.0 + 1.0);
+    assert_eq!(mm.distance_to_row(0, &[10.0, 10.0]), 100.0 + 0.0);
+    assert_eq!(rm.distance_to_row(1, &[10.0, 10.0]), 81.0 + 1.0);
 }
 
 #[test]
 fn prod_vec() {
     let (mm, rm) = matrix_new();
     assert_eq!(
-        mm.prod_vec(&vec![2.0, 3.0]),
+        mm.prod_vec(&[2.0, 3.0]),
         vec![30.0, 33.0 + 2.0, 36.0 + 4.0, 39.0 + 6.0]
     );
     assert_eq!(
-        rm.prod_vec(&vec![1.0, 2.0]),
+        rm.prod_vec(&[1.0, 2.0]),
         vec![20.0, 22.0 + 1.0, 24.0 + 2.0, 26.0 + 3.0]
     );
 }
@@ -92,11 +92,72 @@ fn rref() {
     );
 
     data1.reduced_row_echelon_form();
-    assert_fvec(&data1.data()[0..3], &vec![1.0, 0.0, 0.0]);
-    assert_fvec(&data1.data()[3..6], &vec![0.0, 1.0, 0.0]);
-    assert_fvec(&data1.data()[6..9], &vec![0.0, 0.0, 1.0]);
+    assert_fvec(&data1.data()[0..3], &[1.0, 0.0, 0.0]);
+    assert_fvec(&data1.data()[3..6], &[0.0, 1.0, 0.0]);
+    assert_fvec(&data1.data()[6..9], &[0.0, 0.0, 1.0]);
     assert_fvec(
         &data1.data()[9..12],
-        &vec![0.188953701217875, 0.748566128914163, 0.212107159999675],
+        &[0.188953701217875, 0.748566128914163, 0.212107159999675],
     );
 }
+
+#[test]
+fn constructors() {
+    let m = Matrix::try_from_vec(DATA_4_2.to_vec(), (4, 2)).unwrap();
+    assert_eq!(m.data(), DATA_4_2);
+    assert_eq!(m.dim(), (4, 2));
+    assert!(matches!(
+        Matrix::try_from_vec(DATA_4_2.to_vec(), (3, 2)),
+        Err(InputError::InvalidSize(8, 6))
+    ));
+    Matrix::try_from_vec(vec![], (0, 2)).unwrap_err();
+
+    let z = Matrix::zeros((3, 2));
+    assert_eq!(z.dim(), (3, 2));
+    assert_eq!(z.data(), [0.0; 6]);
+
+    let i = Matrix::identity(3);
+    assert_eq!(i.data(), [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]);
+    assert_eq!(i.prod_vec(&[1.0, 2.0, 3.0]), [1.0, 2.0, 3.0]);
+}
+
+#[test]
+fn submatrix() {
+    let data: Vec<f64> = (0..25).map(f64::from).collect();
+    let m = Matrix::from_vec(data, 5);
+    let s = m.submatrix(&[1, 3], &[4, 0]).unwrap();
+    assert_eq!(s.dim(), (2, 2));
+    assert_eq!(s.data(), [21.0, 23.0, 1.0, 3.0]);
+    m.submatrix(&[1, 5], &[0]).unwrap_err();
+    m.submatrix(&[1], &[5]).unwrap_err();
+    m.submatrix(&[], &[0]).unwrap_err();
+}
+
+#[test]
+fn sym_matrix() {
+    let mut s = SymMatrix::from_value(0.0, 3);
+    s[(0, 2)] = 1.0;
+    s[(2, 1)] = 2.0;
+    assert_eq!(s[(2, 0)], 1.0);
+    assert_eq!(s[(1, 2)], 2.0);
+    assert_eq!(s.data().len(), 6);
+
+    let m = Matrix::new(&[1.0, 2.0, 3.0, 2.0, 4.0, 5.0, 3.0, 5.0, 6.0], 3);
+    let s = SymMatrix::try_from_matrix(&m, 0.0).unwrap();
+    assert_eq!(s.dim(), 3);
+    assert_eq!(s.data(), [1.0, 2.0, 4.0, 3.0, 5.0, 6.0]);
+
+    for i in 0..3 {
+        for j in 0..3 {
+            assert_eq!(s[(i, j)], m[(i, j)]);
+            assert_eq!(s[(i, j)], s[(j, i)]);
+        }
+    }
+
+    let m = Matrix::new(&[1.0, 2.0, 2.1, 4.0], 2);
+    assert!(matches!(
+        SymMatrix::try_from_matrix(&m, 1e-12),
+        Err(InputError::NotSymmetric(0, 1))
+    ));
+    SymMatrix::try_from_matrix(&m, 0.2).unwrap();
+}
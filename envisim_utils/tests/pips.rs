@@ -1,17 +1,15 @@
 use envisim_test_utils::*;
 use envisim_utils::pips::*;
+use envisim_utils::utils::usize_to_f64;
 
 #[test]
 fn pps() {
     let dt1 = vec![1.0f64, 2.0, 3.0, 4.0];
     let dt2 = vec![-1.0f64, 2.0, 3.0, 4.0];
 
-    assert_fvec(
-        pps_from_slice(&dt1).unwrap().data(),
-        &vec![0.1, 0.2, 0.3, 0.4],
-    );
+    assert_fvec(pps_from_slice(&dt1).unwrap().data(), &[0.1, 0.2, 0.3, 0.4]);
 
-    assert!(pps_from_slice(&dt2).is_err());
+    pps_from_slice(&dt2).unwrap_err();
 }
 
 #[test]
@@ -22,13 +20,94 @@ fn pips() {
 
     assert_fvec(
         pips_from_slice(&dt1, 2).unwrap().data(),
-        &vec![0.2, 0.4, 0.6, 0.8],
+        &[0.2, 0.4, 0.6, 0.8],
     );
 
-    assert!(pips_from_slice(&dt2, 2).is_err());
+    pips_from_slice(&dt2, 2).unwrap_err();
 
     assert_fvec(
         pips_from_slice(&dt3, 2).unwrap().data(),
-        &vec![1.0 / 3.0, 1.0 / 3.0, 1.0 / 3.0, 1.0],
+        &[1.0 / 3.0, 1.0 / 3.0, 1.0 / 3.0, 1.0],
     );
 }
+
+#[test]
+fn pips_with_floor() {
+    let dt1 = vec![1.0f64, 2.0, 3.0, 4.0];
+    let dt2 = vec![0.0f64, 0.5, 1.0, 1.0, 2.0, 5.0, 10.0, 40.0, 60.0, 100.0];
+
+    assert_fvec(
+        pips_from_slice_with_floor(&dt1, 2, 0.0, EPS)
+            .unwrap()
+            .data(),
+        &[0.2, 0.4, 0.6, 0.8],
+    );
+    assert_fvec(
+        pips_from_slice_with_floor(&dt1, 2, 0.3, EPS)
+            .unwrap()
+            .data(),
+        &[0.3, 1.7 / 9.0 * 2.0, 1.7 / 9.0 * 3.0, 1.7 / 9.0 * 4.0],
+    );
+
+    for (n, floor) in [(2, 0.1), (3, 0.25), (5, 0.5)] {
+        let p = pips_from_slice_with_floor(&dt2, n, floor, EPS).unwrap();
+        assert!(p.iter().all(|&x| floor <= x && x <= 1.0));
+        assert_delta!(p.iter().sum::<f64>(), usize_to_f64(n), 1e-9);
+    }
+
+    pips_from_slice_with_floor(&dt1, 1, 0.3, EPS).unwrap_err();
+    pips_from_slice_with_floor(&dt1, 5, 0.3, EPS).unwrap_err();
+    pips_from_slice_with_floor(&[0.0, 1.0], 2, 0.5, EPS).unwrap_err();
+    pips_from_slice_with_floor(&[-1.0, 1.0], 1, 0.5, EPS).unwrap_err();
+}
+
+#[test]
+fn pips_order_preserved() {
+    let sizes = [
+        5.0, 120.0, 1.0, 80.0, 3.0, 3.0, 60.0, 2.0, 7.5, 200.0, 0.5, 9.0, 35.0, 4.0, 6.0,
+    ];
+
+    for n in 1..=sizes.len() {
+        let p = pips_from_slice(&sizes, n).unwrap();
+        assert_delta!(p.iter().sum::<f64>(), usize_to_f64(n), 1e-9);
+
+        for i in 0..sizes.len() {
+            for j in 0..sizes.len() {
+                if sizes[i] < sizes[j] {
+                    assert!(p[i] <= p[j], "n = {n}: inversion of units {i} and {j}");
+                } else if sizes[i] == sizes[j] {
+                    assert_eq!(p[i], p[j]);
+                }
+            }
+        }
+
+        // The non-certainty units are proportional to size, with a common factor
+        let factors: Vec<f64> = (0..sizes.len())
+            .filter(|&i| p[i] < 1.0)
+            .map(|i| p[i] / sizes[i])
+            .collect();
+        factors
+            .iter()
+            .for_each(|&f| assert_delta!(f / factors[0], 1.0, 1e-12));
+    }
+
+    // Several certainty units
+    let p = pips_from_slice(&sizes, 6).unwrap();
+    assert_eq!(p.iter().filter(|&&x| x == 1.0).count(), 4);
+    assert_eq!((p[9], p[1], p[3], p[6]), (1.0, 1.0, 1.0, 1.0));
+    assert_delta!(p[12], 35.0 * 2.0 / 76.0);
+}
+
+#[test]
+fn pips_rescale_certainty_units() {
+    use envisim_utils::InputError;
+
+    let (p, n_certain) = pips_rescale(&[1.0, 2.0, 3.0, 20.0], 2.0, 0.0).unwrap();
+    assert_fvec(&p, &[1.0 / 6.0, 2.0 / 6.0, 3.0 / 6.0, 1.0]);
+    assert_eq!(n_certain, 1);
+
+    // Three dominant units would be certainty units, with n = 2
+    let sizes = [100.0, 100.0, 100.0, 1.0, 1.0];
+    let err = pips_rescale(&sizes, 2.0, 0.4);
+    assert!(matches!(err, Err(InputError::TooManyCertaintyUnits(3, 2))));
+}
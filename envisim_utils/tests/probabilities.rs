@@ -1,25 +1,27 @@
 use envisim_test_utils::*;
-use envisim_utils::Probabilities;
+use envisim_utils::pips::pips_from_slice;
+use envisim_utils::utils::usize_to_f64;
+use envisim_utils::{InputError, Probabilities};
 
 fn prob_new() -> Probabilities {
-    Probabilities::with_values(&vec![0.1, 0.2, 0.0, 1.0, 0.6, 0.8]).unwrap()
+    Probabilities::with_values(&[0.1, 0.2, 0.0, 1.0, 0.6, 0.8]).unwrap()
 }
 
 #[test]
 fn check() {
-    assert!(Probabilities::new(2, 0.1).is_ok());
-    assert!(Probabilities::new(2, 0.9).is_ok());
-    assert!(Probabilities::new(2, -0.9).is_err());
-    assert!(Probabilities::new(2, 1.9).is_err());
-    assert!(Probabilities::new(2, f64::NAN).is_err());
-    assert!(Probabilities::check(&vec![0.1, 0.2]).is_ok());
-    assert!(Probabilities::check(&vec![0.1, -0.2]).is_err());
-    assert!(Probabilities::check(&vec![0.1, 1.2]).is_err());
-    assert!(Probabilities::check(&vec![0.1, f64::NAN]).is_err());
+    Probabilities::new(2, 0.1).unwrap();
+    Probabilities::new(2, 0.9).unwrap();
+    Probabilities::new(2, -0.9).unwrap_err();
+    Probabilities::new(2, 1.9).unwrap_err();
+    Probabilities::new(2, f64::NAN).unwrap_err();
+    Probabilities::check(&[0.1, 0.2]).unwrap();
+    assert!(Probabilities::check(&[0.1, -0.2]).is_err());
+    assert!(Probabilities::check(&[0.1, 1.2]).is_err());
+    assert!(Probabilities::check(&[0.1, f64::NAN]).is_err());
 
-    assert!(Probabilities::check_eps(EPS).is_ok());
-    assert!(Probabilities::check_eps(-0.1).is_err());
-    assert!(Probabilities::check_eps(1.0).is_err());
+    Probabilities::check_eps(EPS).unwrap();
+    Probabilities::check_eps(-0.1).unwrap_err();
+    Probabilities::check_eps(1.0).unwrap_err();
 }
 
 #[test]
@@ -43,3 +45,115 @@ fn weight() {
     assert_delta!(p.weight(4, 5), 0.2 / 0.6);
     assert_delta!(p.weight_to(0.6, 5), 0.2 / 0.6);
 }
+
+#[test]
+fn check_monotone_with() {
+    let sizes = [4.0, 1.0, 3.0, 1.0, 2.0];
+    Probabilities::check_monotone_with(&sizes, &[0.8, 0.2, 0.6, 0.2, 0.4], EPS).unwrap();
+    // Equal sizes may have differing probabilities
+    Probabilities::check_monotone_with(&sizes, &[0.8, 0.1, 0.6, 0.3, 0.4], EPS).unwrap();
+    Probabilities::check_monotone_with(&sizes, &[0.8], EPS).unwrap_err();
+
+    let err = Probabilities::check_monotone_with(&sizes, &[0.8, 0.2, 0.3, 0.2, 0.4], EPS)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("unit 4 (size 2) has probability 0.4"), "{err}");
+    assert!(err.contains("unit 2 (size 3) has probability 0.3"), "{err}");
+}
+
+#[test]
+fn diagnose() {
+    let d = Probabilities::diagnose(&PROB_10_U, EPS);
+    assert!(d.is_valid());
+    assert!(d.integer_sum);
+    assert_delta!(d.sum, 5.0, EPS);
+
+    let p = [
+        0.2,
+        -0.5,
+        1.5,
+        f64::NAN,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        2.0,
+        0.3,
+    ];
+    let d = Probabilities::diagnose(&p, EPS);
+    assert!(!d.is_valid());
+    assert_eq!(d.negative, vec![1]);
+    assert_eq!(d.above_one, vec![2, 6]);
+    assert_eq!(d.nan, vec![3]);
+    assert_eq!(d.infinite, vec![4, 5]);
+    assert_delta!(d.sum, 3.5, EPS);
+    assert_delta!(d.integer_distance, 0.5, EPS);
+    assert!(!d.integer_sum);
+    assert_eq!(
+        d.to_string(),
+        "negative: 1 [1]\nlarger than 1: 2 [2, 6]\nnan: 1 [3]\ninfinite: 2 [4, 5]\n\
+        sum: 3.5 (0.5 from 4, not integer)"
+    );
+}
+
+#[test]
+fn coefficient_of_variation() {
+    assert_delta!(
+        Probabilities::coefficient_of_variation(&[0.5; 3]).unwrap(),
+        0.0,
+        EPS
+    );
+    // mean 0.5, sd 0.3
+    assert_delta!(
+        Probabilities::coefficient_of_variation(&[0.2, 0.8, 0.2, 0.8]).unwrap(),
+        0.6,
+        EPS
+    );
+    assert!(Probabilities::coefficient_of_variation(&[0.0; 2])
+        .unwrap()
+        .is_nan());
+
+    Probabilities::coefficient_of_variation(&[]).unwrap_err();
+    Probabilities::coefficient_of_variation(&[0.2, 1.2]).unwrap_err();
+}
+
+#[test]
+fn from_counts() {
+    // The dominating unit is a certainty unit
+    let p = Probabilities::from_counts(&[3, 5, 0, 1000, 2, 6], 3).unwrap();
+    assert_fvec(&p, &[0.375, 0.625, 0.0, 1.0, 0.25, 0.75]);
+    assert_delta!(p.iter().sum::<f64>(), 3.0, EPS);
+
+    // Certainty units are resolved repeatedly, as 10 is a certainty unit once 20 is
+    let p = Probabilities::from_counts(&[1, 1, 10, 20], 3).unwrap();
+    assert_fvec(&p, &[0.5, 0.5, 1.0, 1.0]);
+
+    // Large counts are handled exactly
+    let p = Probabilities::from_counts(&[u64::MAX, u64::MAX, 1], 2).unwrap();
+    assert_fvec(&p, &[1.0, 1.0, 0.0]);
+
+    Probabilities::from_counts(&[1, 2, 3], 0).unwrap_err();
+    Probabilities::from_counts(&[0, 0], 1).unwrap_err();
+    Probabilities::from_counts(&[0, 1, 2], 3).unwrap_err();
+}
+
+#[test]
+fn from_size_fn() {
+    // Reproduces pips_from_slice
+    let sizes = [1.0, 2.0, 3.0, 20.0, 4.0, 40.0, 5.0];
+    let p = Probabilities::from_size_fn(sizes.len(), |i| sizes[i], 4, 1e-12).unwrap();
+    assert_fvec(&p, pips_from_slice(&sizes, 4).unwrap().data());
+    assert_delta!(p.iter().sum::<f64>(), 4.0, EPS);
+
+    // Units of size zero are given probability zero
+    let p = Probabilities::from_size_fn(4, |i| usize_to_f64(i % 2), 2, 1e-12).unwrap();
+    assert_fvec(&p, &[0.0, 1.0, 0.0, 1.0]);
+
+    Probabilities::from_size_fn(3, |i| usize_to_f64(i) - 1.0, 1, 1e-12).unwrap_err();
+    Probabilities::from_size_fn(3, |_| f64::NAN, 1, 1e-12).unwrap_err();
+    Probabilities::from_size_fn(3, |_| f64::INFINITY, 1, 1e-12).unwrap_err();
+    Probabilities::from_size_fn(3, usize_to_f64, 3, 1e-12).unwrap_err();
+    Probabilities::from_size_fn(3, usize_to_f64, 0, 1e-12).unwrap_err();
+
+    // Three dominant units would be certainty units, with n = 2
+    let err = Probabilities::from_size_fn(5, |i| if i < 3 { 100.0 } else { 1.0 }, 2, 0.4);
+    assert!(matches!(err, Err(InputError::TooManyCertaintyUnits(3, 2))));
+}
@@ -1,5 +1,6 @@
 use envisim_test_utils::*;
 use envisim_utils::kd_tree::*;
+use envisim_utils::utils::usize_to_f64;
 use envisim_utils::{Matrix, Probabilities};
 use std::num::NonZeroUsize;
 
@@ -21,7 +22,7 @@ fn searcher() -> Result<(), NodeError> {
         .build(&mut [0, 1, 2, 3])?;
 
     let mut s = Searcher::new_1(&t);
-    s.find_neighbours(&t, &vec![5.0, 5.0]).unwrap();
+    s.find_neighbours(&t, &[5.0, 5.0]).unwrap();
     assert_eq!(s.neighbours(), vec![1]);
     assert_delta!(s.distance_k(0), 41.0);
 
@@ -43,7 +44,7 @@ fn searcher_weighted() -> Result<(), NodeError> {
     let p = Probabilities::new(5, 0.25).unwrap();
 
     let mut s = SearcherWeighted::new(&t);
-    s.find_neighbours(&t, &p, &vec![5.0, 5.0], 0.5).unwrap();
+    s.find_neighbours(&t, &p, &[5.0, 5.0], 0.5).unwrap();
     assert_eq!(s.neighbours(), vec![1, 0]);
     assert_delta!(s.weight_k(0), 0.5);
     assert_delta!(s.weight_k(1), 0.5);
@@ -56,3 +57,208 @@ fn searcher_weighted() -> Result<(), NodeError> {
 
     Ok(())
 }
+
+#[test]
+fn searcher_excluding() -> Result<(), NodeError> {
+    let m = matrix_new();
+    let t = TreeBuilder::new(&m)
+        .try_bucket_size(1)?
+        .build(&mut [0, 1, 2, 3, 4])?;
+    let mut s = Searcher::new_1(&t);
+    let unit = [5.0, 5.0];
+
+    let mut excluded = [false; 5];
+    assert_eq!(
+        s.find_nearest_excluding(&t, unit.iter(), &excluded)
+            .unwrap(),
+        Some(1)
+    );
+
+    excluded[1] = true;
+    assert_eq!(
+        s.find_nearest_excluding(&t, unit.iter(), &excluded)
+            .unwrap(),
+        Some(0)
+    );
+    assert_eq!(s.neighbours(), vec![0]);
+
+    excluded[0] = true;
+    excluded[2] = true;
+    assert_eq!(
+        s.find_nearest_excluding(&t, unit.iter(), &excluded)
+            .unwrap(),
+        Some(3)
+    );
+
+    let excluded = [true; 5];
+    assert_eq!(
+        s.find_nearest_excluding(&t, unit.iter(), &excluded)
+            .unwrap(),
+        None
+    );
+    s.find_nearest_excluding(&t, unit.iter(), &[false; 4])
+        .unwrap_err();
+
+    Ok(())
+}
+
+#[test]
+fn searcher_duplicates() -> Result<(), NodeError> {
+    // Units 0..50 share coordinates (1.0, 1.0), units 50..100 lie on a line
+    let mut data = vec![1.0; 200];
+    (50..100).for_each(|i| {
+        data[i] = usize_to_f64(i);
+        data[100 + i] = 0.5 * usize_to_f64(i);
+    });
+    let m = Matrix::new(&data, 100);
+    let mut t = TreeBuilder::new(&m)
+        .try_bucket_size(5)?
+        .build(&mut (0..100).collect::<Vec<usize>>())?;
+
+    let mut s = Searcher::new_1(&t);
+    s.find_neighbours(&t, &[1.0, 1.0]).unwrap();
+    let mut neighbours = s.neighbours().to_vec();
+    neighbours.sort_unstable();
+    assert_eq!(neighbours, (0..50).collect::<Vec<usize>>());
+
+    s.find_neighbours(&t, &[1.0, 1.0]).unwrap();
+    let first = s.neighbours().to_vec();
+    s.find_neighbours(&t, &[1.0, 1.0]).unwrap();
+    assert_eq!(s.neighbours(), first);
+
+    s.find_neighbours_of_id(&t, 10).unwrap();
+    assert_eq!(s.neighbours().len(), 49);
+    assert!(!s.neighbours().contains(&10));
+
+    let mut s = Searcher::new(&t, NonZeroUsize::new(3).unwrap());
+    s.find_neighbours(&t, &[2.0, 2.0]).unwrap();
+    assert_eq!(s.neighbours().len(), 50);
+    s.find_neighbours_of_id(&t, 50).unwrap();
+    assert_eq!(s.neighbours().len(), 3);
+
+    (0..45).try_for_each(|id| t.remove_unit(id).map(|_| ()))?;
+    s.find_neighbours(&t, &[1.0, 1.0]).unwrap();
+    let mut neighbours = s.neighbours().to_vec();
+    neighbours.sort_unstable();
+    assert_eq!(neighbours, (45..50).collect::<Vec<usize>>());
+
+    Ok(())
+}
+
+#[test]
+fn split_methods() -> Result<(), NodeError> {
+    // Three dense clusters, and a few scattered units
+    let n = 60;
+    let (x, y): (Vec<f64>, Vec<f64>) = (0..n)
+        .map(|i| {
+            let i = f64::from(i);
+            let (cx, cy) = match i as usize % 4 {
+                0 => (0.0, 0.0),
+                1 => (100.0, 0.0),
+                2 => (0.0, 100.0),
+                _ => (
+                    50.0 + 30.0 * (i * 0.37).sin(),
+                    50.0 + 30.0 * (i * 0.71).cos(),
+                ),
+            };
+            (cx + (i * 1.3).sin(), cy + (i * 2.9).cos())
+        })
+        .unzip();
+    let m = Matrix::from_vec(x.into_iter().chain(y).collect(), 60);
+    let units: Vec<usize> = (0..60).collect();
+
+    let t_slide = TreeBuilder::new(&m)
+        .try_bucket_size(3)?
+        .split_method(midpoint_slide)?
+        .build(&mut units.clone())?;
+    let t_median = TreeBuilder::new(&m)
+        .try_bucket_size(3)?
+        .split_method(median)?
+        .build(&mut units.clone())?;
+
+    assert_ne!(format!("{t_slide:?}"), format!("{t_median:?}"));
+
+    let mut s_slide = Searcher::new(&t_slide, NonZeroUsize::new(4).unwrap());
+    let mut s_median = Searcher::new(&t_median, NonZeroUsize::new(4).unwrap());
+
+    for id in 0..60 {
+        s_slide.find_neighbours_of_id(&t_slide, id).unwrap();
+        s_median.find_neighbours_of_id(&t_median, id).unwrap();
+        let mut a = s_slide.neighbours().to_vec();
+        let mut b = s_median.neighbours().to_vec();
+        a.sort_unstable();
+        b.sort_unstable();
+        assert_eq!(a, b);
+    }
+
+    Ok(())
+}
+
+fn haversine(radius: f64) -> impl Fn(&[f64], &[f64]) -> f64 + Sync {
+    move |a: &[f64], b: &[f64]| {
+        let (lat_a, lat_b) = (a[0].to_radians(), b[0].to_radians());
+        let h = ((lat_b - lat_a) / 2.0).sin().powi(2)
+            + lat_a.cos() * lat_b.cos() * ((b[1] - a[1]).to_radians() / 2.0).sin().powi(2);
+        2.0 * radius * h.sqrt().asin()
+    }
+}
+
+#[test]
+fn searcher_distance() -> Result<(), NodeError> {
+    // Latitude and longitude of Stockholm, Uppsala, Gothenburg, Oslo, Helsinki, Tallinn,
+    // Copenhagen, Malmö, and three units around the antimeridian
+    let m = Matrix::new(
+        &[
+            59.33, 59.86, 57.71, 59.91, 60.17, 59.44, 55.68, 55.60, 0.0, 0.0, 0.0, //
+            18.07, 17.64, 11.97, 10.75, 24.94, 24.75, 12.57, 13.00, 179.9, -179.9, 170.0, //
+        ],
+        11,
+    );
+    // The distance captures the radius of the earth, in km
+    let haversine = haversine(6371.0);
+    let t = TreeBuilder::new(&m)
+        .try_bucket_size(1)?
+        .distance(&haversine)?
+        .prune(false)?
+        .build(&mut (0..11).collect::<Vec<usize>>())?;
+    assert!(t.distance().is_some());
+
+    let mut s = Searcher::new_1(&t);
+    for id in 0..11 {
+        let nearest = (0..11)
+            .filter(|&j| j != id)
+            .min_by(|&a, &b| {
+                let da = haversine(&[m[(id, 0)], m[(id, 1)]], &[m[(a, 0)], m[(a, 1)]]);
+                let db = haversine(&[m[(id, 0)], m[(id, 1)]], &[m[(b, 0)], m[(b, 1)]]);
+                da.partial_cmp(&db).unwrap()
+            })
+            .unwrap();
+        s.find_neighbours_of_id(&t, id).unwrap();
+        assert_eq!(s.neighbours(), vec![nearest]);
+    }
+
+    // The units at longitude 179.9 and -179.9 are neighbours across the antimeridian
+    s.find_neighbours_of_id(&t, 8).unwrap();
+    assert_eq!(s.neighbours(), vec![9]);
+    assert_delta!(s.distance_k(0), haversine(&[0.0, 179.9], &[0.0, -179.9]));
+
+    // A position is closest to a coinciding unit, and Stockholm is closest to Uppsala
+    s.find_neighbours(&t, &[59.33, 18.07]).unwrap();
+    assert_eq!(s.neighbours(), vec![0]);
+    s.find_neighbours_of_id(&t, 0).unwrap();
+    assert_eq!(s.neighbours(), vec![1]);
+
+    // A distance for which the projection onto a split is a lower bound, with pruning
+    let manhattan = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum();
+    let t = TreeBuilder::new(&m)
+        .try_bucket_size(1)?
+        .distance(&manhattan)?
+        .build(&mut (0..11).collect::<Vec<usize>>())?;
+    let mut s = Searcher::new(&t, NonZeroUsize::new(2).unwrap());
+    s.find_neighbours(&t, &[58.0, 12.0]).unwrap();
+    assert_eq!(s.neighbours(), vec![2, 6]);
+    assert_delta!(s.distance_k(0), 0.29 + 0.03);
+    assert_delta!(s.distance_k(1), 2.32 + 0.57);
+
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use envisim_test_utils::*;
+use envisim_utils::utils::usize_to_f64;
+use envisim_utils::{symmetric_polynomials, weighted_symmetric_polynomials};
+
+#[test]
+fn symmetric() {
+    // e_1 = 10, e_2 = 2*3 + 2*5 + 3*5 = 31, e_3 = 30
+    assert_fvec(
+        &symmetric_polynomials(&[2.0, 3.0, 5.0], 3),
+        &[1.0, 10.0, 31.0, 30.0],
+    );
+    assert_fvec(&symmetric_polynomials(&[2.0, 3.0, 5.0], 1), &[1.0, 10.0]);
+    assert_fvec(&symmetric_polynomials(&[], 2), &[1.0, 0.0, 0.0]);
+    assert_fvec(&symmetric_polynomials(&[0.5; 4], 0), &[1.0]);
+
+    // e_k of n equal values x is binom(n, k) x^k
+    assert_fvec(
+        &symmetric_polynomials(&[0.5; 4], 4),
+        &[1.0, 2.0, 1.5, 0.5, 0.0625],
+    );
+
+    // Large values do not overflow unless the results do
+    let e = symmetric_polynomials(&[1e100, 1e100, 1e-100], 3);
+    assert_delta!(e[1] / 1e100, 2.0, EPS);
+    assert_delta!(e[2] / 1e200, 1.0, EPS);
+    assert_delta!(e[3] / 1e100, 1.0, EPS);
+}
+
+#[test]
+fn weighted_symmetric() {
+    let (e, f) = weighted_symmetric_polynomials(&[2.0, 3.0, 5.0], &[0.1, 0.2, 0.3], 3);
+    assert_fvec(&e, &symmetric_polynomials(&[2.0, 3.0, 5.0], 3));
+    // f_2 = 0.3 * 6 + 0.4 * 10 + 0.5 * 15, f_3 = 0.6 * 30
+    assert_fvec(&f, &[0.0, 2.3, 13.3, 18.0]);
+
+    // Unit weights give f_k = k e_k
+    let (e, f) = weighted_symmetric_polynomials(&[0.5; 4], &[1.0; 4], 4);
+    for (k, (&fk, &ek)) in f.iter().zip(e.iter()).enumerate() {
+        assert_delta!(fk, usize_to_f64(k) * ek, EPS);
+    }
+}
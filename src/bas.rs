@@ -0,0 +1,296 @@
+// Copyright (C) 2024 Wilmer Prentius, Anton Grafström.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU Affero General Public License as published by the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Balanced acceptance sampling (BAS) designs
+//!
+//! The units are placed in Halton boxes by Halton iterative partitioning: the population is
+//! repeatedly split into groups of (almost) equal size along the dimensions, such that each unit
+//! has its own box.
+//! Each unit is given the index of the first point of the randomly started Halton sequence that
+//! falls in its box.
+//! Since any run of consecutive Halton points is well spread, so is any run of consecutive units
+//! in this order, see [`halton_order`].
+//!
+//! # References
+//! Robertson, B. L., Brown, J. A., McDonald, T., & Jaksons, P. (2013).
+//! BAS: Balanced acceptance sampling of natural resources.
+//! Biometrics, 69(3), 776-784.
+//! <https://doi.org/10.1111/biom.12059>
+//!
+//! Robertson, B. L., McDonald, T., Price, C. J., & Brown, J. A. (2018).
+//! Halton iterative partitioning: spatially balanced sampling via partitioning.
+//! Environmental and Ecological Statistics, 25(3), 305-323.
+//! <https://doi.org/10.1007/s10651-018-0406-6>
+
+use crate::rng::derive_rng;
+use crate::systematic::from_order;
+pub use crate::{SampleOptions, SamplingError};
+use envisim_utils::{InputError, Matrix};
+use rand::Rng;
+
+/// The bases of the Halton sequence, one for each dimension
+const HALTON_BASES: [u128; 10] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29];
+
+// Inverse of a modulo m, for coprime a and m
+fn mod_inverse(a: u128, m: u128) -> u128 {
+    let m = i128::try_from(m).unwrap();
+    let (mut old_r, mut r) = (i128::try_from(a).unwrap(), m);
+    let (mut old_s, mut s) = (1i128, 0i128);
+
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_s, s) = (s, old_s - q * s);
+    }
+
+    u128::try_from(old_s.rem_euclid(m)).unwrap()
+}
+
+/// The Halton boxes of a population
+struct HaltonBoxes {
+    /// The Halton index of the box of each unit
+    indices: Vec<u128>,
+    /// The number of boxes
+    n_boxes: u128,
+    /// The base of each split, in the order of the partitioning
+    radices: Vec<u128>,
+}
+
+impl HaltonBoxes {
+    fn new(coordinates: &Matrix) -> Result<Self, InputError> {
+        let (population_size, dim) = coordinates.dim();
+        InputError::check_range_usize(dim, 1, HALTON_BASES.len())?;
+        coordinates
+            .data()
+            .iter()
+            .try_for_each(|&x| InputError::check_nan(x))?;
+
+        // Split the dimension with the fewest boxes, until there are at least as many boxes as
+        // units
+        let mut digits = vec![0u32; dim];
+        let mut moduli = vec![1u128; dim];
+        let mut radices = Vec::<u128>::new();
+        let mut n_boxes: u128 = 1;
+        let mut cells = vec![vec![0u128; dim]; population_size];
+        let mut groups: Vec<Vec<usize>> = vec![(0..population_size).collect()];
+
+        while n_boxes < population_size as u128 {
+            let k = (0..dim).min_by_key(|&k| moduli[k]).unwrap();
+            let base = HALTON_BASES[k];
+            digits[k] += 1;
+            moduli[k] *= base;
+            n_boxes *= base;
+            radices.push(base);
+
+            let mut new_groups = Vec::<Vec<usize>>::with_capacity(groups.len() * base as usize);
+            for mut group in groups.into_iter() {
+                group.sort_by(|&a, &b| coordinates[(a, k)].total_cmp(&coordinates[(b, k)]));
+                let mut parts = vec![Vec::<usize>::new(); base as usize];
+                let group_size = group.len() as u128;
+
+                for (rank, &id) in group.iter().enumerate() {
+                    let digit = rank as u128 * base / group_size;
+                    cells[id][k] = cells[id][k] * base + digit;
+                    parts[digit as usize].push(id);
+                }
+
+                new_groups.extend(parts.into_iter().filter(|p| !p.is_empty()));
+            }
+            groups = new_groups;
+        }
+
+        // The Halton index i falls in cell c of dimension k iff i mod base^digits equals the
+        // digit reversal of c. The index is found by the chinese remainder theorem.
+        let mut indices = vec![0u128; population_size];
+
+        for k in 0..dim {
+            let base = HALTON_BASES[k];
+            let current = n_boxes / moduli[k];
+            let inverse = mod_inverse(current % moduli[k], moduli[k]);
+
+            for (index, cell) in indices.iter_mut().zip(cells.iter()) {
+                let mut reversed: u128 = 0;
+                let mut c = cell[k];
+                for _ in 0..digits[k] {
+                    reversed = reversed * base + c % base;
+                    c /= base;
+                }
+
+                *index = (*index + reversed * inverse % moduli[k] * current) % n_boxes;
+            }
+        }
+
+        Ok(Self {
+            indices,
+            n_boxes,
+            radices,
+        })
+    }
+
+    // The Halton indices, shifted by a random start
+    fn shifted_indices(&self, seed: u64) -> Vec<u128> {
+        let start = derive_rng(seed, 0).gen_range(0..self.n_boxes);
+        self.indices
+            .iter()
+            .map(|&i| (i + self.n_boxes - start) % self.n_boxes)
+            .collect()
+    }
+
+    // The mixed radix digit reversal of the index, which places spatially close boxes next to
+    // each other
+    fn reversed(&self, mut index: u128) -> u128 {
+        let mut reversed: u128 = 0;
+        for &base in self.radices.iter() {
+            reversed = reversed * base + index % base;
+            index /= base;
+        }
+        reversed
+    }
+}
+
+#[inline]
+fn order_by(keys: &[u128]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..keys.len()).collect();
+    order.sort_unstable_by_key(|&id| keys[id]);
+    order
+}
+
+/// Returns the order of the units along the randomly started Halton sequence.
+/// Any run of consecutive units in this order is spatially balanced.
+/// The order is fully determined by the auxiliaries and the `seed`.
+/// Returns an error if the auxiliaries are missing, or if they have more than 10 dimensions (the
+/// number of Halton bases).
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::bas::*;
+/// use envisim_utils::Matrix;
+///
+/// let p = [0.5; 4];
+/// let m = Matrix::from_vec(vec![0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7], 4);
+/// let order = halton_order(SampleOptions::new(&p)?.auxiliaries(&m)?, 4242)?;
+///
+/// assert_eq!(order.len(), 4);
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn halton_order(options: &SampleOptions, seed: u64) -> Result<Vec<usize>, SamplingError> {
+    options.check_spatially_balanced()?;
+    let boxes = HaltonBoxes::new(options.auxiliaries.unwrap())?;
+    Ok(order_by(&boxes.shifted_indices(seed)))
+}
+
+/// Draw a sample using balanced acceptance sampling.
+/// The units are ordered by the digit reversal of their randomly started Halton index, which
+/// places spatially close units next to each other, and the sample is selected by systematic
+/// sampling along this order.
+/// The sample is fully determined by the auxiliaries, the probabilities and the `seed`.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::bas::*;
+/// use envisim_utils::Matrix;
+///
+/// let p = [0.2, 0.25, 0.35, 0.4, 0.5, 0.5, 0.55, 0.65, 0.7, 0.9];
+/// let m = Matrix::from_vec(vec![0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9], 10);
+/// let s = bas(SampleOptions::new(&p)?.auxiliaries(&m)?, 4242)?;
+///
+/// assert_eq!(s.len(), 5);
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn bas(options: &SampleOptions, seed: u64) -> Result<Vec<usize>, SamplingError> {
+    options.check_spatially_balanced()?;
+    let boxes = HaltonBoxes::new(options.auxiliaries.unwrap())?;
+    let keys: Vec<u128> = boxes
+        .shifted_indices(seed)
+        .iter()
+        .map(|&i| boxes.reversed(i))
+        .collect();
+    let mut sample = from_order(
+        derive_rng(seed, 1).gen(),
+        options.probabilities,
+        &order_by(&keys),
+    )?;
+
+    if options.sorted_output {
+        sample.sort_unstable();
+    }
+
+    Ok(sample)
+}
+
+/// Extends a sample drawn by [`bas`] with `additional_n` new units.
+/// The new units are the first units in the [`halton_order`] which are not already in the
+/// `existing_sample`, and which have a positive probability.
+/// The existing units are kept as is, and only the new units are returned.
+/// Since the extension of an extended sample continues where the previous extension stopped,
+/// extending by one and then by two units gives the same units as extending by three units.
+///
+/// The `existing_sample` must contain the [`bas`] sample with the same `options` and `seed`,
+/// and may contain units added by earlier extensions.
+/// Returns an error if the `existing_sample` contains duplicates or units outside of the
+/// population, if it does not contain the [`bas`] sample, or if there are fewer than
+/// `additional_n` units left to add.
+///
+/// Note that the combined sample is spatially balanced, but it is not a sample with the
+/// inclusion probabilities of `options`.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::bas::*;
+/// use envisim_utils::Matrix;
+///
+/// let p = [0.2, 0.25, 0.35, 0.4, 0.5, 0.5, 0.55, 0.65, 0.7, 0.9];
+/// let m = Matrix::from_vec(vec![0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9], 10);
+/// let mut options = SampleOptions::new(&p)?;
+/// options.auxiliaries(&m)?;
+///
+/// let s = bas(&options, 4242)?;
+/// let new_units = extend(&s, &options, 4242, 2)?;
+///
+/// assert_eq!(new_units.len(), 2);
+/// assert!(new_units.iter().all(|id| !s.contains(id)));
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn extend(
+    existing_sample: &[usize],
+    options: &SampleOptions,
+    seed: u64,
+    additional_n: usize,
+) -> Result<Vec<usize>, SamplingError> {
+    let population_size = options.probabilities.len();
+    let mut selected = vec![false; population_size];
+
+    for &id in existing_sample.iter() {
+        InputError::check_range_usize(id, 0, population_size - 1)?;
+        if selected[id] {
+            return Err(InputError::NotUnique.into());
+        }
+        selected[id] = true;
+    }
+
+    if let Some(&id) = bas(options, seed)?.iter().find(|&&id| !selected[id]) {
+        return Err(InputError::Missing(format!("unit {id} of the bas sample")).into());
+    }
+
+    let mut additional: Vec<usize> = halton_order(options, seed)?
+        .into_iter()
+        .filter(|&id| !selected[id] && options.probabilities[id] > options.eps)
+        .take(additional_n)
+        .collect();
+    InputError::check_range_usize(additional_n, 0, additional.len())?;
+
+    if options.sorted_output {
+        additional.sort_unstable();
+    }
+
+    Ok(additional)
+}
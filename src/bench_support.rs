@@ -0,0 +1,58 @@
+// Copyright (C) 2024 Wilmer Prentius, Anton Grafström.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU Affero General Public License as published by the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Helpers for measuring the runtime of designs
+
+use crate::{SampleOptions, SamplingError};
+use rand::Rng;
+use std::num::NonZeroU32;
+use std::time::{Duration, Instant};
+
+/// Returns the mean wall-clock time of drawing a sample using `design`, over `reps` samples.
+/// The runtimes of the designs differ in their order of complexity, e.g.
+/// [`crate::unequal::brewer`] passes over the population once per selected unit, whereas
+/// [`crate::systematic::sample`] passes over the population once, hence the helper allows
+/// comparing designs on the populations and hardware of interest.
+/// See also the `designs` benchmark of the crate, run by `cargo bench`.
+/// Returns an error if the design returns an error.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::bench_support::measure_design_time;
+/// use envisim_samplr::{unequal::brewer, SampleOptions};
+/// use rand::{rngs::SmallRng, SeedableRng};
+/// use std::num::NonZeroU32;
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let p = [0.2, 0.25, 0.35, 0.4, 0.5, 0.5, 0.55, 0.65, 0.7, 0.9];
+/// let reps = NonZeroU32::new(100).unwrap();
+/// let time = measure_design_time(&mut rng, brewer, &SampleOptions::new(&p)?, reps)?;
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+pub fn measure_design_time<R, F>(
+    rng: &mut R,
+    design: F,
+    options: &SampleOptions,
+    reps: NonZeroU32,
+) -> Result<Duration, SamplingError>
+where
+    R: Rng + ?Sized,
+    F: Fn(&mut R, &SampleOptions) -> Result<Vec<usize>, SamplingError>,
+{
+    let start = Instant::now();
+
+    for _ in 0..reps.get() {
+        std::hint::black_box(design(rng, options)?);
+    }
+
+    Ok(start.elapsed() / reps.get())
+}
@@ -0,0 +1,421 @@
+// Copyright (C) 2024 Wilmer Prentius, Anton Grafström.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU Affero General Public License as published by the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Controlled selection
+
+pub use crate::{SampleOptions, SamplingError};
+use envisim_utils::utils::{sum, usize_to_f64};
+use envisim_utils::{InputError, Probabilities};
+use rand::Rng;
+
+/// The largest number of possible samples handled by [`controlled_selection`]
+const MAX_SAMPLES: usize = 20_000;
+const LP_EPS: f64 = 1e-9;
+
+/// Draw a sample using controlled selection, where the probability of selecting a sample
+/// containing any of the `undesirable` pairs of units is minimized, subject to the inclusion
+/// probabilities.
+/// Probabilities must sum to an integer `n`.
+///
+/// The design is found by linear programming over the sample space: with one variable `p(s)` for
+/// each of the possible samples `s` of size `n`, the probability `sum p(s)` of the samples
+/// containing an undesirable pair is minimized, subject to `sum_{s: i in s} p(s) = p_i` for all
+/// units `i`, and `p(s) >= 0`.
+/// The linear program is solved by the two-phase simplex method (using Bland's rule), where each
+/// pivot counts as an iteration towards `max_iterations`.
+/// The sample is then drawn from the optimal design.
+/// Units with probability at most `eps` are never selected, and units with probability at least
+/// `1 - eps` are always selected.
+///
+/// As the sample space grows exponentially, the design is only available for small
+/// populations.
+/// Returns an error if the number of possible samples of the remaining units is larger than
+/// 20000 (e.g. selecting 5 out of 21 units, with 20349 possible samples).
+///
+/// **Note:** the linear program is solved on each call, which dominates the cost of the design.
+/// For repeated draws from the same design, use [`ControlledDesign`], which solves the linear
+/// program once.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::controlled::*;
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let p = [0.5, 0.5, 0.5, 0.5];
+/// let options = SampleOptions::new(&p)?;
+/// let s = controlled_selection(&mut rng, &options, &[(0, 1), (2, 3)])?;
+///
+/// // The undesirable pairs can be avoided completely
+/// assert_eq!(s.len(), 2);
+/// assert!(s != vec![0, 1] && s != vec![2, 3]);
+/// # Ok::<(), SamplingError>(())
+/// ```
+///
+/// # References
+/// Goodman, R., & Kish, L. (1950).
+/// Controlled selection—a technique in probability sampling.
+/// Journal of the American Statistical Association, 45(251), 350-372.
+/// <https://doi.org/10.1080/01621459.1950.10483356>
+///
+/// Rao, J. N. K., & Nigam, A. K. (1990).
+/// Optimal controlled sampling designs.
+/// Biometrika, 77(4), 807-814.
+/// <https://doi.org/10.1093/biomet/77.4.807>
+pub fn controlled_selection<R>(
+    rng: &mut R,
+    options: &SampleOptions,
+    undesirable: &[(usize, usize)],
+) -> Result<Vec<usize>, SamplingError>
+where
+    R: Rng + ?Sized,
+{
+    Ok(ControlledDesign::new(options, undesirable)?.sample(rng))
+}
+
+/// A controlled selection design, as found by [`controlled_selection`], for repeated draws.
+/// The linear program is solved once, when the design is created, and the samples are drawn
+/// from the stored support of the design.
+/// The design is immutable, and may be shared between threads.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::controlled::*;
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let p = [0.5, 0.5, 0.5, 0.5];
+/// let design = ControlledDesign::new(&SampleOptions::new(&p)?, &[(0, 1), (2, 3)])?;
+///
+/// for _ in 0..10 {
+///     let s = design.sample(&mut rng);
+///     assert!(s != vec![0, 1] && s != vec![2, 3]);
+/// }
+/// # Ok::<(), SamplingError>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct ControlledDesign {
+    // The samples with positive probability, sorted, and their probabilities
+    samples: Vec<Vec<usize>>,
+    probabilities: Vec<f64>,
+}
+
+impl ControlledDesign {
+    /// Finds the controlled selection design, see [`controlled_selection`].
+    /// Returns the same errors as [`controlled_selection`].
+    pub fn new(
+        options: &SampleOptions,
+        undesirable: &[(usize, usize)],
+    ) -> Result<Self, SamplingError> {
+        let probabilities = options.probabilities;
+        let population_size = probabilities.len();
+        let eps = options.eps;
+        let psum = sum(probabilities);
+        Probabilities::check(probabilities)
+            .and(Probabilities::check_eps(eps))
+            .and(InputError::check_integer_approx(psum, eps))?;
+        undesirable.iter().try_for_each(|&(i, j)| {
+            InputError::check_range_usize(i, 0, population_size - 1)
+                .and(InputError::check_range_usize(j, 0, population_size - 1))
+        })?;
+
+        let certain: Vec<usize> = (0..population_size)
+            .filter(|&i| 1.0 - eps <= probabilities[i])
+            .collect();
+        let random: Vec<usize> = (0..population_size)
+            .filter(|&i| eps < probabilities[i] && probabilities[i] < 1.0 - eps)
+            .collect();
+        let sample_size = (psum.round() as usize).saturating_sub(certain.len());
+
+        InputError::check_range_usize(binomial(random.len(), sample_size), 0, MAX_SAMPLES)?;
+
+        let samples = combinations(&random, sample_size);
+        let mut is_certain = vec![false; population_size];
+        certain.iter().for_each(|&i| is_certain[i] = true);
+
+        let costs: Vec<f64> = samples
+            .iter()
+            .map(|s| {
+                let mut included = is_certain.clone();
+                s.iter().for_each(|&i| included[i] = true);
+                if undesirable.iter().any(|&(i, j)| included[i] && included[j]) {
+                    1.0
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+
+        let constraints: Vec<f64> = random.iter().map(|&i| probabilities[i]).collect();
+        let design = simplex(&random, &samples, &constraints, &costs, options)?;
+
+        let (samples, probabilities) = design
+            .iter()
+            .map(|&(s, p)| {
+                let mut sample = samples.get(s).cloned().unwrap_or_default();
+                sample.extend_from_slice(&certain);
+                sample.sort_unstable();
+                (sample, p)
+            })
+            .unzip();
+
+        Ok(Self {
+            samples,
+            probabilities,
+        })
+    }
+    /// Returns the support of the design, as the samples with positive probability, and their
+    /// probabilities.
+    #[inline]
+    pub fn support(&self) -> (&[Vec<usize>], &[f64]) {
+        (&self.samples, &self.probabilities)
+    }
+    /// Draws a sample from the design.
+    /// The sample is sorted.
+    pub fn sample<R>(&self, rng: &mut R) -> Vec<usize>
+    where
+        R: Rng + ?Sized,
+    {
+        let rv = rng.gen::<f64>() * sum(&self.probabilities);
+        let mut psum = 0.0;
+        let mut selected = self.samples.len().saturating_sub(1);
+        for (s, &p) in self.probabilities.iter().enumerate() {
+            psum += p;
+            if rv < psum {
+                selected = s;
+                break;
+            }
+        }
+
+        self.samples.get(selected).cloned().unwrap_or_default()
+    }
+}
+
+fn binomial(n: usize, k: usize) -> usize {
+    if k > n {
+        return 0;
+    }
+
+    let k = k.min(n - k);
+    let mut result: u128 = 1;
+
+    for i in 0..k {
+        result = result * (n - i) as u128 / (i + 1) as u128;
+        if result > usize::MAX as u128 {
+            return usize::MAX;
+        }
+    }
+
+    result as usize
+}
+
+// All subsets of size k of units, in lexicographic order
+fn combinations(units: &[usize], k: usize) -> Vec<Vec<usize>> {
+    let n = units.len();
+    let mut result = Vec::<Vec<usize>>::new();
+
+    if k > n {
+        return result;
+    }
+
+    let mut positions: Vec<usize> = (0..k).collect();
+
+    loop {
+        result.push(positions.iter().map(|&i| units[i]).collect());
+
+        // Find the rightmost position that can be moved
+        let Some(i) = (0..k).rev().find(|&i| positions[i] < n - k + i) else {
+            return result;
+        };
+
+        positions[i] += 1;
+        for j in (i + 1)..k {
+            positions[j] = positions[j - 1] + 1;
+        }
+    }
+}
+
+// Minimizes costs' p, subject to A p = constraints and p >= 0, where A has a row for each of the
+// units, with a 1 in the columns of the samples containing the unit.
+// Returns the samples with positive probability, and their probability.
+fn simplex(
+    units: &[usize],
+    samples: &[Vec<usize>],
+    constraints: &[f64],
+    costs: &[f64],
+    options: &SampleOptions,
+) -> Result<Vec<(usize, f64)>, SamplingError> {
+    let n_rows = units.len();
+    let n_samples = samples.len();
+
+    if n_rows == 0 {
+        return Ok(vec![(0, 1.0)]);
+    }
+
+    // The columns are the samples, followed by one artificial variable per row, and the rhs
+    let n_cols = n_samples + n_rows;
+    let mut tableau = vec![vec![0.0; n_cols + 1]; n_rows];
+    let mut basis: Vec<usize> = (n_samples..n_cols).collect();
+
+    for (row, &unit) in units.iter().enumerate() {
+        for (col, sample) in samples.iter().enumerate() {
+            if sample.contains(&unit) {
+                tableau[row][col] = 1.0;
+            }
+        }
+        tableau[row][n_samples + row] = 1.0;
+        tableau[row][n_cols] = constraints[row];
+    }
+
+    let mut iterations = 0usize;
+
+    // Phase 1: minimize the sum of the artificial variables
+    let mut reduced = vec![0.0; n_cols + 1];
+    reduced[n_samples..n_cols].fill(1.0);
+    for row in tableau.iter() {
+        reduced
+            .iter_mut()
+            .zip(row.iter())
+            .for_each(|(r, &t)| *r -= t);
+    }
+    simplex_solve(
+        &mut tableau,
+        &mut basis,
+        &mut reduced,
+        n_cols,
+        &mut iterations,
+        options,
+    )?;
+
+    if -reduced[n_cols] > LP_EPS * usize_to_f64(n_rows) {
+        return Err(InputError::Missing("a feasible design".to_owned()).into());
+    }
+
+    // Drive the remaining (zero) artificial variables out of the basis
+    for row in 0..n_rows {
+        if basis[row] < n_samples {
+            continue;
+        }
+
+        if let Some(col) = (0..n_samples).find(|&c| tableau[row][c].abs() > LP_EPS) {
+            pivot(&mut tableau, &mut basis, &mut reduced, row, col);
+        }
+    }
+
+    // Phase 2: minimize the costs, without the artificial variables
+    reduced.fill(0.0);
+    reduced[..n_samples].copy_from_slice(costs);
+    for (row, &b) in tableau.iter().zip(basis.iter()) {
+        if b < n_samples && costs[b] != 0.0 {
+            let c = costs[b];
+            reduced
+                .iter_mut()
+                .zip(row.iter())
+                .for_each(|(r, &t)| *r -= c * t);
+        }
+    }
+    simplex_solve(
+        &mut tableau,
+        &mut basis,
+        &mut reduced,
+        n_samples,
+        &mut iterations,
+        options,
+    )?;
+
+    Ok(basis
+        .iter()
+        .zip(tableau.iter())
+        .filter(|(&b, row)| b < n_samples && row[n_cols] > 0.0)
+        .map(|(&b, row)| (b, row[n_cols]))
+        .collect())
+}
+
+// Pivots until no column in 0..n_enter has a negative reduced cost
+fn simplex_solve(
+    tableau: &mut [Vec<f64>],
+    basis: &mut [usize],
+    reduced: &mut [f64],
+    n_enter: usize,
+    iterations: &mut usize,
+    options: &SampleOptions,
+) -> Result<(), SamplingError> {
+    let rhs = reduced.len() - 1;
+
+    loop {
+        // Bland's rule, the first column with negative reduced cost enters
+        let Some(col) = (0..n_enter).find(|&c| reduced[c] < -LP_EPS) else {
+            return Ok(());
+        };
+
+        // The row with the smallest ratio leaves, ties broken by the smallest basic variable
+        let mut leaving: Option<(usize, f64)> = None;
+        for (row, values) in tableau.iter().enumerate() {
+            if values[col] <= LP_EPS {
+                continue;
+            }
+
+            let ratio = values[rhs] / values[col];
+            leaving = match leaving {
+                Some((r, best))
+                    if best < ratio - LP_EPS
+                        || ((ratio - best).abs() <= LP_EPS && basis[r] < basis[row]) =>
+                {
+                    Some((r, best))
+                }
+                _ => Some((row, ratio)),
+            };
+        }
+
+        // The problem is bounded, as all variables are probabilities
+        let Some((row, _)) = leaving else {
+            return Ok(());
+        };
+
+        *iterations += 1;
+        if *iterations > options.max_iterations.get() {
+            return Err(SamplingError::MaxIterations(options.max_iterations));
+        }
+
+        pivot(tableau, basis, reduced, row, col);
+    }
+}
+
+fn pivot(
+    tableau: &mut [Vec<f64>],
+    basis: &mut [usize],
+    reduced: &mut [f64],
+    row: usize,
+    col: usize,
+) {
+    let value = tableau[row][col];
+    tableau[row].iter_mut().for_each(|t| *t /= value);
+    let pivot_row = tableau[row].clone();
+
+    for (r, values) in tableau.iter_mut().enumerate() {
+        let factor = values[col];
+        if r == row || factor == 0.0 {
+            continue;
+        }
+        values
+            .iter_mut()
+            .zip(pivot_row.iter())
+            .for_each(|(v, &p)| *v -= factor * p);
+    }
+
+    let factor = reduced[col];
+    reduced
+        .iter_mut()
+        .zip(pivot_row.iter())
+        .for_each(|(v, &p)| *v -= factor * p);
+    basis[row] = col;
+}
@@ -0,0 +1,123 @@
+// Copyright (C) 2024 Wilmer Prentius, Anton Grafström.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU Affero General Public License as published by the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Coordination of multiple samples from the same population
+
+use crate::poisson;
+pub use crate::{SampleOptions, SamplingError};
+use envisim_utils::pips::pips_rescale;
+use envisim_utils::utils::{sum, usize_to_f64};
+use envisim_utils::InputError;
+use rand::Rng;
+
+/// Draw two disjoint samples from the same population.
+/// The first sample is drawn using `design` and the probabilities of `options_a`.
+/// The units of the first sample are then removed from the population, and the probabilities of
+/// `options_b` are rescaled among the remaining units, such that they sum to their original sum.
+/// The second sample is drawn using `design` and these rescaled probabilities.
+///
+/// The second sample is thus drawn conditionally on the first sample, and its (marginal)
+/// inclusion probabilities generally differ from the probabilities of `options_b`.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::coordinated::*;
+/// use envisim_samplr::pivotal_method::spm;
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let pa = [0.2; 10];
+/// let pb = [0.3; 10];
+/// let (sa, sb) = disjoint_samples(
+///     &mut rng,
+///     &SampleOptions::new(&pa)?,
+///     &SampleOptions::new(&pb)?,
+///     spm,
+/// )?;
+///
+/// assert_eq!(sa.len(), 2);
+/// assert_eq!(sb.len(), 3);
+/// assert!(sa.iter().all(|id| !sb.contains(id)));
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn disjoint_samples<R, F>(
+    rng: &mut R,
+    options_a: &SampleOptions,
+    options_b: &SampleOptions,
+    design: F,
+) -> Result<(Vec<usize>, Vec<usize>), SamplingError>
+where
+    R: Rng + ?Sized,
+    F: Fn(&mut R, &SampleOptions) -> Result<Vec<usize>, SamplingError>,
+{
+    let population_size = options_a.probabilities.len();
+    let size_a = sum(options_a.probabilities);
+    let size_b = sum(options_b.probabilities);
+    InputError::check_sizes(options_b.probabilities.len(), population_size)?;
+    InputError::check_range_f64(
+        size_a + size_b,
+        0.0,
+        usize_to_f64(population_size) + options_b.eps,
+    )?;
+
+    let sample_a = design(rng, options_a)?;
+
+    InputError::check_range_f64(
+        size_b,
+        0.0,
+        usize_to_f64(population_size - sample_a.len()) + options_b.eps,
+    )?;
+
+    // The probabilities of the remaining units are rescaled to sum to size_b
+    let mut sizes_b = options_b.probabilities.to_vec();
+    sample_a.iter().for_each(|&id| sizes_b[id] = 0.0);
+    let (probabilities_b, _) = pips_rescale(&sizes_b, size_b, options_b.eps)?;
+    let conditional_options = SampleOptions {
+        probabilities: &probabilities_b,
+        ..*options_b
+    };
+    let sample_b = design(rng, &conditional_options)?;
+
+    Ok((sample_a, sample_b))
+}
+
+/// Draw a poisson sample using permanent random numbers (PRNs).
+/// Unit `i` is included if and only if `prn[i] < p[i]` or `p[i] == 1.0`, where the PRNs are the
+/// `random_values` of the options, and `p` are the probabilities.
+/// No rng is used, hence samples drawn from the same population with shared PRNs are positively
+/// coordinated: raising the probability of a unit can only add it to the sample.
+/// Returns an error if the `random_values` are missing, or if any PRN is outside `[0.0, 1.0]`.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::coordinated::*;
+///
+/// let prn = [0.1, 0.8, 0.35, 0.6, 0.05];
+/// let pa = [0.2; 5];
+/// let pb = [0.4; 5];
+/// let sa = poisson_prn(SampleOptions::new(&pa)?.random_values(&prn)?)?;
+/// let sb = poisson_prn(SampleOptions::new(&pb)?.random_values(&prn)?)?;
+///
+/// assert_eq!(sa, vec![0, 4]);
+/// assert_eq!(sb, vec![0, 2, 4]);
+/// # Ok::<(), SamplingError>(())
+/// ```
+///
+/// # References
+/// Ohlsson, E. (1995).
+/// Coordination of samples using permanent random numbers.
+/// In B. G. Cox et al. (Eds.), Business survey methods (pp. 153-169).
+/// John Wiley & Sons.
+pub fn poisson_prn(options: &SampleOptions) -> Result<Vec<usize>, SamplingError> {
+    options.check_coordinated()?;
+    poisson::sample_with_uniforms(options.random_values.unwrap(), options.probabilities)
+}
@@ -89,6 +89,56 @@ where
     cube_new(rng, options)?.sample_with_return()
 }
 
+/// Runs only the flight phase of the cube method, and returns the probabilities after the
+/// flight phase.
+/// Units resolved during the flight phase have probability `0.0` or `1.0`, and at most as many
+/// units as there are auxilliary variables in `balancing` remain with a fractional probability.
+/// The balancing constraints that could not be met during the flight phase are thus resolved by
+/// the landing phase, of at most the same number of units.
+///
+/// The returned probabilities are a diagnostic of the flight phase, e.g. for research on landing
+/// strategies, and still need a landing phase to become a sample.
+/// Given the same rng, the flight phase equals that of [`cube`].
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::cube_method::*;
+/// use envisim_utils::Matrix;
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let p = [0.2, 0.25, 0.35, 0.4, 0.5, 0.5, 0.55, 0.65, 0.7, 0.9];
+/// let bal_m = Matrix::from_vec(vec![
+///     0.2, 0.25, 0.35, 0.4, 0.5, 0.5, 0.55, 0.65, 0.7, 0.9,
+///     0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9,
+/// ], 10);
+/// let q = cube_flight_only(&mut rng, SampleOptions::new(&p)?.balancing(&bal_m)?)?;
+///
+/// assert!(q.iter().filter(|&&x| 0.0 < x && x < 1.0).count() <= 2);
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn cube_flight_only<'a, R>(
+    rng: &'a mut R,
+    options: &SampleOptions<'a>,
+) -> Result<Vec<f64>, SamplingError>
+where
+    R: Rng + ?Sized,
+{
+    let mut cube = cube_new(rng, options)?;
+    let container = &cube.run_flight().container;
+
+    Ok((0..container.population_size())
+        .map(|id| {
+            let p = container.probabilities()[id];
+            if container.indices().contains(id) {
+                p
+            } else {
+                p.round()
+            }
+        })
+        .collect())
+}
+
 /// Draw a sample using the stratified cube method.
 /// The sample is balanced on the provided auxilliary variables in `balancing`.
 /// The first auxilliary variable should not be the probability vector.
@@ -559,9 +609,8 @@ where
     }
     #[inline]
     fn decide_unit(&mut self, container: &mut Container<'a, R>, id: usize) -> Option<bool> {
-        container.decide_unit(id).unwrap().map(|r| {
+        container.decide_unit(id).unwrap().inspect(|_| {
             self.tree.remove_unit(id).unwrap();
-            r
         })
     }
 }
@@ -628,13 +677,11 @@ where
             .set_n_neighbours(NonZeroUsize::new(n_neighbours).unwrap());
 
         container.indices_mut().clear();
-        self.tree = Box::new(
-            TreeBuilder::new(data.unwrap().0)
-                .bucket_size(data.unwrap().1)
-                .unwrap()
-                .build(ids)
-                .unwrap(),
-        );
+        *self.tree = TreeBuilder::new(data.unwrap().0)
+            .bucket_size(data.unwrap().1)
+            .unwrap()
+            .build(ids)
+            .unwrap();
 
         for id in ids.iter() {
             container.indices_mut().insert(*id).unwrap();
@@ -873,7 +920,7 @@ mod tests {
             3,
         );
         mat2.reduced_row_echelon_form();
-        assert!(&mat2.data()[0..9] == vec![1.0f64, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]);
+        assert!(mat2.data()[0..9] == vec![1.0f64, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]);
         assert_fvec(
             &mat2.data()[9..12], // col 3
             &[-2.5, 1.833333333333333, 0.166666666666667],
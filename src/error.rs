@@ -11,6 +11,10 @@ pub enum SamplingError {
     Node(NodeError),
     // max iterations reached
     MaxIterations(NonZeroUsize),
+    // the design yields an empty sample, see SampleOptions::allow_empty_sample
+    EmptySample,
+    // reading the input failed
+    Io(std::io::Error),
 }
 
 impl std::error::Error for SamplingError {
@@ -19,6 +23,7 @@ impl std::error::Error for SamplingError {
             SamplingError::Indices(ref err) => Some(err),
             SamplingError::Input(ref err) => Some(err),
             SamplingError::Node(ref err) => Some(err),
+            SamplingError::Io(ref err) => Some(err),
             _ => None,
         }
     }
@@ -33,6 +38,11 @@ impl std::fmt::Display for SamplingError {
             SamplingError::MaxIterations(max_iter) => {
                 write!(f, "max iterations ({max_iter}) reached")
             }
+            SamplingError::EmptySample => write!(
+                f,
+                "the design yields an empty sample, as the probabilities sum to zero"
+            ),
+            SamplingError::Io(ref err) => err.fmt(f),
         }
     }
 }
@@ -52,3 +62,8 @@ impl From<NodeError> for SamplingError {
         SamplingError::Node(err)
     }
 }
+impl From<std::io::Error> for SamplingError {
+    fn from(err: std::io::Error) -> SamplingError {
+        SamplingError::Io(err)
+    }
+}
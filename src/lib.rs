@@ -23,16 +23,53 @@
 //! The samples can then be seen as a miniature version of the population.
 //! This generally yields low variances for the variable of interest, if there is a general
 //! relationship between the auxilliaries and the variables of interest.
+//!
+//! **Random number generators** are passed as `&mut R` with `R: Rng + ?Sized`, hence any rng may
+//! be used, including a trait object `&mut dyn RngCore` chosen at runtime.
+//! Only designs that need to seed new rngs, e.g. [`unequal::sampford_resumable`], require a
+//! concrete rng type.
+//!
+//! ```
+//! use envisim_samplr::{unequal::pareto, SampleOptions};
+//! use rand::rngs::{SmallRng, StdRng};
+//! use rand::{RngCore, SeedableRng};
+//!
+//! let use_std = true; // e.g. read from a config
+//! let mut rng: Box<dyn RngCore> = if use_std {
+//!     Box::new(StdRng::seed_from_u64(4242))
+//! } else {
+//!     Box::new(SmallRng::seed_from_u64(4242))
+//! };
+//!
+//! let p = [0.2, 0.25, 0.35, 0.4, 0.5, 0.5, 0.55, 0.65, 0.7, 0.9];
+//! let s = SampleOptions::new(&p)?.sample(rng.as_mut(), pareto)?;
+//!
+//! assert_eq!(s.len(), 5);
+//! # Ok::<(), envisim_samplr::SamplingError>(())
+//! ```
 
+pub mod bas;
+pub mod bench_support;
+pub mod controlled;
+pub mod coordinated;
 pub mod cube_method;
 mod error;
+pub mod multiphase;
 pub mod pivotal_method;
 pub mod poisson;
+mod prepared;
+mod rng;
 mod sample_options;
+pub mod spatial;
+pub mod splitting;
 pub mod srs;
 pub mod systematic;
+pub mod test_support;
 pub mod unequal;
 mod utils;
 
 pub use error::SamplingError;
-pub use sample_options::{SampleOptions, Sampler};
+pub use prepared::{PreparedDesign, PreparedFrame};
+pub use rng::{derive_rng, AntitheticRng};
+pub use sample_options::{ProgressEvent, ProgressHook, SampleOptions, Sampler};
+pub use utils::{mask_to_sample, merge_samples, sample_jaccard, sample_overlap, sample_to_mask};
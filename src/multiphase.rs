@@ -0,0 +1,130 @@
+// Copyright (C) 2024 Wilmer Prentius, Anton Grafström.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU Affero General Public License as published by the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Multi-phase sampling designs
+
+pub use crate::{SampleOptions, SamplingError};
+use envisim_utils::{InputError, Probabilities};
+use rand::Rng;
+
+/// A two-phase sample, see [`two_phase`].
+#[derive(Clone, Debug)]
+pub struct TwoPhaseSample {
+    /// The units of the first phase sample
+    pub first_phase: Vec<usize>,
+    /// The units of the second phase sample, a subset of the first phase sample
+    pub second_phase: Vec<usize>,
+    /// The two-phase inclusion probabilities of the units in `second_phase` (in the same order),
+    /// i.e. the product of the first phase probability and the conditional second phase
+    /// probability
+    pub probabilities: Vec<f64>,
+}
+
+/// Draw a two-phase sample.
+/// The first phase sample is drawn using `phase1_design` and `phase1_options`.
+/// Then, `phase2_probabilities` is called with the first phase sample, and should return the
+/// conditional inclusion probabilities of the second phase, one for each first phase unit (in the
+/// same order).
+/// The second phase sample is drawn from the first phase sample using `phase2_design` and these
+/// conditional probabilities.
+/// The second phase options are those of `phase1_options`, where the auxiliaries, balancing
+/// variables and random values are restricted to the first phase units.
+/// If the first phase sample is empty, the second phase is not drawn.
+///
+/// The returned two-phase probabilities, `pi_1 * pi_2|1`, are used in the estimation of the
+/// second phase variables, e.g. by the Horvitz-Thompson estimator.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::multiphase::*;
+/// use envisim_samplr::poisson;
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let p = [0.6; 10];
+/// let s = two_phase(
+///     &mut rng,
+///     &SampleOptions::new(&p)?,
+///     poisson::sample,
+///     |s1| vec![0.5; s1.len()],
+///     poisson::sample,
+/// )?;
+///
+/// assert!(s.second_phase.iter().all(|id| s.first_phase.contains(id)));
+/// assert!(s.probabilities.iter().all(|&p| (p - 0.3).abs() < 1e-12));
+/// # Ok::<(), SamplingError>(())
+/// ```
+///
+/// # References
+/// Särndal, C. E., Swensson, B., & Wretman, J. (1992).
+/// Model assisted survey sampling. Chapter 9.
+/// Springer-Verlag.
+pub fn two_phase<R, F1, G, F2>(
+    rng: &mut R,
+    phase1_options: &SampleOptions,
+    phase1_design: F1,
+    phase2_probabilities: G,
+    phase2_design: F2,
+) -> Result<TwoPhaseSample, SamplingError>
+where
+    R: Rng + ?Sized,
+    F1: FnOnce(&mut R, &SampleOptions) -> Result<Vec<usize>, SamplingError>,
+    G: FnOnce(&[usize]) -> Vec<f64>,
+    F2: FnOnce(&mut R, &SampleOptions) -> Result<Vec<usize>, SamplingError>,
+{
+    let first_phase = phase1_design(rng, phase1_options)?;
+
+    if first_phase.is_empty() {
+        return Ok(TwoPhaseSample {
+            first_phase,
+            second_phase: vec![],
+            probabilities: vec![],
+        });
+    }
+
+    let conditional = phase2_probabilities(&first_phase);
+    InputError::check_lengths(&conditional, &first_phase)?;
+    Probabilities::check(&conditional)?;
+
+    // The population level inputs are restricted to the first phase units
+    let auxiliaries = phase1_options
+        .auxiliaries
+        .map(|m| m.submatrix(&first_phase, &(0..m.ncol()).collect::<Vec<usize>>()))
+        .transpose()?;
+    let balancing = phase1_options
+        .balancing
+        .map(|m| m.submatrix(&first_phase, &(0..m.ncol()).collect::<Vec<usize>>()))
+        .transpose()?;
+    let random_values: Option<Vec<f64>> = phase1_options
+        .random_values
+        .map(|v| first_phase.iter().map(|&id| v[id]).collect());
+
+    let phase2_options = SampleOptions {
+        probabilities: &conditional,
+        auxiliaries: auxiliaries.as_ref(),
+        balancing: balancing.as_ref(),
+        random_values: random_values.as_deref(),
+        ..*phase1_options
+    };
+
+    let second_phase: Vec<usize> = phase2_design(rng, &phase2_options)?;
+    let probabilities = second_phase
+        .iter()
+        .map(|&k| phase1_options.probabilities[first_phase[k]] * conditional[k])
+        .collect();
+
+    Ok(TwoPhaseSample {
+        second_phase: second_phase.iter().map(|&k| first_phase[k]).collect(),
+        first_phase,
+        probabilities,
+    })
+}
@@ -62,6 +62,13 @@ pub struct LocalPivotalMethod2<'a> {
 /// Draw a sample using the sequential pivotal method.
 /// A variant of the pivotal method where unit competes in order.
 ///
+/// The units are processed in index order, where the pivotal update is applied to the two
+/// first undecided units, i.e. a pair of adjacent units of the list.
+/// Thus, the sample is spread along the list order, and adjacent units are rarely selected
+/// together, which makes the design suitable for e.g. temporally ordered frames.
+/// If the probabilities do not sum to an integer, the sample size is random, see
+/// [`ordered_pivotal`] for a fixed size version.
+///
 /// # Examples
 /// ```
 /// use envisim_samplr::pivotal_method::*;
@@ -87,6 +94,39 @@ where
 {
     spm_new(rng, options)?.sample_with_return()
 }
+
+/// Draw a sample using the ordered pivotal method, i.e. the sequential pivotal method [`spm`],
+/// where the probabilities must sum to an integer.
+/// The units are processed in index order, and the sample is spread along the list order, e.g.
+/// in time for temporally ordered frames.
+/// Returns an error if the probabilities do not sum to an integer (within `eps`).
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::pivotal_method::*;
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let p = [0.2, 0.25, 0.35, 0.4, 0.5, 0.5, 0.55, 0.65, 0.7, 0.9];
+/// let s = SampleOptions::new(&p)?.sample(&mut rng, ordered_pivotal)?;
+///
+/// assert_eq!(s.len(), 5);
+/// # Ok::<(), SamplingError>(())
+/// ```
+///
+/// # References
+/// Deville, J. C., & Tille, Y. (1998).
+/// Unequal probability sampling without replacement through a splitting method.
+/// Biometrika, 85(1), 89-101.
+/// <https://doi.org/10.1093/biomet/85.1.89>
+#[inline]
+pub fn ordered_pivotal<R>(rng: &mut R, options: &SampleOptions) -> Result<Vec<usize>, SamplingError>
+where
+    R: Rng + ?Sized,
+{
+    InputError::check_integer_approx(sum(options.probabilities), options.eps)?;
+    spm(rng, options)
+}
 #[inline]
 fn spm_new<'a, R>(
     rng: &'a mut R,
@@ -458,7 +498,7 @@ where
                     .find_neighbours_of_id(&self.tree, self.candidates[i])
                     .unwrap();
 
-                if self.searcher.neighbours().iter().any(|&id| id == id1) {
+                if self.searcher.neighbours().contains(&id1) {
                     i += 1;
                 } else {
                     self.candidates.swap_remove(i);
@@ -473,9 +513,8 @@ where
     }
     #[inline]
     fn decide_unit(&mut self, container: &mut Container<'a, R>, id: usize) -> Option<bool> {
-        container.decide_unit(id).unwrap().map(|r| {
+        container.decide_unit(id).unwrap().inspect(|_| {
             self.tree.remove_unit(id).unwrap();
-            r
         })
     }
 }
@@ -524,7 +563,7 @@ where
                     .find_neighbours_of_id(&self.tree, self.candidates[i])
                     .unwrap();
 
-                if self.searcher.neighbours().iter().any(|&id| id == id1) {
+                if self.searcher.neighbours().contains(&id1) {
                     i += 1;
                 } else {
                     // If we does not find any compatible matches, we use the candidates to continue our seach
@@ -549,9 +588,8 @@ where
     }
     #[inline]
     fn decide_unit(&mut self, container: &mut Container<'a, R>, id: usize) -> Option<bool> {
-        container.decide_unit(id).unwrap().map(|r| {
+        container.decide_unit(id).unwrap().inspect(|_| {
             self.tree.remove_unit(id).unwrap();
-            r
         })
     }
 }
@@ -579,9 +617,8 @@ where
     }
     #[inline]
     fn decide_unit(&mut self, container: &mut Container<'a, R>, id: usize) -> Option<bool> {
-        container.decide_unit(id).unwrap().map(|r| {
+        container.decide_unit(id).unwrap().inspect(|_| {
             self.tree.remove_unit(id).unwrap();
-            r
         })
     }
 }
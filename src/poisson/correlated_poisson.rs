@@ -444,9 +444,8 @@ where
     }
     #[inline]
     fn decide_unit(&mut self, container: &mut Container<'a, R>, id: usize) -> Option<bool> {
-        container.decide_unit(id).unwrap().map(|r| {
+        container.decide_unit(id).unwrap().inspect(|_| {
             self.tree.remove_unit(id).unwrap();
-            r
         })
     }
 }
@@ -546,11 +545,11 @@ mod tests {
 
         let mut cps = cps_new(&mut rng, &SampleOptions::new(&PROB_10_E)?)?;
         decide_and_update(&mut cps, 0, 0.0);
-        assert_fvec(&cps.container.probabilities().data()[1..=4], &vec![0.0; 4]);
+        assert_fvec(&cps.container.probabilities().data()[1..=4], &[0.0; 4]);
 
         let mut cps = cps_new(&mut rng, &SampleOptions::new(&PROB_10_E)?)?;
         decide_and_update(&mut cps, 0, 0.999);
-        assert_fvec(&cps.container.probabilities().data()[1..=4], &vec![0.25; 4]);
+        assert_fvec(&cps.container.probabilities().data()[1..=4], &[0.25; 4]);
         Ok(())
     }
 
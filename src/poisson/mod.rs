@@ -12,7 +12,10 @@
 
 //! Poisson method designs
 
+use crate::ProgressEvent;
 pub use crate::{SampleOptions, SamplingError};
+use envisim_utils::pips::pips_rescale;
+use envisim_utils::utils::usize_to_f64;
 use envisim_utils::{InputError, Probabilities};
 use rand::Rng;
 
@@ -28,7 +31,7 @@ where
     probabilities
         .iter()
         .enumerate()
-        .filter_map(|(i, &p)| (rng.gen::<f64>() <= p).then_some(i))
+        .filter_map(|(i, &p)| (rng.gen::<f64>() < p).then_some(i))
         .collect()
 }
 
@@ -53,6 +56,133 @@ where
     Ok(internal(rng, probabilities))
 }
 
+/// Draw a sample using a poisson design, where the probabilities are supplied by an iterator
+/// rather than a slice.
+/// The design is drawn in a single pass, and only the sample is kept in memory, hence the
+/// probabilities may be generated lazily (e.g. read from disk) for very large populations.
+/// Given the same rng, the sample equals that of [`sample`] on the collected probabilities.
+/// Returns an error if any probability is invalid, or if the iterator is empty.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::poisson::*;
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let p = (0..1000).map(|i| if i % 2 == 0 { 0.004 } else { 0.006 });
+/// let s = sample_from_iter(&mut rng, p)?;
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn sample_from_iter<R, I>(rng: &mut R, probabilities: I) -> Result<Vec<usize>, SamplingError>
+where
+    R: Rng + ?Sized,
+    I: IntoIterator<Item = f64>,
+{
+    let mut sample = Vec::<usize>::new();
+    let mut n_units: usize = 0;
+
+    for (id, p) in probabilities.into_iter().enumerate() {
+        InputError::check_nan(p).and(InputError::check_range_f64(p, 0.0, 1.0))?;
+
+        if rng.gen::<f64>() < p {
+            sample.push(id);
+        }
+
+        n_units += 1;
+    }
+
+    if n_units == 0 {
+        return Err(InputError::IsEmpty.into());
+    }
+
+    Ok(sample)
+}
+
+/// Draw a sample using a poisson design, with a pre-generated uniform random number per unit.
+/// Unit `i` is included if `uniforms[i] < probabilities[i]`, or if `probabilities[i]` is `1.0`,
+/// hence units with probability `1.0` are included even if their uniform is `1.0`.
+/// Reusing the same uniforms for related probability vectors induces dependence between the
+/// samples, e.g. the samples are nested if the probabilities are monotone, and the uniforms may
+/// also be quasi-random.
+/// Returns an error if `probabilities` is empty, if `uniforms` and `probabilities` have different
+/// lengths, or if any uniform is not in `[0.0, 1.0]`.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::poisson::*;
+///
+/// let u = [0.1, 0.8, 0.35, 0.6, 0.05];
+/// let s = sample_with_uniforms(&u, &[0.4; 5])?;
+///
+/// assert_eq!(s, vec![0, 2, 4]);
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn sample_with_uniforms(
+    uniforms: &[f64],
+    probabilities: &[f64],
+) -> Result<Vec<usize>, SamplingError> {
+    InputError::check_empty(probabilities)
+        .and(InputError::check_lengths(uniforms, probabilities))
+        .and(Probabilities::check(probabilities))?;
+    uniforms.iter().try_for_each(|&u| {
+        InputError::check_nan(u).and(InputError::check_range_f64(u, 0.0, 1.0))
+    })?;
+
+    Ok(uniforms
+        .iter()
+        .zip(probabilities.iter())
+        .enumerate()
+        .filter_map(|(i, (&u, &p))| (u < p || p >= 1.0).then_some(i))
+        .collect())
+}
+
+/// Inclusion probabilities proportional to size, with expected sample size `expected_n`.
+/// Given an array of non-negative sizes, returns inclusion probabilities proportional to size,
+/// summing to `expected_n`.
+/// Units whose probability would exceed `1.0` (within `eps`) are given probability `1.0`, and
+/// the remaining probability is redistributed among the other units.
+/// As for [`envisim_utils::pips::pips_from_slice`], the redistribution preserves the relative
+/// order of the sizes, and the probabilities of the units not given probability `1.0` are
+/// proportional to size.
+/// Returns an error if `sizes` is empty, if any size is negative, if `expected_n` is
+/// non-positive, or if `expected_n` exceeds the number of units with positive size.
+/// Returns [`InputError::TooManyCertaintyUnits`] if more than `expected_n` units would be given
+/// probability `1.0`, see [`envisim_utils::pips::pips_rescale`].
+///
+/// The probabilities are intended for use with [`sample`], where the realized sample size is
+/// random, with expectation `expected_n`.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::poisson::*;
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let sizes = [1.0, 2.0, 3.0, 4.0, 10.0];
+/// let p = from_expected_size(&sizes, 2.5, 1e-12)?;
+///
+/// assert_eq!(p[4], 1.0);
+/// assert!((p.iter().sum::<f64>() - 2.5).abs() < 1e-12);
+/// let s = SampleOptions::new(&p)?.sample(&mut rng, sample)?;
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn from_expected_size(
+    sizes: &[f64],
+    expected_n: f64,
+    eps: f64,
+) -> Result<Vec<f64>, SamplingError> {
+    InputError::check_empty(sizes)
+        .and(Probabilities::check_eps(eps))
+        .and(InputError::check_positive(expected_n))?;
+    sizes.iter().try_for_each(|&x| {
+        InputError::check_nan(x).and(InputError::check_range_f64(x, 0.0, f64::INFINITY))
+    })?;
+    let n_positive = sizes.iter().filter(|&&x| x > 0.0).count();
+    InputError::check_range_f64(expected_n, 0.0, usize_to_f64(n_positive) + eps)?;
+
+    Ok(pips_rescale(sizes, expected_n, eps)?.0)
+}
+
 /// Draw a sample using a conditional poisson design.
 /// Redraws a poisson sample until the fixed sample size is achieved.
 /// May terminate after `max_iterations`.
@@ -81,11 +211,56 @@ where
     Probabilities::check(probabilities)
         .and(InputError::check_sample_size(sample_size, population_size))?;
 
-    for _ in 0..options.max_iterations.get() {
-        let s = internal(rng, probabilities);
+    rejective(rng, options, |s| s.len() == sample_size)
+}
+
+/// Draw a sample using a rejective poisson design, where poisson samples are drawn until one is
+/// accepted by the predicate `accept`.
+/// The design is thus a poisson design conditioned on the predicate, e.g. conditional poisson
+/// sampling if `accept` checks the sample size, or a poisson design conditioned on the sample
+/// including at least one unit from each region.
+/// Each poisson sample counts as an iteration towards `max_iterations`, after which
+/// [`SamplingError::MaxIterations`] is returned.
+/// Reports a [`crate::ProgressEvent::Iteration`] for each iteration, if a progress hook is set.
+///
+/// **Note:** the inclusion probabilities of the design are generally _not_ equal to the
+/// probabilities, and an arbitrary predicate may make acceptance rare, or impossible.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::poisson::*;
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let p = [0.2, 0.25, 0.35, 0.4, 0.5, 0.5, 0.55, 0.65, 0.7, 0.9];
+/// let options = SampleOptions::new(&p)?;
+/// let s = rejective(&mut rng, &options, |s| s.len() == 5)?;
+///
+/// assert_eq!(s.len(), 5);
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn rejective<R, F>(
+    rng: &mut R,
+    options: &SampleOptions,
+    accept: F,
+) -> Result<Vec<usize>, SamplingError>
+where
+    R: Rng + ?Sized,
+    F: Fn(&[usize]) -> bool,
+{
+    let probabilities = options.probabilities;
+    Probabilities::check(probabilities)?;
+
+    for iteration in 0..options.max_iterations.get() {
+        options.report(ProgressEvent::Iteration {
+            iteration,
+            max_iterations: options.max_iterations,
+        });
+
+        let sample = internal(rng, probabilities);
 
-        if s.len() == sample_size {
-            return Ok(s);
+        if accept(&sample) {
+            return Ok(sample);
         }
     }
 
@@ -0,0 +1,195 @@
+// Copyright (C) 2024 Wilmer Prentius, Anton Grafström.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU Affero General Public License as published by the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::SamplingError;
+use envisim_utils::utils::{sum, usize_to_f64};
+use envisim_utils::{InputError, Probabilities};
+use rand::Rng;
+
+/// The designs that can be drawn from a [`PreparedFrame`]
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug)]
+pub enum PreparedDesign {
+    /// Systematic sampling in the order of the frame, see [`crate::systematic::sample`].
+    /// Uses the cumulative sums, such that each unit is found by a binary search.
+    Systematic,
+    /// With replacement sampling of `n` units, see [`crate::unequal::with_replacement`].
+    /// Uses an alias table, such that each unit is drawn in constant time.
+    WithReplacement(usize),
+}
+
+/// A frame of probabilities, prepared for repeated sampling.
+/// The structures needed by the designs of [`PreparedDesign`] are computed once, when the frame
+/// is created, rather than each time a sample is drawn.
+/// This benefits systematic sampling, which otherwise traverses the full frame for each sample,
+/// and with replacement sampling, which otherwise sorts `n` random numbers.
+/// Other designs gain nothing from a prepared frame, and should be used directly.
+/// The frame is immutable, and may be shared between threads.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::{PreparedDesign, PreparedFrame};
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let p = [0.2, 0.25, 0.35, 0.4, 0.5, 0.5, 0.55, 0.65, 0.7, 0.9];
+/// let frame = PreparedFrame::new(&p, 1e-12)?;
+///
+/// assert_eq!(frame.sample(&mut rng, PreparedDesign::Systematic)?.len(), 5);
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct PreparedFrame {
+    probabilities: Vec<f64>,
+    eps: f64,
+    // The cumulative sums, such that unit i covers [cumulative[i], cumulative[i + 1]]
+    cumulative: Vec<f64>,
+    // The alias table of the draw probabilities p / sum(p)
+    alias_probabilities: Vec<f64>,
+    aliases: Vec<usize>,
+}
+
+impl PreparedFrame {
+    /// Prepares a frame of probabilities, where `eps` is the tolerance used when checking the sum
+    /// of the probabilities, as for [`crate::SampleOptions`].
+    /// Returns an error if the probabilities are empty or invalid, or if `eps` is invalid.
+    pub fn new(probabilities: &[f64], eps: f64) -> Result<Self, SamplingError> {
+        InputError::check_empty(probabilities)
+            .and(Probabilities::check(probabilities))
+            .and(Probabilities::check_eps(eps).map(|_| ()))?;
+
+        let mut cumulative = Vec::<f64>::with_capacity(probabilities.len() + 1);
+        cumulative.push(0.0);
+        for &p in probabilities.iter() {
+            cumulative.push(cumulative.last().unwrap() + p);
+        }
+
+        let (alias_probabilities, aliases) = alias_table(probabilities);
+
+        Ok(Self {
+            probabilities: probabilities.to_vec(),
+            eps,
+            cumulative,
+            alias_probabilities,
+            aliases,
+        })
+    }
+    /// Returns the probabilities of the frame
+    #[inline]
+    pub fn probabilities(&self) -> &[f64] {
+        &self.probabilities
+    }
+    /// Draws a sample from the frame using `design`.
+    /// The samples are sorted.
+    /// Returns an error if the probabilities do not sum to 1.0 (within `eps`) when sampling with
+    /// replacement.
+    pub fn sample<R>(
+        &self,
+        rng: &mut R,
+        design: PreparedDesign,
+    ) -> Result<Vec<usize>, SamplingError>
+    where
+        R: Rng + ?Sized,
+    {
+        match design {
+            PreparedDesign::Systematic => Ok(self.systematic(rng.gen())),
+            PreparedDesign::WithReplacement(n) => {
+                InputError::check_integer_approx_equal(sum(&self.probabilities), 1.0, self.eps)?;
+                let mut sample: Vec<usize> = (0..n).map(|_| self.draw(rng)).collect();
+                sample.sort_unstable();
+                Ok(sample)
+            }
+        }
+    }
+    // Selects the units covering rv, rv + 1, ...
+    fn systematic(&self, rv: f64) -> Vec<usize> {
+        let total = *self.cumulative.last().unwrap();
+        let mut sample = Vec::<usize>::with_capacity(total.ceil() as usize);
+        let mut r = rv;
+        let mut start: usize = 0;
+
+        while r <= total {
+            // The first unit with a positive probability, covering r
+            let mut id = start + self.cumulative[start + 1..].partition_point(|&c| c < r);
+            while id < self.probabilities.len() && self.probabilities[id] <= 0.0 {
+                id += 1;
+            }
+            if id == self.probabilities.len() {
+                break;
+            }
+
+            sample.push(id);
+            start = id + 1;
+            r += 1.0;
+
+            if start == self.probabilities.len() {
+                break;
+            }
+        }
+
+        sample
+    }
+    #[inline]
+    fn draw<R>(&self, rng: &mut R) -> usize
+    where
+        R: Rng + ?Sized,
+    {
+        let id = rng.gen_range(0..self.aliases.len());
+
+        if rng.gen::<f64>() < self.alias_probabilities[id] {
+            id
+        } else {
+            self.aliases[id]
+        }
+    }
+}
+
+// Vose's alias method
+fn alias_table(probabilities: &[f64]) -> (Vec<f64>, Vec<usize>) {
+    let population_size = probabilities.len();
+    let scale = usize_to_f64(population_size) / sum(probabilities);
+    let mut scaled: Vec<f64> = probabilities.iter().map(|&p| p * scale).collect();
+    let mut aliases: Vec<usize> = (0..population_size).collect();
+
+    if !scale.is_finite() {
+        return (vec![1.0; population_size], aliases);
+    }
+
+    // Units with zero probability are placed last, in order to be paired first
+    let (mut small, mut large): (Vec<usize>, Vec<usize>) =
+        (0..population_size).partition(|&i| scaled[i] < 1.0);
+    small.sort_by(|&a, &b| scaled[b].total_cmp(&scaled[a]));
+
+    while let (Some(&s), Some(&l)) = (small.last(), large.last()) {
+        small.pop();
+        aliases[s] = l;
+        scaled[l] -= 1.0 - scaled[s];
+
+        if scaled[l] < 1.0 {
+            large.pop();
+            small.push(l);
+        }
+    }
+
+    let last_positive = probabilities.iter().rposition(|&p| p > 0.0).unwrap();
+    for i in large.into_iter().chain(small) {
+        // Remaining due to rounding errors
+        if probabilities[i] > 0.0 {
+            scaled[i] = 1.0;
+        } else {
+            scaled[i] = 0.0;
+            aliases[i] = last_positive;
+        }
+    }
+
+    (scaled, aliases)
+}
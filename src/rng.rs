@@ -0,0 +1,147 @@
+// Copyright (C) 2024 Wilmer Prentius, Anton Grafström.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU Affero General Public License as published by the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+use rand::rngs::SmallRng;
+use rand::{RngCore, SeedableRng};
+
+#[inline]
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Mixes `master_seed` and `stream_id` into a seed for stream `stream_id`.
+#[inline]
+pub(crate) fn derive_seed(master_seed: u64, stream_id: u64) -> u64 {
+    splitmix64(splitmix64(master_seed) ^ splitmix64(stream_id.wrapping_add(0x632b_e59b_d9b4_e019)))
+}
+
+/// Returns a rng for stream `stream_id`, derived from `master_seed`.
+/// The master seed and the stream id are mixed using SplitMix64, such that consecutive stream
+/// ids (e.g. strata or replicates) yield well separated, reproducible streams.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::derive_rng;
+/// use rand::Rng;
+///
+/// let mut rng_0 = derive_rng(4242, 0);
+/// let mut rng_1 = derive_rng(4242, 1);
+/// assert_ne!(rng_0.gen::<u64>(), rng_1.gen::<u64>());
+/// assert_eq!(derive_rng(4242, 1).gen::<u64>(), derive_rng(4242, 1).gen::<u64>());
+/// ```
+///
+/// # References
+/// Steele, G. L., Lea, D., & Flood, C. H. (2014).
+/// Fast splittable pseudorandom number generators.
+/// ACM SIGPLAN Notices, 49(10), 453-472.
+/// <https://doi.org/10.1145/2714064.2660195>
+pub fn derive_rng(master_seed: u64, stream_id: u64) -> SmallRng {
+    SmallRng::seed_from_u64(derive_seed(master_seed, stream_id))
+}
+
+/// A rng producing antithetic pairs of random number streams, for variance reduction in Monte
+/// Carlo studies.
+/// The first member of a pair passes on, and records, the numbers of the inner rng.
+/// The second member replays the recorded numbers with all bits flipped, such that a uniform `u`
+/// drawn by `gen::<f64>()` is replayed as `1 - u` (up to `2^-53`).
+/// If the second member consumes more numbers than the first, the remaining numbers are taken from
+/// the inner rng.
+///
+/// Designs that consume their uniforms in a predictable order, e.g.
+/// [`crate::poisson::sample`], [`crate::systematic::sample`] and [`crate::unequal::pareto`],
+/// produce negatively correlated samples for the two members of a pair.
+/// Other designs, e.g. rejective designs, remain valid, but gain little.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::{derive_rng, AntitheticRng};
+/// use rand::Rng;
+///
+/// let mut rng = AntitheticRng::new(derive_rng(4242, 0));
+/// let u: f64 = rng.gen();
+/// rng.replay();
+/// let v: f64 = rng.gen();
+///
+/// assert!((u + v - 1.0).abs() < 1e-15);
+/// ```
+#[derive(Clone, Debug)]
+pub struct AntitheticRng<R> {
+    rng: R,
+    recorded: Vec<u64>,
+    // The position of the replay, or None if recording
+    position: Option<usize>,
+}
+
+impl<R> AntitheticRng<R>
+where
+    R: RngCore,
+{
+    /// Wraps `rng`, starting the first member of a pair
+    pub fn new(rng: R) -> Self {
+        Self {
+            rng,
+            recorded: Vec::new(),
+            position: None,
+        }
+    }
+    /// Starts the first member of a new pair, discarding the recorded numbers
+    #[inline]
+    pub fn new_pair(&mut self) {
+        self.recorded.clear();
+        self.position = None;
+    }
+    /// Starts the second member of the current pair, replaying the recorded numbers flipped
+    #[inline]
+    pub fn replay(&mut self) {
+        self.position = Some(0);
+    }
+}
+
+impl<R> RngCore for AntitheticRng<R>
+where
+    R: RngCore,
+{
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        match self.position {
+            Some(ref mut position) if *position < self.recorded.len() => {
+                *position += 1;
+                !self.recorded[*position - 1]
+            }
+            Some(_) => self.rng.next_u64(),
+            None => {
+                let value = self.rng.next_u64();
+                self.recorded.push(value);
+                value
+            }
+        }
+    }
+    #[inline]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+    #[inline]
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
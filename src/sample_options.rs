@@ -1,14 +1,38 @@
 use crate::SamplingError;
 use envisim_utils::kd_tree::{midpoint_slide, FindSplit, Node, TreeBuilder};
+use envisim_utils::utils::{sum, usize_to_f64};
 use envisim_utils::{InputError, Matrix, Probabilities};
 use rand::Rng;
-use std::num::NonZeroUsize;
+use std::num::{NonZeroU32, NonZeroUsize};
+
+/// Progress reported by long-running designs and simulations, to a hook set by
+/// [`SampleOptions::progress`].
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug)]
+pub enum ProgressEvent {
+    /// A (rejective) design has started iteration `iteration` out of at most `max_iterations`.
+    Iteration {
+        iteration: usize,
+        max_iterations: NonZeroUsize,
+    },
+    /// A simulation has completed `replicate` out of `reps` replicates.
+    /// Replicates drawn in parallel may be reported in batches.
+    Replicate { replicate: u32, reps: NonZeroU32 },
+}
+
+/// A hook receiving [`ProgressEvent`]s, see [`SampleOptions::progress`].
+/// The hook may be called from several threads, and should return quickly.
+pub type ProgressHook<'a> = &'a (dyn Fn(ProgressEvent) + Sync);
 
 pub struct SampleOptions<'a> {
     // Base
     pub(crate) probabilities: &'a [f64],
     pub(crate) eps: f64,
     pub(crate) max_iterations: NonZeroUsize,
+    pub(crate) progress: Option<ProgressHook<'a>>,
+    pub(crate) sorted_output: bool,
+    pub(crate) pareto_calibration: bool,
+    pub(crate) allow_empty_sample: bool,
 
     // Spatially balanced sampling
     pub(crate) auxiliaries: Option<&'a Matrix<'a>>,
@@ -25,12 +49,16 @@ pub struct SampleOptions<'a> {
 impl<'a> SampleOptions<'a> {
     #[inline]
     pub fn new(probabilities: &'a [f64]) -> Result<Self, InputError> {
-        Probabilities::check(probabilities)?;
+        InputError::check_empty(probabilities).and(Probabilities::check(probabilities))?;
 
         Ok(Self {
             probabilities,
             eps: 1e-12,
             max_iterations: unsafe { NonZeroUsize::new_unchecked(1000) },
+            progress: None,
+            sorted_output: true,
+            pareto_calibration: false,
+            allow_empty_sample: true,
             auxiliaries: None,
             bucket_size: unsafe { NonZeroUsize::new_unchecked(40) },
             split_method: midpoint_slide,
@@ -38,6 +66,108 @@ impl<'a> SampleOptions<'a> {
             random_values: None,
         })
     }
+    /// Creates options from probabilities whose sum is within `tolerance` of an integer, e.g.
+    /// due to rounding when converting sizes to probabilities.
+    /// The probabilities are silently adjusted in place, by proportional rescaling, such that they
+    /// sum to the nearest integer.
+    /// Units that would get a probability larger than 1.0 are given probability 1.0, and the
+    /// remaining units are rescaled again.
+    /// If the sum is further than `tolerance` from an integer, or rounds to zero, the probabilities
+    /// are left as is.
+    /// Returns an error if `tolerance` is not in `[0.0, 0.5)`, or if the probabilities are
+    /// invalid.
+    ///
+    /// # Examples
+    /// ```
+    /// use envisim_samplr::SampleOptions;
+    ///
+    /// let mut p = [0.5, 0.5, 1.0000001];
+    /// SampleOptions::new_snapped(&mut p, 1e-6)?;
+    /// assert_eq!(p[2], 1.0);
+    /// # Ok::<(), envisim_utils::InputError>(())
+    /// ```
+    pub fn new_snapped(probabilities: &'a mut [f64], tolerance: f64) -> Result<Self, InputError> {
+        InputError::check_range_f64(tolerance, 0.0, 0.5)
+            .and(InputError::check_valid_f64(tolerance, 0.5))?;
+        InputError::check_empty(probabilities)?;
+        probabilities
+            .iter()
+            .try_for_each(|&p| InputError::check_range_f64(p, 0.0, 1.0 + tolerance))?;
+
+        let target = sum(probabilities).round();
+
+        if target > 0.0 && (sum(probabilities) - target).abs() <= tolerance {
+            let mut certain = vec![false; probabilities.len()];
+
+            loop {
+                let n_certain = usize_to_f64(certain.iter().filter(|&&c| c).count());
+                let rest: f64 = probabilities
+                    .iter()
+                    .zip(certain.iter())
+                    .filter_map(|(&p, &c)| (!c).then_some(p))
+                    .sum();
+                let scale = if rest > 0.0 {
+                    (target - n_certain) / rest
+                } else {
+                    1.0
+                };
+                let mut changed = false;
+
+                for (p, c) in probabilities.iter_mut().zip(certain.iter_mut()) {
+                    if *c {
+                        continue;
+                    }
+                    *p *= scale;
+                    if *p >= 1.0 {
+                        *p = 1.0;
+                        *c = true;
+                        changed = true;
+                    }
+                }
+
+                if !changed {
+                    break;
+                }
+            }
+        }
+
+        Self::new(probabilities)
+    }
+    /// Returns the inclusion probabilities
+    #[inline]
+    pub fn probabilities(&self) -> &'a [f64] {
+        self.probabilities
+    }
+    /// Returns the expected sample size, i.e. the sum of the inclusion probabilities
+    ///
+    /// # Examples
+    /// ```
+    /// use envisim_samplr::SampleOptions;
+    ///
+    /// let p = [0.2, 0.5, 0.8, 0.25];
+    /// assert_eq!(SampleOptions::new(&p)?.expected_size(), 1.75);
+    /// # Ok::<(), envisim_utils::InputError>(())
+    /// ```
+    #[inline]
+    pub fn expected_size(&self) -> f64 {
+        sum(self.probabilities)
+    }
+    /// Returns `true` if the sum of the inclusion probabilities is within `eps` of an integer,
+    /// which is required by the fixed size designs, e.g. [`crate::unequal::brewer`]
+    ///
+    /// # Examples
+    /// ```
+    /// use envisim_samplr::SampleOptions;
+    ///
+    /// let p = [0.2, 0.5, 0.8, 0.5];
+    /// assert!(SampleOptions::new(&p)?.is_fixed_size());
+    /// assert!(!SampleOptions::new(&p[1..])?.is_fixed_size());
+    /// # Ok::<(), envisim_utils::InputError>(())
+    /// ```
+    #[inline]
+    pub fn is_fixed_size(&self) -> bool {
+        InputError::check_integer_approx(self.expected_size(), self.eps).is_ok()
+    }
     #[inline]
     pub fn eps(&mut self, eps: f64) -> Result<&mut Self, InputError> {
         self.eps = Probabilities::check_eps(eps)?;
@@ -51,6 +181,48 @@ impl<'a> SampleOptions<'a> {
         self.max_iterations = max_iterations;
         Ok(self)
     }
+    /// Sets a hook receiving [`ProgressEvent`]s from designs that support it, e.g.
+    /// [`crate::unequal::sampford`], and from simulations repeatedly drawing samples using the
+    /// options.
+    #[inline]
+    pub fn progress(&mut self, progress: ProgressHook<'a>) -> Result<&mut Self, InputError> {
+        self.progress = Some(progress);
+        Ok(self)
+    }
+    /// Sets whether designs should sort the returned sample (default `true`).
+    /// If `false`, designs that would otherwise sort their sample, such as
+    /// [`crate::unequal::sampford`] and [`crate::unequal::brewer`], return the units in order of
+    /// selection.
+    /// Only the default guarantees that the sample is sorted.
+    #[inline]
+    pub fn sorted_output(&mut self, sorted_output: bool) -> Result<&mut Self, InputError> {
+        self.sorted_output = sorted_output;
+        Ok(self)
+    }
+    /// Sets whether [`crate::unequal::pareto`] calibrates its shape parameters, such that the
+    /// exact inclusion probabilities equal the probabilities (default `false`).
+    /// See [`crate::unequal::pareto_parameters`].
+    #[inline]
+    pub fn pareto_calibration(
+        &mut self,
+        pareto_calibration: bool,
+    ) -> Result<&mut Self, InputError> {
+        self.pareto_calibration = pareto_calibration;
+        Ok(self)
+    }
+    /// Sets whether the fixed size designs [`crate::unequal::sampford`],
+    /// [`crate::unequal::pareto`] and [`crate::unequal::brewer`] may return an empty sample, when
+    /// the probabilities sum to zero (default `true`).
+    /// If `false`, these designs return [`SamplingError::EmptySample`] instead, e.g. to catch a
+    /// stratum where all probabilities are accidentally zero.
+    #[inline]
+    pub fn allow_empty_sample(
+        &mut self,
+        allow_empty_sample: bool,
+    ) -> Result<&mut Self, InputError> {
+        self.allow_empty_sample = allow_empty_sample;
+        Ok(self)
+    }
     #[inline]
     pub fn auxiliaries(&mut self, auxiliaries: &'a Matrix<'a>) -> Result<&mut Self, InputError> {
         InputError::check_sizes(auxiliaries.nrow(), self.probabilities.len())?;
@@ -86,6 +258,22 @@ impl<'a> SampleOptions<'a> {
         Ok(self)
     }
     #[inline]
+    pub(crate) fn check_empty_sample(&self, sample_size: usize) -> Result<(), SamplingError> {
+        if sample_size == 0 && !self.allow_empty_sample {
+            return Err(SamplingError::EmptySample);
+        }
+
+        Ok(())
+    }
+    /// Reports `event` to the progress hook, if set.
+    /// Lets simulations outside of the crate report their replicates.
+    #[inline]
+    pub fn report(&self, event: ProgressEvent) {
+        if let Some(progress) = self.progress {
+            progress(event);
+        }
+    }
+    #[inline]
     pub fn sample<R>(&self, rng: &mut R, sampler: Sampler<R>) -> Result<Vec<usize>, SamplingError>
     where
         R: Rng + ?Sized,
@@ -0,0 +1,357 @@
+// Copyright (C) 2024 Wilmer Prentius, Anton Grafström.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU Affero General Public License as published by the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Spatial stratification, and spatially balanced designs on the sphere
+//!
+//! For designs that are both spatially balanced and balanced on auxiliary totals, see
+//! [`crate::cube_method::local_cube`].
+
+use crate::pivotal_method::lpm_2;
+use crate::ProgressEvent;
+pub use crate::{SampleOptions, SamplingError};
+use envisim_utils::utils::usize_to_f64;
+use envisim_utils::{InputError, Matrix, Probabilities};
+use rand::Rng;
+use std::num::NonZeroU32;
+
+/// Partitions the population into `n_strata` spatially compact strata of approximately equal
+/// total probability, and returns the stratum (`0..n_strata`) of each unit.
+/// The space is split recursively, like a k-d tree: the units of a cell are sorted along the
+/// dimension with the largest spread, and split where the cumulative probability best matches
+/// the number of strata allocated to each side.
+/// As units are not divided, the probability mass of a stratum deviates from the mean by at most
+/// about the largest probability of the population.
+/// Stratifying with `n_strata` equal to the sample size, and selecting one unit per stratum,
+/// gives a spatially balanced sample.
+/// Returns an error if the `coordinates` do not have one row per unit, if any coordinate is
+/// `NaN`, or if `n_strata` is not in `[1, N]`.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::spatial::*;
+/// use envisim_utils::Matrix;
+///
+/// let m = Matrix::from_vec(vec![0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9], 10);
+/// let strata = partition_strata(&m, &[0.2; 10], 2)?;
+///
+/// assert_eq!(strata, vec![0, 0, 0, 0, 0, 1, 1, 1, 1, 1]);
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn partition_strata(
+    coordinates: &Matrix,
+    probabilities: &[f64],
+    n_strata: usize,
+) -> Result<Vec<usize>, SamplingError> {
+    let (population_size, dim) = coordinates.dim();
+    InputError::check_sizes(population_size, probabilities.len())
+        .and(InputError::check_valid_usize(dim, 0))
+        .and(InputError::check_range_usize(n_strata, 1, population_size))
+        .and(Probabilities::check(probabilities))?;
+    coordinates
+        .data()
+        .iter()
+        .try_for_each(|&x| InputError::check_nan(x))?;
+
+    let mut strata = vec![0usize; population_size];
+    let mut units: Vec<usize> = (0..population_size).collect();
+    split_cell(
+        coordinates,
+        probabilities,
+        &mut units,
+        0,
+        n_strata,
+        &mut strata,
+    );
+
+    Ok(strata)
+}
+
+/// Converts latitudes and longitudes, in degrees, to 3D cartesian coordinates on the unit sphere.
+/// Row `i` of the returned matrix holds the coordinates of unit `i`.
+/// The euclidean distance between two units on the unit sphere is increasing in their
+/// great-circle distance, hence the coordinates can be used as [`SampleOptions::auxiliaries`],
+/// or to build a [`envisim_utils::kd_tree::TreeBuilder`], to find neighbours by great-circle
+/// proximity.
+/// Returns an error if `lat` and `lon` have different lengths, if any latitude is not in
+/// `[-90, 90]`, or if any longitude is not in `[-180, 180]`.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::spatial::*;
+///
+/// let m = spherical_coordinates(&[0.0, 90.0], &[90.0, 0.0])?;
+///
+/// assert!((m[(0, 1)] - 1.0).abs() < 1e-12);
+/// assert!((m[(1, 2)] - 1.0).abs() < 1e-12);
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn spherical_coordinates<'a>(lat: &[f64], lon: &[f64]) -> Result<Matrix<'a>, SamplingError> {
+    InputError::check_lengths(lat, lon)?;
+    lat.iter().try_for_each(|&x| {
+        InputError::check_nan(x).and(InputError::check_range_f64(x, -90.0, 90.0))
+    })?;
+    lon.iter().try_for_each(|&x| {
+        InputError::check_nan(x).and(InputError::check_range_f64(x, -180.0, 180.0))
+    })?;
+
+    let (cos_lat, sin_lat): (Vec<f64>, Vec<f64>) = lat
+        .iter()
+        .map(|&x| (x.to_radians().cos(), x.to_radians().sin()))
+        .unzip();
+    let mut data = Vec::<f64>::with_capacity(3 * lat.len());
+    data.extend(
+        cos_lat
+            .iter()
+            .zip(lon.iter())
+            .map(|(&c, &y)| c * y.to_radians().cos()),
+    );
+    data.extend(
+        cos_lat
+            .iter()
+            .zip(lon.iter())
+            .map(|(&c, &y)| c * y.to_radians().sin()),
+    );
+    data.extend_from_slice(&sin_lat);
+
+    Ok(Matrix::from_vec(data, lat.len()))
+}
+
+/// Draw a spatially balanced sample of units on a sphere, using the local pivotal method 2.
+/// The latitudes and longitudes, in degrees, are converted to 3D cartesian coordinates on the
+/// unit sphere by [`spherical_coordinates`], such that the sample is spread by great-circle
+/// proximity.
+/// Thus, unlike when spreading on the latitudes and longitudes directly, units close to the
+/// poles, or on both sides of the antimeridian, are treated as neighbours.
+/// The tree is built using the bucket size and split method of `options`, and any
+/// auxiliaries of `options` are ignored.
+/// Returns an error if `lat` and `lon` do not match the population, or if any latitude or
+/// longitude is out of range.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::spatial::*;
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let p = [0.5; 6];
+/// let lat = [89.0, 89.0, 0.0, 0.0, -45.0, -45.0];
+/// let lon = [0.0, 180.0, -179.0, 179.0, 10.0, 20.0];
+/// let s = lpm_spherical(&mut rng, &SampleOptions::new(&p)?, &lat, &lon)?;
+///
+/// assert_eq!(s.len(), 3);
+/// # Ok::<(), SamplingError>(())
+/// ```
+///
+/// # References
+/// Grafström, A., Lundström, N. L., & Schelin, L. (2012).
+/// Spatially balanced sampling through the pivotal method.
+/// Biometrics, 68(2), 514-520.
+/// <https://doi.org/10.1111/j.1541-0420.2011.01699.x>
+pub fn lpm_spherical<R>(
+    rng: &mut R,
+    options: &SampleOptions,
+    lat: &[f64],
+    lon: &[f64],
+) -> Result<Vec<usize>, SamplingError>
+where
+    R: Rng + ?Sized,
+{
+    InputError::check_sizes(lat.len(), options.probabilities.len())?;
+    let coordinates = spherical_coordinates(lat, lon)?;
+    let spherical_options = SampleOptions {
+        auxiliaries: Some(&coordinates),
+        ..*options
+    };
+
+    lpm_2(rng, &spherical_options)
+}
+
+/// Draw a spatially balanced sample where no two units are closer than `min_dist`, by redrawing
+/// samples of the local pivotal method 2 until the constraint holds.
+/// The distances are euclidean distances between the rows of `coordinates`, which are also used
+/// to spread the sample, and any auxiliaries of `options` are ignored.
+/// Each sample counts as an iteration towards the `max_iterations` of the options, after which
+/// [`SamplingError::MaxIterations`] is returned.
+/// Reports a [`crate::ProgressEvent::Iteration`] for each iteration, if a progress hook is set.
+///
+/// **Note:** the constraint distorts the inclusion probabilities, which generally do _not_ equal
+/// the probabilities of `options`, as units close to many other units are less likely to be
+/// selected.
+/// Hence, the realized inclusion probabilities are approximated by the relative frequencies of
+/// the units in `pi_reps` additional samples, drawn by the same rng, and returned alongside the
+/// sample for use in estimation.
+/// Units never observed in the additional samples are given probability `0.0`, which signals
+/// that `pi_reps` is too small for the approximation.
+///
+/// Returns an error if `min_dist` is non-positive, or if `coordinates` does not match the
+/// population.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::spatial::*;
+/// use envisim_utils::Matrix;
+/// use rand::{rngs::SmallRng, SeedableRng};
+/// use std::num::NonZeroU32;
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let p = [0.5; 8];
+/// let m = Matrix::new(&[
+///     0.0, 0.1, 0.2, 0.3, 0.0, 0.1, 0.2, 0.3, //
+///     0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0, //
+/// ], 8);
+/// let pi_reps = NonZeroU32::new(100).unwrap();
+/// let (s, pi) = min_distance_sample(&mut rng, &SampleOptions::new(&p)?, &m, 0.15, pi_reps)?;
+///
+/// assert_eq!(s.len(), 4);
+/// assert_eq!(pi.len(), 8);
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn min_distance_sample<R>(
+    rng: &mut R,
+    options: &SampleOptions,
+    coordinates: &Matrix,
+    min_dist: f64,
+    pi_reps: NonZeroU32,
+) -> Result<(Vec<usize>, Vec<f64>), SamplingError>
+where
+    R: Rng + ?Sized,
+{
+    InputError::check_nan(min_dist).and(InputError::check_positive(min_dist))?;
+    InputError::check_sizes(coordinates.nrow(), options.probabilities.len())?;
+
+    let spread_options = SampleOptions {
+        auxiliaries: Some(coordinates),
+        ..*options
+    };
+    let sample = min_distance_draw(rng, &spread_options, min_dist)?;
+
+    let mut counts = vec![0u32; options.probabilities.len()];
+    for _ in 0..pi_reps.get() {
+        min_distance_draw(rng, &spread_options, min_dist)?
+            .iter()
+            .for_each(|&id| counts[id] += 1);
+    }
+
+    let reps = f64::from(pi_reps.get());
+    let probabilities = counts.iter().map(|&c| f64::from(c) / reps).collect();
+
+    Ok((sample, probabilities))
+}
+
+// Redraws samples of lpm_2, spread by the auxiliaries, until no two units are closer than min_dist
+fn min_distance_draw<R>(
+    rng: &mut R,
+    options: &SampleOptions,
+    min_dist: f64,
+) -> Result<Vec<usize>, SamplingError>
+where
+    R: Rng + ?Sized,
+{
+    let coordinates = options.auxiliaries.unwrap();
+    let min_dist2 = min_dist.powi(2);
+
+    for iteration in 0..options.max_iterations.get() {
+        options.report(ProgressEvent::Iteration {
+            iteration,
+            max_iterations: options.max_iterations,
+        });
+
+        let sample = lpm_2(rng, options)?;
+        let feasible = sample.iter().enumerate().all(|(k, &i)| {
+            sample[(k + 1)..].iter().all(|&j| {
+                coordinates
+                    .row_iter(i)
+                    .zip(coordinates.row_iter(j))
+                    .fold(0.0, |acc, (&a, &b)| acc + (a - b).powi(2))
+                    >= min_dist2
+            })
+        });
+
+        if feasible {
+            return Ok(sample);
+        }
+    }
+
+    Err(SamplingError::MaxIterations(options.max_iterations))
+}
+
+// Assigns the units to the strata first_stratum..(first_stratum + n_strata)
+fn split_cell(
+    coordinates: &Matrix,
+    probabilities: &[f64],
+    units: &mut [usize],
+    first_stratum: usize,
+    n_strata: usize,
+    strata: &mut [usize],
+) {
+    if n_strata == 1 {
+        units.iter().for_each(|&id| strata[id] = first_stratum);
+        return;
+    }
+
+    // The dimension with the largest spread
+    let dim = (0..coordinates.ncol())
+        .map(|k| {
+            let (lo, hi) = units
+                .iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |acc, &id| {
+                    (
+                        acc.0.min(coordinates[(id, k)]),
+                        acc.1.max(coordinates[(id, k)]),
+                    )
+                });
+            (k, hi - lo)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap()
+        .0;
+    units.sort_unstable_by(|&a, &b| {
+        coordinates[(a, dim)]
+            .total_cmp(&coordinates[(b, dim)])
+            .then(a.cmp(&b))
+    });
+
+    let left_strata = n_strata / 2;
+    let total: f64 = units.iter().map(|&id| probabilities[id]).sum();
+    let target = total * usize_to_f64(left_strata) / usize_to_f64(n_strata);
+
+    // The number of units of the left cell, such that its probability is closest to the target,
+    // while leaving at least one unit per stratum on each side
+    let mut split = 0;
+    let mut psum = 0.0;
+    while split < units.len() && psum + probabilities[units[split]] <= target {
+        psum += probabilities[units[split]];
+        split += 1;
+    }
+    if split < units.len() && (psum + probabilities[units[split]] - target) < (target - psum) {
+        split += 1;
+    }
+    let split = split.clamp(left_strata, units.len() - (n_strata - left_strata));
+
+    let (left, right) = units.split_at_mut(split);
+    split_cell(
+        coordinates,
+        probabilities,
+        left,
+        first_stratum,
+        left_strata,
+        strata,
+    );
+    split_cell(
+        coordinates,
+        probabilities,
+        right,
+        first_stratum + left_strata,
+        n_strata - left_strata,
+        strata,
+    );
+}
@@ -0,0 +1,218 @@
+// Copyright (C) 2024 Wilmer Prentius, Anton Grafström.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU Affero General Public License as published by the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Splitting method designs
+//!
+//! In the splitting method, the vector of inclusion probabilities is, at each step, split into two
+//! vectors `a` and `b`, such that `lambda * a + (1 - lambda) * b` equals the current vector.
+//! The vector `a` is chosen with probability `lambda`, otherwise `b` is chosen.
+//! Since the expected vector after each step is the current vector, the inclusion probabilities
+//! are preserved.
+//! The process continues until all units are decided, i.e. have probability `0.0` or `1.0`.
+//!
+//! # References
+//! Deville, J. C., & Tillé, Y. (1998).
+//! Unequal probability sampling without replacement through a splitting method.
+//! Biometrika, 85(1), 89-101.
+//! <https://doi.org/10.1093/biomet/85.1.89>
+
+use crate::utils::Container;
+pub use crate::{SampleOptions, SamplingError};
+use envisim_utils::utils::sum;
+use envisim_utils::{InputError, Probabilities};
+use rand::Rng;
+
+/// The tolerance of the check that a split preserves the probabilities
+const SPLIT_EPS: f64 = 1e-9;
+
+/// A split of the current inclusion probabilities, as returned by a splitter.
+pub struct ProbabilitySplit {
+    /// The probability of choosing the vector `a`
+    pub lambda: f64,
+    /// The units affected by the split, as `(unit, a, b)`, where `a` and `b` are the new
+    /// probabilities of the unit in the respective vector.
+    /// Units not in the list keep their current probability in both vectors.
+    pub changes: Vec<(usize, f64, f64)>,
+}
+
+/// Draw a sample using the splitting method, where `splitter` decides how the current inclusion
+/// probabilities are split at each step.
+///
+/// The splitter is called with the current probabilities of all units, and the list of undecided
+/// units.
+/// For each unit `(id, a, b)` in the returned split, `lambda * a + (1 - lambda) * b` must equal
+/// the current probability of unit `id` (within `1e-9`), and `a` and `b` must be in
+/// `[0.0, 1.0]`, otherwise [`InputError::SplitNotPreserving`] or
+/// [`InputError::InvalidRangeF64`] is returned.
+/// Each split must decide at least one unit in both vectors, otherwise
+/// [`InputError::SplitUndecided`] is returned.
+/// If only one undecided unit remains, it is decided randomly according to its probability.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::splitting::*;
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let p = [0.2, 0.25, 0.35, 0.4, 0.5, 0.5, 0.55, 0.65, 0.7, 0.9];
+/// let options = SampleOptions::new(&p)?;
+/// let s = splitting(&mut rng, &options, minimum_support_splitter)?;
+///
+/// assert_eq!(s.len(), 5);
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn splitting<R, F>(
+    rng: &mut R,
+    options: &SampleOptions,
+    mut splitter: F,
+) -> Result<Vec<usize>, SamplingError>
+where
+    R: Rng + ?Sized,
+    F: FnMut(&[f64], &[usize]) -> ProbabilitySplit,
+{
+    Probabilities::check(options.probabilities).and(Probabilities::check_eps(options.eps))?;
+    let mut container = Container::new(rng, options)?;
+
+    while container.indices().len() > 1 {
+        let split = splitter(container.probabilities().data(), container.indices().list());
+        InputError::check_nan(split.lambda).and(InputError::check_range_f64(
+            split.lambda,
+            0.0,
+            1.0,
+        ))?;
+
+        // Both vectors must decide at least one undecided unit, regardless of which one is drawn
+        let eps = container.probabilities().eps;
+        let decides = |p: f64| p <= eps || 1.0 - eps <= p;
+        let (decides_a, decides_b) = split
+            .changes
+            .iter()
+            .filter(|&&(id, _, _)| container.indices().contains(id))
+            .fold((false, false), |(da, db), &(_, a, b)| {
+                (da || decides(a), db || decides(b))
+            });
+        if !(decides_a && decides_b) {
+            return Err(InputError::SplitUndecided.into());
+        }
+
+        let choose_a = container.rng().gen::<f64>() < split.lambda;
+
+        for &(id, a, b) in split.changes.iter() {
+            let current = container.probabilities()[id];
+            if (split.lambda * a + (1.0 - split.lambda) * b - current).abs() >= SPLIT_EPS {
+                return Err(InputError::SplitNotPreserving(id, current).into());
+            }
+            let p = if choose_a { a } else { b };
+            InputError::check_nan(p).and(InputError::check_range_f64(p, 0.0, 1.0))?;
+
+            if container.indices().contains(id) {
+                container.probabilities_mut()[id] = p;
+                container.decide_unit(id)?;
+            }
+        }
+    }
+
+    if let Some(id) = container.update_last_unit() {
+        container.decide_unit(id)?;
+    }
+
+    Ok(container.sample_mut().sort().to_vec())
+}
+
+/// Draw a sample using the minimum support design, i.e. the splitting method with the
+/// [`minimum_support_splitter`].
+/// The design has a support of at most as many samples as there are units.
+/// Probabilities must sum to an integer.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::splitting::*;
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let p = [0.2, 0.25, 0.35, 0.4, 0.5, 0.5, 0.55, 0.65, 0.7, 0.9];
+/// let s = SampleOptions::new(&p)?.sample(&mut rng, minimum_support)?;
+///
+/// assert_eq!(s.len(), 5);
+/// # Ok::<(), SamplingError>(())
+/// ```
+///
+/// # References
+/// Deville, J. C., & Tillé, Y. (1998).
+/// Unequal probability sampling without replacement through a splitting method.
+/// Biometrika, 85(1), 89-101.
+/// <https://doi.org/10.1093/biomet/85.1.89>
+#[inline]
+pub fn minimum_support<R>(rng: &mut R, options: &SampleOptions) -> Result<Vec<usize>, SamplingError>
+where
+    R: Rng + ?Sized,
+{
+    InputError::check_integer_approx(sum(options.probabilities), options.eps)?;
+    splitting(rng, options, minimum_support_splitter)
+}
+
+/// A splitter reproducing the pivotal method.
+/// The first two undecided units compete: if their probabilities sum to less than `1.0`, one of
+/// them is given probability `0.0`, otherwise one of them is given probability `1.0`.
+/// The other unit receives the remaining probability.
+pub fn pivotal_splitter(probabilities: &[f64], units: &[usize]) -> ProbabilitySplit {
+    let (i, j) = (units[0], units[1]);
+    let (pi, pj) = (probabilities[i], probabilities[j]);
+    let psum = pi + pj;
+
+    if psum < 1.0 {
+        ProbabilitySplit {
+            lambda: pi / psum,
+            changes: vec![(i, psum, 0.0), (j, 0.0, psum)],
+        }
+    } else {
+        ProbabilitySplit {
+            lambda: (1.0 - pj) / (2.0 - psum),
+            changes: vec![(i, 1.0, psum - 1.0), (j, psum - 1.0, 1.0)],
+        }
+    }
+}
+
+/// A splitter reproducing the minimum support design.
+/// With `n` being the (rounded) sum of the probabilities of the undecided units, the vector `a`
+/// selects the `n` undecided units with largest probabilities, and `lambda` is chosen as large as
+/// possible, such that at least one unit is decided in `b`.
+/// Thus, the sample is selected in at most as many steps as there are units.
+pub fn minimum_support_splitter(probabilities: &[f64], units: &[usize]) -> ProbabilitySplit {
+    let mut order = units.to_vec();
+    order.sort_unstable_by(|&a, &b| probabilities[b].partial_cmp(&probabilities[a]).unwrap());
+    let n = (order
+        .iter()
+        .fold(0.0, |acc, &id| acc + probabilities[id])
+        .round() as usize)
+        .min(order.len());
+
+    let min_selected = order[..n]
+        .iter()
+        .fold(1.0f64, |acc, &id| acc.min(probabilities[id]));
+    let max_unselected = order[n..]
+        .iter()
+        .fold(0.0f64, |acc, &id| acc.max(probabilities[id]));
+    let lambda = min_selected.min(1.0 - max_unselected);
+
+    let changes = order
+        .iter()
+        .enumerate()
+        .map(|(k, &id)| {
+            let v = if k < n { 1.0 } else { 0.0 };
+            let b = ((probabilities[id] - lambda * v) / (1.0 - lambda)).clamp(0.0, 1.0);
+            (id, v, b)
+        })
+        .collect();
+
+    ProbabilitySplit { lambda, changes }
+}
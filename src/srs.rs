@@ -82,3 +82,35 @@ where
     sample.sort_unstable();
     Ok(sample)
 }
+
+/// Select one member from a (household) roster of size `roster_size`, with equal probability.
+/// Returns the (zero based) position of the selected member in the roster.
+/// Serves the purpose of a Kish selection grid, but gives exactly equal probabilities for any
+/// roster size, as opposed to the printed grids which are approximate for large rosters.
+/// Returns an error if `roster_size` is zero.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::srs::*;
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let member = kish_grid(&mut rng, 4)?;
+///
+/// assert!(member < 4);
+/// # Ok::<(), SamplingError>(())
+/// ```
+///
+/// # References
+/// Kish, L. (1949).
+/// A procedure for objective respondent selection within the household.
+/// Journal of the American Statistical Association, 44(247), 380-387.
+/// <https://doi.org/10.1080/01621459.1949.10483314>
+#[inline]
+pub fn kish_grid<R>(rng: &mut R, roster_size: usize) -> Result<usize, SamplingError>
+where
+    R: Rng + ?Sized,
+{
+    InputError::check_valid_usize(roster_size, 0)?;
+    Ok(rng.gen_range(0..roster_size))
+}
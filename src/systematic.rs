@@ -13,7 +13,7 @@
 //! Systematic sampling designs
 
 pub use crate::{SampleOptions, SamplingError};
-use envisim_utils::Probabilities;
+use envisim_utils::{InputError, Probabilities, SymMatrix};
 use rand::Rng;
 
 /// Draw a systematic sample, using the provided order
@@ -67,8 +67,84 @@ where
     from_order(rng.gen(), probabilities, &order)
 }
 
+/// Returns the second order inclusion probabilities of the systematic design [`sample`], for
+/// the `units`.
+/// Element `(a, b)` of the returned matrix is the probability that both `units[a]` and
+/// `units[b]` are included, and the diagonal holds the first order probabilities.
+/// Hence, if `units` is a sample, the matrix matches the order of the sample.
+///
+/// A unit `i` is included if the random start falls in the interval covered by `i`, modulo 1,
+/// and the joint probabilities are the lengths of the intersections of these intervals.
+///
+/// **Note:** the joint probabilities of many pairs are exactly zero, e.g. of adjacent units with
+/// probabilities summing to at most 1, as such units can never be selected together.
+/// Hence, the SYG and HT variance estimators, which divide by the joint probabilities, are
+/// undefined for the systematic design, and no unbiased variance estimator exists.
+/// Returns an error if `units` is empty or contains units outside of the population.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::systematic::*;
+///
+/// let p = [0.5, 0.5, 0.5, 0.5];
+/// let pi2 = second_order(&SampleOptions::new(&p)?, &[0, 1, 2, 3])?;
+///
+/// assert_eq!(pi2[(0, 1)], 0.0);
+/// assert_eq!(pi2[(0, 2)], 0.5);
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn second_order(options: &SampleOptions, units: &[usize]) -> Result<SymMatrix, SamplingError> {
+    let probabilities = options.probabilities;
+    let population_size = probabilities.len();
+    Probabilities::check(probabilities).and(InputError::check_empty(units))?;
+    units
+        .iter()
+        .try_for_each(|&id| InputError::check_range_usize(id, 0, population_size - 1))?;
+
+    // The parts of [0, 1) covered by unit id, modulo 1
+    let mut psum: f64 = 0.0;
+    let mut intervals = Vec::<[(f64, f64); 2]>::with_capacity(population_size);
+
+    for &p in probabilities.iter() {
+        let start = psum - psum.floor();
+        let end = start + p;
+        intervals.push(if end <= 1.0 {
+            [(start, end), (0.0, 0.0)]
+        } else {
+            [(start, 1.0), (0.0, end - 1.0)]
+        });
+        psum += p;
+    }
+
+    let overlap = |i: usize, j: usize| -> f64 {
+        let mut length = 0.0;
+        for a in intervals[i].iter() {
+            for b in intervals[j].iter() {
+                length += (a.1.min(b.1) - a.0.max(b.0)).max(0.0);
+            }
+        }
+        length
+    };
+
+    let mut result = SymMatrix::from_value(0.0, units.len());
+
+    for (a, &i) in units.iter().enumerate() {
+        result[(a, a)] = probabilities[i];
+
+        for (b, &j) in units.iter().enumerate().skip(a + 1) {
+            result[(a, b)] = if i == j {
+                probabilities[i]
+            } else {
+                overlap(i, j)
+            };
+        }
+    }
+
+    Ok(result)
+}
+
 #[inline]
-fn from_order(
+pub(crate) fn from_order(
     rv: f64,
     probabilities: &[f64],
     order: &[usize],
@@ -0,0 +1,66 @@
+// Copyright (C) 2024 Wilmer Prentius, Anton Grafström.
+//
+// This program is free software: you can redistribute it and/or modify it under the terms of the
+// GNU Affero General Public License as published by the Free Software Foundation, version 3.
+//
+// This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY; without
+// even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the GNU
+// Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License along with this
+// program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Helpers for validating designs, e.g. custom designs or closures wrapping the designs of this
+//! crate
+
+use crate::{SampleOptions, SamplingError};
+use envisim_utils::InputError;
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+use std::num::NonZeroU32;
+
+/// Draws `reps` samples using `design`, with a [`SmallRng`] seeded by `seed`, and asserts that
+/// every sample has the fixed size, i.e. the sum of the inclusion probabilities rounded to the
+/// nearest integer.
+/// Returns an error if the probabilities do not sum to an integer, within the `eps` of the
+/// options, or if the design returns an error.
+///
+/// # Panics
+/// Panics on the first sample that does not have the fixed size, reporting the replicate and
+/// the observed size.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::test_support::assert_fixed_size;
+/// use envisim_samplr::{unequal::brewer, SampleOptions};
+/// use std::num::NonZeroU32;
+///
+/// let p = [0.2, 0.25, 0.35, 0.4, 0.5, 0.5, 0.55, 0.65, 0.7, 0.9];
+/// let reps = NonZeroU32::new(100).unwrap();
+/// assert_fixed_size(brewer, &SampleOptions::new(&p)?, reps, 4242)?;
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+pub fn assert_fixed_size<F>(
+    design: F,
+    options: &SampleOptions,
+    reps: NonZeroU32,
+    seed: u64,
+) -> Result<(), SamplingError>
+where
+    F: Fn(&mut SmallRng, &SampleOptions) -> Result<Vec<usize>, SamplingError>,
+{
+    let expected_size = options.expected_size();
+    InputError::check_integer_approx(expected_size, options.eps)?;
+    let sample_size = expected_size.round() as usize;
+    let mut rng = SmallRng::seed_from_u64(seed);
+
+    for rep in 0..reps.get() {
+        let size = design(&mut rng, options)?.len();
+        assert!(
+            size == sample_size,
+            "replicate {rep} has sample size {size}, but the fixed size is {sample_size}"
+        );
+    }
+
+    Ok(())
+}
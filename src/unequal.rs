@@ -13,34 +13,54 @@
 //! Unequal probability sampling designs
 
 use crate::poisson;
+use crate::rng::derive_seed;
+use crate::utils::fill_uniform;
+use crate::ProgressEvent;
 pub use crate::{SampleOptions, SamplingError};
 use envisim_utils::utils::{sum, usize_to_f64};
-use envisim_utils::{Indices, InputError, Probabilities};
-use rand::Rng;
+use envisim_utils::{
+    weighted_symmetric_polynomials, Indices, InputError, Probabilities, SymMatrix,
+};
+use rand::{Rng, SeedableRng};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::num::NonZeroUsize;
 
 // Assumes probabilites sum to 1.0
+// Units with zero probability are never drawn
 #[inline]
 fn draw<R>(rng: &mut R, probabilities: &[f64]) -> usize
 where
     R: Rng + ?Sized,
 {
-    let population_size = probabilities.len();
     let rv = rng.gen::<f64>();
     let mut psum: f64 = 0.0;
+    let mut last_positive: usize = probabilities.len() - 1;
 
     for (i, &p) in probabilities.iter().enumerate() {
+        if p <= 0.0 {
+            continue;
+        }
+
         psum += p;
+        last_positive = i;
 
-        if rv <= psum {
+        if rv < psum {
             return i;
         }
     }
 
-    population_size - 1
+    last_positive
 }
 
 /// Draw a with replacment sample according to draw probabilities
 /// Probabilities must sum to 1.0.
+/// The sample is sorted by construction, regardless of [`SampleOptions::sorted_output`].
+///
+/// As the sample is drawn with replacement, `n` may be larger than the population size, in which
+/// case units are selected multiple times.
+/// The units are selected in a single pass over the population and the sorted random numbers, so
+/// the method remains efficient for large `n`.
 ///
 /// # Examples
 /// ```
@@ -64,19 +84,113 @@ pub fn with_replacement<R>(
 where
     R: Rng + ?Sized,
 {
-    let probabilities = options.probabilities;
+    Probabilities::check(options.probabilities)?;
+    InputError::check_integer_approx_equal(sum(options.probabilities), 1.0, options.eps)?;
+
+    Ok(with_replacement_internal(rng, options.probabilities, n))
+}
+
+/// Draw a with replacement sample according to draw probabilities proportional to `weights`.
+/// The weights are normalized internally, hence they may be any relative selection weights,
+/// e.g. sizes, and need not sum to 1.0.
+/// Otherwise equivalent to [`with_replacement`].
+/// Returns an error if any weight is negative or not finite, or if the weights sum to zero.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::unequal::*;
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let w = [2.0, 3.0, 5.0];
+/// let s = with_replacement_weights(&mut rng, &w, 5)?;
+///
+/// assert_eq!(s.len(), 5);
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn with_replacement_weights<R>(
+    rng: &mut R,
+    weights: &[f64],
+    n: usize,
+) -> Result<Vec<usize>, SamplingError>
+where
+    R: Rng + ?Sized,
+{
+    InputError::check_empty(weights)?;
+    weights.iter().try_for_each(|&w| {
+        InputError::check_nan(w).and(InputError::check_range_f64(w, 0.0, f64::MAX))
+    })?;
+
+    let wsum = sum(weights);
+    InputError::check_positive(wsum).and(InputError::check_range_f64(wsum, 0.0, f64::MAX))?;
 
+    let probabilities: Vec<f64> = weights.iter().map(|&w| w / wsum).collect();
+    Ok(with_replacement_internal(rng, &probabilities, n))
+}
+
+/// Draw a with replacement sample according to draw probabilities, where the random numbers
+/// are drawn in antithetic pairs, `u` and `1 - u`.
+/// Probabilities must sum to 1.0.
+/// If `n` is odd, the last random number is drawn without a pair.
+/// Otherwise equivalent to [`with_replacement`].
+///
+/// The pairs are negatively correlated, e.g. a unit with a small cumulative probability paired
+/// with a unit with a large cumulative probability, which reduces the variance of estimators
+/// that are monotone in the random numbers, such as the mean of a variable sorted together with
+/// the population.
+///
+/// **Note:** the antithetic pairs induce dependence between the draws, hence the sample is _not_
+/// a with replacement sample of independent draws, and the usual variance estimators do not
+/// apply.
+/// The design is intended for variance reduction in Monte Carlo studies, not for estimation from
+/// a single sample.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::unequal::*;
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let p = [0.1; 10];
+/// let options = SampleOptions::new(&p)?;
+/// let s = with_replacement_antithetic(&mut rng, &options, 6)?;
+///
+/// assert_eq!(s.len(), 6);
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn with_replacement_antithetic<R>(
+    rng: &mut R,
+    options: &SampleOptions,
+    n: usize,
+) -> Result<Vec<usize>, SamplingError>
+where
+    R: Rng + ?Sized,
+{
     Probabilities::check(options.probabilities)?;
     InputError::check_integer_approx_equal(sum(options.probabilities), 1.0, options.eps)?;
 
-    if n == 0 {
-        return Ok(vec![]);
-    }
+    let mut rvs = Vec::<f64>::new();
+    fill_uniform(rng, &mut rvs, n.div_ceil(2));
+    rvs.extend_from_within(..(n / 2));
+    rvs[n.div_ceil(2)..].iter_mut().for_each(|u| *u = 1.0 - *u);
 
-    let mut rvs = Vec::<f64>::with_capacity(n);
+    Ok(with_replacement_select(options.probabilities, rvs, n))
+}
 
-    for _ in 0..n {
-        rvs.push(rng.gen::<f64>());
+fn with_replacement_internal<R>(rng: &mut R, probabilities: &[f64], n: usize) -> Vec<usize>
+where
+    R: Rng + ?Sized,
+{
+    let mut rvs = Vec::<f64>::new();
+    fill_uniform(rng, &mut rvs, n);
+
+    with_replacement_select(probabilities, rvs, n)
+}
+
+// Selects the units in whose interval [psum, psum+p) the random values fall
+fn with_replacement_select(probabilities: &[f64], mut rvs: Vec<f64>, n: usize) -> Vec<usize> {
+    if n == 0 {
+        return vec![];
     }
 
     rvs.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
@@ -110,11 +224,85 @@ where
         }
     }
 
+    // If the probabilities sum to slightly less than 1.0, the largest random values may not have
+    // been assigned
+    if sample.len() < n {
+        let last = probabilities.iter().rposition(|&p| p > 0.0).unwrap_or(0);
+        sample.resize(n, last);
+    }
+
+    sample
+}
+
+/// Draw `n` distinct units, by repeated with replacement draws according to draw probabilities,
+/// until `n` distinct units have been drawn.
+/// Probabilities must sum to 1.0.
+/// Each draw counts as an iteration towards `max_iterations`.
+///
+/// **Note:** this is a convenience heuristic, and the inclusion probabilities are *not*
+/// proportional to the draw probabilities, nor known in closed form.
+/// Use a proper without replacement design, such as [`pareto`] or [`brewer`], if the inclusion
+/// probabilities are needed for estimation.
+///
+/// Returns an error if `n` is larger than the number of units with a positive probability.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::unequal::*;
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let p = [0.1, 0.2, 0.3, 0.4, 0.0];
+/// let options = SampleOptions::new(&p)?;
+/// let s = distinct_with_replacement(&mut rng, &options, 3)?;
+///
+/// assert_eq!(s.len(), 3);
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn distinct_with_replacement<R>(
+    rng: &mut R,
+    options: &SampleOptions,
+    n: usize,
+) -> Result<Vec<usize>, SamplingError>
+where
+    R: Rng + ?Sized,
+{
+    let probabilities = options.probabilities;
+
+    Probabilities::check(probabilities)?;
+    InputError::check_integer_approx_equal(sum(probabilities), 1.0, options.eps)?;
+    InputError::check_range_usize(n, 0, probabilities.iter().filter(|&&p| p > 0.0).count())?;
+
+    let mut selected = vec![false; probabilities.len()];
+    let mut sample = Vec::<usize>::with_capacity(n);
+
+    for _ in 0..options.max_iterations.get() {
+        if sample.len() == n {
+            break;
+        }
+
+        let id = draw(rng, probabilities);
+        if !selected[id] {
+            selected[id] = true;
+            sample.push(id);
+        }
+    }
+
+    if sample.len() < n {
+        return Err(SamplingError::MaxIterations(options.max_iterations));
+    }
+
+    if options.sorted_output {
+        sample.sort_unstable();
+    }
+
     Ok(sample)
 }
 
 /// Draw a sample using a sampford design.
 /// Probabilities must sum to an integer.
+/// Units with probability at most `eps` are never selected.
+/// Reports a [`ProgressEvent::Iteration`] for each rejection iteration, if a progress hook is set.
 ///
 /// # Examples
 /// ```
@@ -134,50 +322,282 @@ pub fn sampford<R>(rng: &mut R, options: &SampleOptions) -> Result<Vec<usize>, S
 where
     R: Rng + ?Sized,
 {
-    let probabilities = options.probabilities;
-    let eps = options.eps;
-
-    let psum = sum(probabilities);
-    Probabilities::check(probabilities)
-        .and(Probabilities::check_eps(eps))
-        .and(InputError::check_integer_approx(psum, eps))?;
-    let sample_size = psum.round() as usize;
+    let (sample_size, probabilities, norm_probs) = sampford_setup(options)?;
+    options.check_empty_sample(sample_size)?;
 
     if sample_size == 0 {
         return Ok(vec![]);
     } else if sample_size == 1 {
-        return Ok(vec![draw(rng, probabilities)]);
+        return Ok(vec![draw(rng, &norm_probs)]);
     }
 
-    let norm_probs: Vec<f64> = probabilities.iter().map(|&p| p / psum).collect();
+    for iteration in 0..options.max_iterations.get() {
+        options.report(ProgressEvent::Iteration {
+            iteration,
+            max_iterations: options.max_iterations,
+        });
 
-    for _ in 0..options.max_iterations.get() {
-        let mut sample = poisson::internal(rng, probabilities);
-
-        if sample.len() != sample_size - 1 {
-            continue;
+        if let Some(sample) =
+            sampford_attempt(rng, options, &probabilities, &norm_probs, sample_size)
+        {
+            return Ok(sample);
         }
+    }
 
-        let a_unit = draw(rng, &norm_probs);
+    Err(SamplingError::MaxIterations(options.max_iterations))
+}
 
-        // Since sample is ordered, we don't need to check units with
-        // higher id than a_unit
-        if sample
-            .iter()
-            .find(|&&id| id >= a_unit)
-            .is_some_and(|&id| id != a_unit)
+/// The result of [`sampford_resumable`].
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Resumable {
+    /// A sample was drawn.
+    Sample(Vec<usize>),
+    /// No sample was drawn within the iteration budget. Holds the total number of iterations
+    /// consumed, to be passed as `start_iteration` to a later call.
+    Pending(usize),
+}
+
+/// Draw a sample using a sampford design, in a way that can be checkpointed and resumed.
+/// Probabilities must sum to an integer.
+/// Units with probability at most `eps` are never selected.
+///
+/// Instead of a running rng, each rejection iteration `k` uses a fresh rng seeded by
+/// `R::seed_from_u64`, with a seed derived from `seed` and `k` as in [`crate::derive_rng`].
+/// Thus, the state of the design is fully described by `seed` and the number of iterations
+/// consumed, both of which are trivial to store.
+/// Each call runs at most `max_iterations` iterations, starting at iteration `start_iteration`,
+/// and returns [`Resumable::Pending`] if no sample was accepted.
+/// Resuming with the returned iteration count yields the same sample as an uninterrupted run.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::unequal::*;
+/// use rand::rngs::SmallRng;
+/// use std::num::NonZeroUsize;
+///
+/// let p = [0.2, 0.25, 0.35, 0.4, 0.5, 0.5, 0.55, 0.65, 0.7, 0.9];
+/// let mut options = SampleOptions::new(&p)?;
+/// options.max_iterations(NonZeroUsize::new(1).unwrap())?;
+/// let mut start = 0;
+///
+/// let s = loop {
+///     match sampford_resumable::<SmallRng>(4242, &options, start)? {
+///         Resumable::Sample(s) => break s,
+///         Resumable::Pending(iterations) => start = iterations, // checkpoint
+///         _ => unreachable!(),
+///     }
+/// };
+///
+/// assert_eq!(s.len(), 5);
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn sampford_resumable<R>(
+    seed: u64,
+    options: &SampleOptions,
+    start_iteration: usize,
+) -> Result<Resumable, SamplingError>
+where
+    R: Rng + SeedableRng,
+{
+    let (sample_size, probabilities, norm_probs) = sampford_setup(options)?;
+    let rng_at = |iteration: usize| R::seed_from_u64(derive_seed(seed, iteration as u64));
+    options.check_empty_sample(sample_size)?;
+
+    if sample_size == 0 {
+        return Ok(Resumable::Sample(vec![]));
+    } else if sample_size == 1 {
+        return Ok(Resumable::Sample(vec![draw(
+            &mut rng_at(start_iteration),
+            &norm_probs,
+        )]));
+    }
+
+    let end_iteration = start_iteration.saturating_add(options.max_iterations.get());
+
+    for iteration in start_iteration..end_iteration {
+        options.report(ProgressEvent::Iteration {
+            iteration,
+            max_iterations: options.max_iterations,
+        });
+
+        let mut rng = rng_at(iteration);
+        if let Some(sample) =
+            sampford_attempt(&mut rng, options, &probabilities, &norm_probs, sample_size)
         {
-            sample.push(a_unit);
+            return Ok(Resumable::Sample(sample));
+        }
+    }
+
+    Ok(Resumable::Pending(end_iteration))
+}
+
+// Returns the sample size, the probabilities with units at most eps set to zero, and the
+// normalized probabilities
+#[inline]
+fn sampford_setup(options: &SampleOptions) -> Result<(usize, Vec<f64>, Vec<f64>), SamplingError> {
+    let eps = options.eps;
+    let psum = sum(options.probabilities);
+    Probabilities::check(options.probabilities)
+        .and(Probabilities::check_eps(eps))
+        .and(InputError::check_integer_approx(psum, eps))?;
+
+    let probabilities: Vec<f64> = options
+        .probabilities
+        .iter()
+        .map(|&p| if p <= eps { 0.0 } else { p })
+        .collect();
+    let norm_probs = probabilities.iter().map(|&p| p / psum).collect();
+
+    Ok((psum.round() as usize, probabilities, norm_probs))
+}
+
+#[inline]
+fn sampford_attempt<R>(
+    rng: &mut R,
+    options: &SampleOptions,
+    probabilities: &[f64],
+    norm_probs: &[f64],
+    sample_size: usize,
+) -> Option<Vec<usize>>
+where
+    R: Rng + ?Sized,
+{
+    let mut sample = poisson::internal(rng, probabilities);
+
+    if sample.len() != sample_size - 1 {
+        return None;
+    }
+
+    let a_unit = draw(rng, norm_probs);
+
+    // Since sample is ordered, we don't need to check units with
+    // higher id than a_unit
+    if sample
+        .iter()
+        .find(|&&id| id >= a_unit)
+        .is_some_and(|&id| id != a_unit)
+    {
+        sample.push(a_unit);
+
+        if options.sorted_output {
             sample.sort_unstable();
-            return Ok(sample);
         }
+
+        return Some(sample);
     }
 
-    Err(SamplingError::MaxIterations(options.max_iterations))
+    None
+}
+
+/// Returns the second order inclusion probabilities of the sampford design, for the `units`.
+/// Element `(a, b)` of the returned matrix is the probability that both `units[a]` and
+/// `units[b]` are included, and the diagonal holds the first order probabilities.
+/// Hence, if `units` is a sample, the matrix matches the order of the sample.
+/// The probabilities are exact, and computed in `O(N n)` time per pair of units.
+/// Returns an error if the probabilities do not sum to an integer, or if `units` is empty or
+/// contains units outside of the population.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::unequal::*;
+///
+/// // Equal probabilities, the design is simple random sampling
+/// let p = [0.5; 4];
+/// let m = sampford_second_order(&SampleOptions::new(&p)?, &[0, 1, 3])?;
+///
+/// assert!((m[(0, 1)] - 1.0 / 6.0).abs() < 1e-12);
+/// assert_eq!(m[(2, 2)], 0.5);
+/// # Ok::<(), SamplingError>(())
+/// ```
+///
+/// # References
+/// Sampford, M. R. (1967).
+/// On sampling without replacement with unequal probabilities of selection.
+/// Biometrika, 54(3-4), 499-513.
+/// <https://doi.org/10.1093/biomet/54.3-4.499>
+pub fn sampford_second_order(
+    options: &SampleOptions,
+    units: &[usize],
+) -> Result<SymMatrix, SamplingError> {
+    let eps = options.eps;
+    let (sample_size, probabilities, _) = sampford_setup(options)?;
+    let population_size = probabilities.len();
+    InputError::check_empty(units)?;
+    units
+        .iter()
+        .try_for_each(|&id| InputError::check_range_usize(id, 0, population_size - 1))?;
+
+    let is_certain = |id: usize| 1.0 - eps <= probabilities[id];
+    // The design of the non-certainty units has probability proportional to
+    // (n - sum_s p) prod_s p / (1 - p).
+    let random: Vec<usize> = (0..population_size)
+        .filter(|&id| probabilities[id] > 0.0 && !is_certain(id))
+        .collect();
+    let random_size = sample_size - (0..population_size).filter(|&id| is_certain(id)).count();
+
+    // The odds, scaled by their geometric mean to avoid overflow, as the probabilities are
+    // invariant to scaling
+    let mut odds = vec![0.0; population_size];
+    random
+        .iter()
+        .for_each(|&id| odds[id] = (probabilities[id] / (1.0 - probabilities[id])).ln());
+    let scale = random.iter().map(|&id| odds[id]).sum::<f64>() / usize_to_f64(random.len().max(1));
+    random
+        .iter()
+        .for_each(|&id| odds[id] = (odds[id] - scale).exp());
+
+    // The elementary symmetric polynomials e_k of the odds, and f_k, the sums over the subsets of
+    // size k of (sum_s p) prod_s odds, of the non-certainty units except the excluded units
+    let polynomials = |excluded: &[usize], degree: usize| -> (Vec<f64>, Vec<f64>) {
+        let (values, weights): (Vec<f64>, Vec<f64>) = random
+            .iter()
+            .filter(|id| !excluded.contains(id))
+            .map(|&id| (odds[id], probabilities[id]))
+            .unzip();
+        weighted_symmetric_polynomials(&values, &weights, degree)
+    };
+
+    let (e, f) = polynomials(&[], random_size);
+    let norm = usize_to_f64(random_size) * e[random_size] - f[random_size];
+    let mut second_order = SymMatrix::from_value(0.0, units.len());
+
+    for (a, &i) in units.iter().enumerate() {
+        second_order[(a, a)] = probabilities[i];
+
+        for (b, &j) in units.iter().enumerate().skip(a + 1) {
+            second_order[(a, b)] = if probabilities[i] == 0.0 || probabilities[j] == 0.0 {
+                0.0
+            } else if is_certain(i) {
+                probabilities[j]
+            } else if is_certain(j) || i == j {
+                probabilities[i]
+            } else if random_size < 2 {
+                0.0
+            } else {
+                let (e2, f2) = polynomials(&[i, j], random_size - 2);
+                odds[i]
+                    * odds[j]
+                    * ((usize_to_f64(random_size) - probabilities[i] - probabilities[j])
+                        * e2[random_size - 2]
+                        - f2[random_size - 2])
+                    / norm
+            };
+        }
+    }
+
+    Ok(second_order)
 }
 
 /// Draw a sample using a pareto design.
 /// Probabilities must sum to an integer.
+/// Units with probability at most `eps` are never selected.
+///
+/// The inclusion probabilities of the design equal the target probabilities only
+/// asymptotically.
+/// If [`SampleOptions::pareto_calibration`] is set, the shape parameters of the design are
+/// calibrated such that the exact inclusion probabilities equal the targets, see
+/// [`pareto_parameters`].
 ///
 /// # Examples
 /// ```
@@ -210,34 +630,338 @@ where
         .and(InputError::check_integer_approx(psum, eps))?;
 
     let sample_size = psum.round() as usize;
+    options.check_empty_sample(sample_size)?;
+
+    let calibrated: Vec<f64>;
+    let parameters = if options.pareto_calibration {
+        calibrated = pareto_calibrate(probabilities, sample_size, options)?;
+        &calibrated
+    } else {
+        probabilities
+    };
 
-    let q_values: Vec<f64> = probabilities
+    let q_values: Vec<f64> = parameters
         .iter()
-        .map(|&p| {
-            let u = rng.gen::<f64>();
+        .map(|&p| pareto_q(rng.gen::<f64>(), p, eps))
+        .collect();
+
+    let mut sample: Vec<usize> = (0..probabilities.len())
+        .filter(|&i| probabilities[i] > eps)
+        .collect();
+    // Ties are broken by index, so that the selection is deterministic given the q values
+    sample.sort_unstable_by(|&a, &b| q_values[a].total_cmp(&q_values[b]).then(a.cmp(&b)));
+    sample.truncate(sample_size);
+    Ok(sample)
+}
+
+// The ranking variable of a unit with probability p, given the uniform u
+#[inline]
+fn pareto_q(u: f64, p: f64, eps: f64) -> f64 {
+    if 1.0 - eps < u || p <= eps {
+        return f64::INFINITY;
+    }
+
+    let res = (u * (1.0 - p)) / (p * (1.0 - u));
 
-            if 1.0 - eps < u || p < eps {
-                return f64::INFINITY;
+    if res.is_nan() {
+        return f64::INFINITY;
+    }
+
+    res
+}
+
+/// The tolerance of the calibration of the pareto shape parameters
+const PARETO_CALIBRATION_EPS: f64 = 1e-10;
+
+// The exact inclusion probabilities of the pareto design with shape parameters `parameters`.
+// Unit k is included if fewer than n other units have a smaller ranking variable Q. On the log
+// scale, x = ln(Q_j) is logistic with location c_j = ln((1 - p_j) / p_j), hence
+// pi_k = int P(#{j != k: ln(Q_j) < x} < n) f_k(x) dx,
+// which is integrated by composite Gauss-Legendre quadrature on intervals of width 1/2.
+fn pareto_exact(parameters: &[f64], sample_size: usize, eps: f64) -> Vec<f64> {
+    const WIDTH: f64 = 0.5;
+    const TAIL: f64 = 36.0;
+    let nodes = [-(0.6f64.sqrt()), 0.0, 0.6f64.sqrt()];
+    let weights = [5.0 / 18.0, 8.0 / 18.0, 5.0 / 18.0];
+
+    let mut inclusion: Vec<f64> = parameters
+        .iter()
+        .map(|&p| if p <= eps { 0.0 } else { 1.0 })
+        .collect();
+    let random: Vec<usize> = (0..parameters.len())
+        .filter(|&id| eps < parameters[id] && parameters[id] < 1.0 - eps)
+        .collect();
+    let n_certain = parameters.iter().filter(|&&p| 1.0 - eps <= p).count();
+    let size = sample_size.saturating_sub(n_certain);
+
+    if size >= random.len() {
+        return inclusion;
+    } else if size == 0 {
+        random.iter().for_each(|&id| inclusion[id] = 0.0);
+        return inclusion;
+    }
+
+    let locations: Vec<f64> = parameters.iter().map(|&p| ((1.0 - p) / p).ln()).collect();
+    let logistic = |x: f64| 1.0 / (1.0 + (-x).exp());
+    let mut counts = vec![0.0; size];
+
+    for &k in random.iter() {
+        let lo = locations[k] - TAIL;
+        let intervals = (2.0 * TAIL / WIDTH) as usize;
+        let mut integral = 0.0;
+
+        for interval in 0..intervals {
+            for (node, weight) in nodes.iter().zip(weights.iter()) {
+                let x = lo + WIDTH * (usize_to_f64(interval) + 0.5 + 0.5 * node);
+                let fk = logistic(x - locations[k]);
+
+                // The distribution of the number of other units ranked before x, up to size - 1
+                counts.fill(0.0);
+                counts[0] = 1.0;
+                for &j in random.iter().filter(|&&j| j != k) {
+                    let f = logistic(x - locations[j]);
+                    for c in (1..size).rev() {
+                        counts[c] = counts[c] * (1.0 - f) + counts[c - 1] * f;
+                    }
+                    counts[0] *= 1.0 - f;
+                }
+
+                integral += weight * WIDTH * fk * (1.0 - fk) * counts.iter().sum::<f64>();
             }
+        }
 
-            let res = (u * (1.0 - p)) / (p * (1.0 - u));
+        inclusion[k] = integral;
+    }
+
+    inclusion
+}
 
-            if res.is_nan() {
-                return f64::INFINITY;
+// Calibrates the shape parameters, such that the exact inclusion probabilities equal the targets
+fn pareto_calibrate(
+    targets: &[f64],
+    sample_size: usize,
+    options: &SampleOptions,
+) -> Result<Vec<f64>, SamplingError> {
+    let eps = options.eps;
+    let logit = |p: f64| (p / (1.0 - p)).ln();
+    let mut parameters = targets.to_vec();
+
+    for _ in 0..options.max_iterations.get() {
+        let inclusion = pareto_exact(&parameters, sample_size, eps);
+        let mut max_difference: f64 = 0.0;
+
+        for (id, &target) in targets.iter().enumerate() {
+            if target <= eps || 1.0 - eps <= target {
+                continue;
             }
 
-            res
-        })
+            max_difference = max_difference.max((inclusion[id] - target).abs());
+            let pi = inclusion[id].clamp(f64::MIN_POSITIVE, 1.0 - f64::EPSILON);
+            let l = logit(parameters[id]) + logit(target) - logit(pi);
+            parameters[id] = (1.0 / (1.0 + (-l).exp())).clamp(2.0 * eps, 1.0 - 2.0 * eps);
+        }
+
+        if max_difference < PARETO_CALIBRATION_EPS {
+            pareto_normalize(&mut parameters, targets, sample_size, eps);
+            return Ok(parameters);
+        }
+    }
+
+    Err(SamplingError::MaxIterations(options.max_iterations))
+}
+
+// The design only depends on the odds of the parameters up to a common factor. Shifts the
+// parameters on the logit scale, such that they sum to the sample size.
+fn pareto_normalize(parameters: &mut [f64], targets: &[f64], sample_size: usize, eps: f64) {
+    let random: Vec<usize> = (0..targets.len())
+        .filter(|&id| eps < targets[id] && targets[id] < 1.0 - eps)
         .collect();
+    let n_certain = targets.iter().filter(|&&p| 1.0 - eps <= p).count();
+    let size = usize_to_f64(sample_size.saturating_sub(n_certain));
+    let logits: Vec<f64> = random
+        .iter()
+        .map(|&id| (parameters[id] / (1.0 - parameters[id])).ln())
+        .collect();
+    let logistic = |x: f64| 1.0 / (1.0 + (-x).exp());
+    let mut shift = 0.0;
 
-    let mut sample: Vec<usize> = (0..probabilities.len()).collect();
-    sample.sort_by(|&a, &b| q_values[a].partial_cmp(&q_values[b]).unwrap());
-    sample.truncate(sample_size);
-    Ok(sample)
+    for _ in 0..100 {
+        let (value, derivative) = logits.iter().fold((-size, 0.0), |(v, d), &l| {
+            let p = logistic(l + shift);
+            (v + p, d + p * (1.0 - p))
+        });
+
+        if value.abs() < 1e-14 || derivative <= 0.0 {
+            break;
+        }
+
+        shift -= value / derivative;
+    }
+
+    for (&id, &l) in random.iter().zip(logits.iter()) {
+        parameters[id] = logistic(l + shift);
+    }
+}
+
+/// Returns the shape parameters used by [`pareto`], i.e. the probabilities, or the calibrated
+/// parameters if [`SampleOptions::pareto_calibration`] is set.
+/// The calibration adjusts the parameters iteratively on the logit scale, until the exact
+/// inclusion probabilities of the design, computed by numerical integration as in Aires (1999),
+/// are within `1e-10` of the target probabilities.
+/// As the design only depends on the odds of the parameters up to a common factor, the calibrated
+/// parameters are scaled to sum to the sample size, and can be used as probabilities of a plain
+/// pareto design.
+/// Each iteration takes `O(N^2 n)` time, thus the calibration is intended for small
+/// populations, where the asymptotic approximation is poor.
+/// Returns [`SamplingError::MaxIterations`] if the calibration does not converge within
+/// `max_iterations` iterations.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::unequal::*;
+///
+/// let p = [0.2, 0.3, 0.5, 0.6, 0.9, 0.5];
+/// let mut options = SampleOptions::new(&p)?;
+/// options.pareto_calibration(true)?;
+/// let parameters = pareto_parameters(&options)?;
+/// let pi = pareto_inclusion_probabilities(&SampleOptions::new(&parameters)?, 3)?;
+///
+/// assert!((pi[0] - 0.2).abs() < 1e-9);
+/// # Ok::<(), SamplingError>(())
+/// ```
+///
+/// # References
+/// Aires, N. (1999).
+/// Algorithms to find exact inclusion probabilities for conditional Poisson sampling and Pareto
+/// πps sampling designs.
+/// Methodology and Computing in Applied Probability, 1(4), 457-469.
+/// <https://doi.org/10.1023/A:1010091628740>
+pub fn pareto_parameters(options: &SampleOptions) -> Result<Vec<f64>, SamplingError> {
+    let probabilities = options.probabilities;
+    let psum = sum(probabilities);
+    Probabilities::check(probabilities).and(InputError::check_integer_approx(psum, options.eps))?;
+
+    if options.pareto_calibration {
+        pareto_calibrate(probabilities, psum.round() as usize, options)
+    } else {
+        Ok(probabilities.to_vec())
+    }
+}
+
+/// Returns the exact inclusion probabilities of the pareto design of sample size `n`, using the
+/// probabilities of `options` as shape parameters, see [`pareto_parameters`].
+/// The probabilities need not sum to `n`.
+/// Returns an error if `n` is larger than the population size.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::unequal::*;
+///
+/// let p = [0.2, 0.3, 0.5, 0.6, 0.9, 0.5];
+/// let pi = pareto_inclusion_probabilities(&SampleOptions::new(&p)?, 3)?;
+///
+/// assert!((pi.iter().sum::<f64>() - 3.0).abs() < 1e-9);
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn pareto_inclusion_probabilities(
+    options: &SampleOptions,
+    n: usize,
+) -> Result<Vec<f64>, SamplingError> {
+    Probabilities::check(options.probabilities).and(InputError::check_range_usize(
+        n,
+        0,
+        options.probabilities.len(),
+    ))?;
+    Ok(pareto_exact(options.probabilities, n, options.eps))
+}
+
+/// Heap entry of [`pareto_from_iter`], ordered by ranking variable and then by index, such that
+/// the unit with the largest ranking variable is at the top of the heap.
+struct ParetoKey(f64, usize);
+
+impl PartialEq for ParetoKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl Eq for ParetoKey {}
+impl PartialOrd for ParetoKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for ParetoKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(self.1.cmp(&other.1))
+    }
+}
+
+/// Draw a sample using a pareto design, where the probabilities are supplied by an iterator
+/// rather than a slice.
+/// The iterator is traversed twice: once to find the sample size, and once to draw the sample.
+/// Only the `n` best ranked units are kept in memory, hence the probabilities may be generated
+/// lazily (e.g. read from disk) for very large populations.
+/// Given the same rng, the sample equals that of [`pareto`] on the collected probabilities.
+/// Probabilities must sum to an integer.
+/// Units with probability at most `eps` are never selected.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::unequal::*;
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let p = (0..1000).map(|i| if i % 2 == 0 { 0.004 } else { 0.006 });
+/// let s = pareto_from_iter(&mut rng, p, 1e-12)?;
+///
+/// assert_eq!(s.len(), 5);
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn pareto_from_iter<R, I>(
+    rng: &mut R,
+    probabilities: I,
+    eps: f64,
+) -> Result<Vec<usize>, SamplingError>
+where
+    R: Rng + ?Sized,
+    I: IntoIterator<Item = f64> + Clone,
+{
+    Probabilities::check_eps(eps)?;
+    let mut population_size: usize = 0;
+    let psum = probabilities.clone().into_iter().try_fold(0.0, |acc, p| {
+        InputError::check_nan(p).and(InputError::check_range_f64(p, 0.0, 1.0))?;
+        population_size += 1;
+        Ok::<f64, InputError>(acc + p)
+    })?;
+    if population_size == 0 {
+        return Err(InputError::IsEmpty.into());
+    }
+    InputError::check_integer_approx(psum, eps)?;
+
+    let sample_size = psum.round() as usize;
+    let mut heap = BinaryHeap::<ParetoKey>::with_capacity(sample_size + 1);
+
+    for (id, p) in probabilities.into_iter().enumerate() {
+        let key = ParetoKey(pareto_q(rng.gen::<f64>(), p, eps), id);
+
+        if p <= eps || sample_size == 0 {
+            continue;
+        }
+
+        if heap.len() < sample_size {
+            heap.push(key);
+        } else if heap.peek().is_some_and(|max| key < *max) {
+            heap.pop();
+            heap.push(key);
+        }
+    }
+
+    Ok(heap.into_sorted_vec().iter().map(|k| k.1).collect())
 }
 
 /// Draw a sample using a brewer design.
 /// Probabilities must sum to an integer.
+/// Units with probability at most `eps` are never selected.
 ///
 /// # Examples
 /// ```
@@ -253,6 +977,60 @@ where
 /// ```
 #[inline]
 pub fn brewer<R>(rng: &mut R, options: &SampleOptions) -> Result<Vec<usize>, SamplingError>
+where
+    R: Rng + ?Sized,
+{
+    brewer_internal(rng, options, None)
+}
+
+/// A step of a sequential design, see [`brewer_traced`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SelectionStep {
+    /// The index of the step, starting at 0
+    pub step: usize,
+    /// The unit selected at this step
+    pub unit: usize,
+    /// The conditional probability of selecting the unit, given the previous steps
+    pub probability: f64,
+}
+
+/// Draw a sample using a brewer design, and return the trace of the selection.
+/// The sample equals that of [`brewer`] given the same rng.
+/// The trace records each step of the selection, in order.
+/// Certainty units are selected first, with conditional probability 1.0.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::unequal::*;
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let p = [0.2, 0.25, 0.35, 0.4, 0.5, 0.5, 0.55, 0.65, 0.7, 0.9];
+/// let options = SampleOptions::new(&p)?;
+/// let (s, trace) = brewer_traced(&mut rng, &options)?;
+///
+/// assert_eq!(s.len(), 5);
+/// assert_eq!(trace.len(), 5);
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn brewer_traced<R>(
+    rng: &mut R,
+    options: &SampleOptions,
+) -> Result<(Vec<usize>, Vec<SelectionStep>), SamplingError>
+where
+    R: Rng + ?Sized,
+{
+    let mut trace = Vec::<SelectionStep>::new();
+    let sample = brewer_internal(rng, options, Some(&mut trace))?;
+    Ok((sample, trace))
+}
+
+#[inline]
+fn brewer_internal<R>(
+    rng: &mut R,
+    options: &SampleOptions,
+    mut trace: Option<&mut Vec<SelectionStep>>,
+) -> Result<Vec<usize>, SamplingError>
 where
     R: Rng + ?Sized,
 {
@@ -265,6 +1043,7 @@ where
         .and(InputError::check_integer_approx(psum, eps))?;
 
     let mut sample_size = psum.round() as usize;
+    options.check_empty_sample(sample_size)?;
     let mut n_d = psum;
     let mut indices = Indices::with_fill(probabilities.len());
     let mut sample = Vec::<usize>::with_capacity(sample_size);
@@ -274,6 +1053,13 @@ where
             indices.remove(id).unwrap();
         } else if 1.0 - eps <= p {
             indices.remove(id).unwrap();
+            if let Some(ref mut trace) = trace {
+                trace.push(SelectionStep {
+                    step: sample.len(),
+                    unit: id,
+                    probability: 1.0,
+                });
+            }
             sample.push(id);
             n_d -= 1.0;
             sample_size -= 1;
@@ -286,7 +1072,7 @@ where
         psum = 0.0;
         for &id in indices.list() {
             let p = probabilities[id];
-            q_probs[id] = p * (n_d - p) / (n_d - p * usize_to_f64(sample_size - i + 1));
+            q_probs[id] = p * (n_d - p) / (n_d - p * usize_to_f64(sample_size - i));
             psum += q_probs[id];
         }
 
@@ -296,11 +1082,209 @@ where
 
         let a_unit = draw(rng, &q_probs);
         indices.remove(a_unit).unwrap();
+        if let Some(ref mut trace) = trace {
+            trace.push(SelectionStep {
+                step: sample.len(),
+                unit: a_unit,
+                probability: q_probs[a_unit],
+            });
+        }
         sample.push(a_unit);
         q_probs[a_unit] = 0.0;
         n_d -= probabilities[a_unit];
     }
 
+    if options.sorted_output {
+        sample.sort_unstable();
+    }
+
+    Ok(sample)
+}
+
+/// Returns the units with probability at least `1.0 - eps`, i.e. the certainty units.
+/// These units are included with certainty by the designs of this module, e.g. [`brewer`],
+/// rather than being selected stochastically.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::unequal::*;
+///
+/// let p = [0.2, 1.0, 0.5, 0.3, 1.0];
+/// assert_eq!(certainty_units(&SampleOptions::new(&p)?)?, vec![1, 4]);
+/// # Ok::<(), SamplingError>(())
+/// ```
+pub fn certainty_units(options: &SampleOptions) -> Result<Vec<usize>, SamplingError> {
+    let probabilities = options.probabilities;
+    let eps = options.eps;
+    Probabilities::check(probabilities).and(Probabilities::check_eps(eps))?;
+
+    Ok(probabilities
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &p)| (1.0 - eps <= p).then_some(i))
+        .collect())
+}
+
+/// Heap entry of [`weighted_wor`], ordered in reverse by key, such that the unit with the
+/// smallest key is at the top of the heap.
+struct WeightedKey(f64, usize);
+
+impl PartialEq for WeightedKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl Eq for WeightedKey {}
+impl PartialOrd for WeightedKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for WeightedKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.total_cmp(&self.0)
+    }
+}
+
+/// Draw a weighted sample without replacement of size `n`, in one pass over `weights`, using the
+/// A-Res algorithm of Efraimidis and Spirakis.
+/// Each unit is assigned the key `u^(1/w)`, where `u` is uniform, and the `n` units with the
+/// largest keys are selected.
+/// Units with zero weight are never selected.
+/// Returns an error if `weights` is empty, if any weight is negative or not finite, or if fewer
+/// than `n` units have a positive weight.
+///
+/// **Note:** the design is equivalent to successive sampling proportional to weight.
+/// The inclusion probabilities are _not_ proportional to the weights, and are generally unknown.
+/// Thus, the sample should not be used with estimators requiring inclusion probabilities, such as
+/// the Horvitz-Thompson estimator.
+/// For such purposes, use a πps design, such as [`pareto`] or [`sampford`].
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::unequal::*;
+/// use rand::{rngs::SmallRng, SeedableRng};
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let w = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0];
+/// let s = weighted_wor(&mut rng, w.iter().copied(), 3)?;
+///
+/// assert_eq!(s.len(), 3);
+/// # Ok::<(), SamplingError>(())
+/// ```
+///
+/// # References
+/// Efraimidis, P. S., & Spirakis, P. G. (2006).
+/// Weighted random sampling with a reservoir.
+/// Information Processing Letters, 97(5), 181-185.
+/// <https://doi.org/10.1016/j.ipl.2005.11.003>
+pub fn weighted_wor<R, I>(rng: &mut R, weights: I, n: usize) -> Result<Vec<usize>, SamplingError>
+where
+    R: Rng + ?Sized,
+    I: IntoIterator<Item = f64>,
+{
+    let mut heap = BinaryHeap::<WeightedKey>::with_capacity(n + 1);
+    let mut n_units: usize = 0;
+
+    for (id, w) in weights.into_iter().enumerate() {
+        InputError::check_nan(w).and(InputError::check_range_f64(w, 0.0, f64::MAX))?;
+        n_units += 1;
+
+        if w == 0.0 || n == 0 {
+            continue;
+        }
+
+        // log(u^(1/w)) = log(u) / w, which preserves the ordering of the keys
+        let key = rng.gen::<f64>().ln() / w;
+
+        if heap.len() < n {
+            heap.push(WeightedKey(key, id));
+        } else if heap.peek().is_some_and(|min| min.0 < key) {
+            heap.pop();
+            heap.push(WeightedKey(key, id));
+        }
+    }
+
+    if n_units == 0 {
+        return Err(InputError::IsEmpty.into());
+    }
+    InputError::check_sizes(heap.len(), n)?;
+
+    let mut sample: Vec<usize> = heap.into_iter().map(|k| k.1).collect();
+    sample.sort_unstable();
+    Ok(sample)
+}
+
+/// Draw a sample without replacement of size `n`, using Lahiri's rejective method.
+/// In each iteration, a unit `i` is drawn uniformly, together with a random value `r` in
+/// `[0, max size)`, and the unit is accepted if `r < sizes[i]`.
+/// Accepted units that are already selected are discarded, until `n` distinct units are
+/// selected.
+/// Each iteration consumes one pair of random numbers, and at most `max_iterations` iterations
+/// are run, after which [`SamplingError::MaxIterations`] is returned.
+/// Returns an error if any size is not positive, or if `n` is larger than the population size.
+///
+/// **Note:** the first unit is selected with probability proportional to size, but as the
+/// sample is without replacement, the design is equivalent to successive sampling proportional to
+/// size, as [`weighted_wor`].
+/// The inclusion probabilities are _not_ proportional to the sizes.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::unequal::*;
+/// use rand::{rngs::SmallRng, SeedableRng};
+/// use std::num::NonZeroUsize;
+///
+/// let mut rng = SmallRng::from_entropy();
+/// let sizes = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0];
+/// let s = lahiri(&mut rng, &sizes, 3, NonZeroUsize::new(10000).unwrap())?;
+///
+/// assert_eq!(s.len(), 3);
+/// # Ok::<(), SamplingError>(())
+/// ```
+///
+/// # References
+/// Lahiri, D. B. (1951).
+/// A method of sample selection providing unbiased ratio estimates.
+/// Bulletin of the International Statistical Institute, 33(2), 133-140.
+pub fn lahiri<R>(
+    rng: &mut R,
+    sizes: &[f64],
+    n: usize,
+    max_iterations: NonZeroUsize,
+) -> Result<Vec<usize>, SamplingError>
+where
+    R: Rng + ?Sized,
+{
+    sizes.iter().try_for_each(|&s| {
+        InputError::check_nan(s)
+            .and(InputError::check_positive(s))
+            .and(InputError::check_range_f64(s, 0.0, f64::MAX))
+    })?;
+    InputError::check_range_usize(n, 0, sizes.len())?;
+
+    let max_size = sizes.iter().fold(0.0, |acc: f64, &s| acc.max(s));
+    let mut selected = vec![false; sizes.len()];
+    let mut sample = Vec::<usize>::with_capacity(n);
+
+    for _ in 0..max_iterations.get() {
+        if sample.len() == n {
+            break;
+        }
+
+        let id = rng.gen_range(0..sizes.len());
+        let r = rng.gen::<f64>() * max_size;
+
+        if r < sizes[id] && !selected[id] {
+            selected[id] = true;
+            sample.push(id);
+        }
+    }
+
+    if sample.len() < n {
+        return Err(SamplingError::MaxIterations(max_iterations));
+    }
+
     sample.sort_unstable();
     Ok(sample)
 }
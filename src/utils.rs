@@ -11,9 +11,165 @@
 // program. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::{SampleOptions, SamplingError};
-use envisim_utils::{Indices, Probabilities};
+use envisim_utils::utils::usize_to_f64;
+use envisim_utils::{Indices, InputError, Probabilities};
 use rand::Rng;
 
+/// Fills `buffer` with `n` uniform random numbers in `[0.0, 1.0)`.
+/// The random bits are generated in bulk, which avoids the per-call overhead of `rng.gen()`.
+/// The numbers are converted as by the `Standard` distribution, hence for generators producing
+/// their bytes from successive `u64`s (e.g. `SmallRng`), the numbers equal those of `n`
+/// successive calls to `rng.gen::<f64>()`.
+pub fn fill_uniform<R>(rng: &mut R, buffer: &mut Vec<f64>, n: usize)
+where
+    R: Rng + ?Sized,
+{
+    const BATCH_SIZE: usize = 256;
+    const SCALE: f64 = 1.0 / (1u64 << 53) as f64;
+    let mut bits = [0u64; BATCH_SIZE];
+
+    buffer.clear();
+    buffer.reserve(n);
+
+    while buffer.len() < n {
+        let batch = &mut bits[..BATCH_SIZE.min(n - buffer.len())];
+        rng.fill(batch);
+        buffer.extend(batch.iter().map(|&b| (b >> 11) as f64 * SCALE));
+    }
+}
+
+/// Merges samples drawn separately from parts of a population, e.g. strata, into a sample of
+/// global indices.
+/// The indices of `samples[k]` are local to part `k`, and `offsets[k]` is the global index of the
+/// first unit of part `k`.
+/// Returns the sorted global sample, or an error if `samples` and `offsets` have different
+/// lengths.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::merge_samples;
+///
+/// // Two strata of sizes 4 and 6
+/// let s = merge_samples(&[vec![1, 3], vec![0, 5]], &[0, 4])?;
+/// assert_eq!(s, vec![1, 3, 4, 9]);
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+pub fn merge_samples(
+    samples: &[Vec<usize>],
+    offsets: &[usize],
+) -> Result<Vec<usize>, SamplingError> {
+    InputError::check_lengths(samples, offsets)?;
+
+    let mut merged: Vec<usize> = samples
+        .iter()
+        .zip(offsets.iter())
+        .flat_map(|(sample, &offset)| sample.iter().map(move |&id| id + offset))
+        .collect();
+    merged.sort_unstable();
+
+    Ok(merged)
+}
+
+/// Returns the inclusion mask of `sample`, of length `population_size`, where element `i` is
+/// `true` if unit `i` is in the sample.
+/// Units selected multiple times, e.g. by with replacement designs, are marked once.
+/// Returns an error if any unit of the sample is not smaller than `population_size`.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::sample_to_mask;
+///
+/// let mask = sample_to_mask(&[0, 2, 3], 5)?;
+/// assert_eq!(mask, vec![true, false, true, true, false]);
+/// # Ok::<(), envisim_samplr::SamplingError>(())
+/// ```
+pub fn sample_to_mask(
+    sample: &[usize],
+    population_size: usize,
+) -> Result<Vec<bool>, SamplingError> {
+    if population_size == 0 {
+        InputError::check_sizes(sample.len(), 0)?;
+    }
+    sample.iter().try_for_each(|&id| {
+        InputError::check_range_usize(id, 0, population_size.saturating_sub(1))
+    })?;
+
+    let mut mask = vec![false; population_size];
+    sample.iter().for_each(|&id| mask[id] = true);
+
+    Ok(mask)
+}
+
+/// Returns the sorted sample of units whose element of `mask` is `true`, i.e. the inverse of
+/// [`sample_to_mask`].
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::mask_to_sample;
+///
+/// assert_eq!(mask_to_sample(&[true, false, true, true, false]), vec![0, 2, 3]);
+/// ```
+pub fn mask_to_sample(mask: &[bool]) -> Vec<usize> {
+    mask.iter()
+        .enumerate()
+        .filter_map(|(i, &included)| included.then_some(i))
+        .collect()
+}
+
+/// Returns the number of units common to the samples `a` and `b`.
+/// The samples are assumed to be sorted and free of duplicates, as returned by the designs with
+/// the default [`SampleOptions::sorted_output`], which allows a single merge pass.
+/// The result is unspecified for unsorted samples, which are only checked in debug builds.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::sample_overlap;
+///
+/// assert_eq!(sample_overlap(&[0, 2, 5, 7], &[1, 2, 7, 8, 9]), 2);
+/// ```
+pub fn sample_overlap(a: &[usize], b: &[usize]) -> usize {
+    debug_assert!(a.windows(2).all(|w| w[0] < w[1]), "a is not sorted");
+    debug_assert!(b.windows(2).all(|w| w[0] < w[1]), "b is not sorted");
+
+    let (mut i, mut j, mut overlap) = (0, 0, 0);
+
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                overlap += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+
+    overlap
+}
+
+/// Returns the Jaccard index of the samples `a` and `b`, i.e. the number of common units divided
+/// by the number of units in either sample.
+/// The index is `1.0` if both samples are empty.
+/// As for [`sample_overlap`], the samples are assumed to be sorted and free of duplicates.
+///
+/// # Examples
+/// ```
+/// use envisim_samplr::sample_jaccard;
+///
+/// assert_eq!(sample_jaccard(&[0, 2, 5, 7], &[1, 2, 7, 8]), 2.0 / 6.0);
+/// ```
+pub fn sample_jaccard(a: &[usize], b: &[usize]) -> f64 {
+    let overlap = sample_overlap(a, b);
+    let union = a.len() + b.len() - overlap;
+
+    if union == 0 {
+        return 1.0;
+    }
+
+    usize_to_f64(overlap) / usize_to_f64(union)
+}
+
 pub struct Sample(Vec<usize>);
 
 impl Sample {
@@ -0,0 +1,139 @@
+use envisim_samplr::bas::*;
+use envisim_samplr::systematic::sample_random_order;
+use envisim_test_utils::*;
+use envisim_utils::Matrix;
+use rand::Rng;
+
+mod test_utils;
+use test_utils::*;
+
+#[test]
+fn test_halton_order() -> Result<(), SamplingError> {
+    // A 4 x 3 grid, where each unit has its own Halton box
+    let coords: Vec<f64> = (0..12)
+        .map(|i| f64::from(i % 4))
+        .chain((0..12).map(|i| f64::from(i / 4)))
+        .collect();
+    let m = Matrix::from_vec(coords, 12);
+    let p = [0.5; 12];
+    let mut options = SampleOptions::new(&p)?;
+    options.auxiliaries(&m)?;
+
+    for seed in 0..20 {
+        let order = halton_order(&options, seed)?;
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..12).collect::<Vec<usize>>());
+
+        // Any 6 consecutive Halton points fall in each half of the first dimension three times,
+        // and in each third of the second dimension twice
+        let first = &order[0..6];
+        assert_eq!(first.iter().filter(|&&id| id % 4 < 2).count(), 3);
+        for row in 0..3 {
+            assert_eq!(first.iter().filter(|&&id| id / 4 == row).count(), 2);
+        }
+    }
+
+    assert_eq!(halton_order(&options, 7)?, halton_order(&options, 7)?);
+    halton_order(&SampleOptions::new(&p)?, 7).unwrap_err();
+    Ok(())
+}
+
+#[test]
+fn test_bas() -> Result<(), SamplingError> {
+    let p = &PROB_10_U;
+    let m = Matrix::from_ref(&DATA_10_2, 10);
+    let mut options = SampleOptions::new(p)?;
+    options.auxiliaries(&m)?;
+    let mut seed = 0;
+
+    test_wor2(
+        || {
+            seed += 1;
+            let s = bas(&options, seed)?;
+            assert_eq!(s.len(), 5);
+            Ok(s)
+        },
+        p,
+        1e-2,
+        100000,
+    )
+}
+
+#[test]
+fn test_bas_spatial_balance() -> Result<(), SamplingError> {
+    // Points on the unit square, and the deviation of the number of sampled units in each
+    // quadrant from its expectation
+    let mut rng = seeded_rng();
+    let n_units = 200;
+    let coords: Vec<f64> = (0..2 * n_units).map(|_| rng.gen::<f64>()).collect();
+    let m = Matrix::from_vec(coords, n_units);
+    let p = vec![0.1; n_units];
+    let mut options = SampleOptions::new(&p)?;
+    options.auxiliaries(&m)?;
+
+    let quadrant_deviation = |s: &[usize]| -> f64 {
+        let mut counts = [0.0; 4];
+        let mut expected = [0.0; 4];
+        for id in 0..n_units {
+            let q = usize::from(m[(id, 0)] < 0.5) + 2 * usize::from(m[(id, 1)] < 0.5);
+            expected[q] += p[id];
+            if s.contains(&id) {
+                counts[q] += 1.0;
+            }
+        }
+        counts
+            .iter()
+            .zip(expected.iter())
+            .map(|(c, e)| (c - e) * (c - e))
+            .sum()
+    };
+
+    // Compared to systematic sampling in random order, both the sample and the first units of
+    // the Halton order are spatially balanced
+    let (mut dev_bas, mut dev_order, mut dev_sys) = (0.0, 0.0, 0.0);
+    for seed in 0..2000 {
+        dev_bas += quadrant_deviation(&bas(&options, seed)?);
+        dev_order += quadrant_deviation(&halton_order(&options, seed)?[0..20]);
+        dev_sys += quadrant_deviation(&sample_random_order(&mut rng, &options)?);
+    }
+
+    assert!(dev_bas < 0.5 * dev_sys);
+    assert!(dev_order < 0.5 * dev_sys);
+    Ok(())
+}
+
+#[test]
+fn test_extend() -> Result<(), SamplingError> {
+    let p = &PROB_10_U;
+    let m = Matrix::from_ref(&DATA_10_2, 10);
+    let mut options = SampleOptions::new(p)?;
+    options.auxiliaries(&m)?;
+
+    for seed in 0..20 {
+        let s = bas(&options, seed)?;
+        let added = extend(&s, &options, seed, 3)?;
+        assert_eq!(added.len(), 3);
+        assert!(added.iter().all(|id| !s.contains(id)));
+
+        // The original units are preserved, and extensions continue where the last one stopped
+        let mut combined = s.clone();
+        combined.extend(extend(&s, &options, seed, 1)?);
+        combined.extend(extend(&combined, &options, seed, 2)?);
+        combined.sort_unstable();
+        let mut expected: Vec<usize> = s.iter().chain(added.iter()).copied().collect();
+        expected.sort_unstable();
+        assert_eq!(combined, expected);
+
+        // The sample can be extended to the full population
+        assert_eq!(extend(&s, &options, seed, 5)?.len(), 5);
+        extend(&s, &options, seed, 6).unwrap_err();
+
+        // Inconsistent existing samples
+        extend(&s[1..], &options, seed, 1).unwrap_err();
+        extend(&[s.as_slice(), &s[0..1]].concat(), &options, seed, 1).unwrap_err();
+        extend(&[s.as_slice(), &[10]].concat(), &options, seed, 1).unwrap_err();
+    }
+
+    Ok(())
+}
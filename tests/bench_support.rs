@@ -0,0 +1,18 @@
+use envisim_samplr::bench_support::*;
+use envisim_samplr::unequal::brewer;
+use envisim_samplr::{SampleOptions, SamplingError};
+use envisim_test_utils::*;
+use std::num::NonZeroU32;
+use std::time::Duration;
+
+#[test]
+fn test_measure_design_time() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let options = SampleOptions::new(&PROB_10_U)?;
+    let time = measure_design_time(&mut rng, brewer, &options, NonZeroU32::new(100).unwrap())?;
+
+    assert!(time > Duration::ZERO);
+    assert!(time < Duration::from_secs(1));
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use envisim_samplr::controlled::*;
+use envisim_test_utils::*;
+
+mod test_utils;
+use test_utils::*;
+
+#[test]
+fn test_controlled_selection() -> Result<(), SamplingError> {
+    let p = &PROB_10_U;
+    let opts = SampleOptions::new(p)?;
+    let undesirable = [(0, 1), (3, 4), (8, 9)];
+    let design = ControlledDesign::new(&opts, &undesirable)?;
+
+    // A linear program is solved for each sample, hence the marginals are tested for
+    // ControlledDesign, and controlled_selection is only compared to it
+    let (mut rng_a, mut rng_b) = (seeded_rng(), seeded_rng());
+    for _ in 0..200 {
+        assert_eq!(
+            controlled_selection(&mut rng_a, &opts, &undesirable)?,
+            design.sample(&mut rng_b)
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_controlled_selection_avoids_pairs() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let p = [0.5, 0.5, 0.5, 0.5, 1.0, 0.0];
+    let opts = SampleOptions::new(&p)?;
+    let undesirable = [(0, 1), (2, 3), (4, 5)];
+
+    // All samples containing a pair can be avoided, and certain and zero units are respected
+    test_wor2(
+        || {
+            let s = controlled_selection(&mut rng, &opts, &undesirable)?;
+            assert_eq!(s.len(), 3);
+            assert!(s.contains(&4));
+            assert!(!(s.contains(&0) && s.contains(&1)));
+            assert!(!(s.contains(&2) && s.contains(&3)));
+            Ok(s)
+        },
+        &p,
+        1e-2,
+        100000,
+    )
+}
+
+#[test]
+fn test_controlled_design() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let p = &PROB_10_U;
+    let opts = SampleOptions::new(p)?;
+    let undesirable = [(0, 1), (3, 4), (8, 9)];
+    let design = ControlledDesign::new(&opts, &undesirable)?;
+
+    let (samples, probabilities) = design.support();
+    assert_eq!(samples.len(), probabilities.len());
+    assert_delta!(probabilities.iter().sum::<f64>(), 1.0, 1e-9);
+
+    // The linear program is solved once, hence more iterations than for controlled_selection
+    test_wor2(|| Ok(design.sample(&mut rng)), p, 1e-2, 100000)
+}
+
+#[test]
+fn test_controlled_selection_errors() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let p = [0.5, 0.5, 0.5];
+    controlled_selection(&mut rng, &SampleOptions::new(&p)?, &[]).unwrap_err();
+    let q = [0.5, 0.5];
+    controlled_selection(&mut rng, &SampleOptions::new(&q)?, &[(0, 2)]).unwrap_err();
+
+    // Too many possible samples
+    let large = [0.5; 40];
+    controlled_selection(&mut rng, &SampleOptions::new(&large)?, &[]).unwrap_err();
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use envisim_samplr::coordinated::*;
+use envisim_samplr::pivotal_method::lpm_1;
+use envisim_test_utils::*;
+use envisim_utils::utils::usize_to_f64;
+use envisim_utils::Matrix;
+use rand::Rng;
+
+#[test]
+fn disjoint() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let data = Matrix::from_ref(&DATA_10_2, 10);
+    let pb = [0.4; 10];
+    let mut options_a = SampleOptions::new(&PROB_10_E)?;
+    options_a.auxiliaries(&data)?;
+    let mut options_b = SampleOptions::new(&pb)?;
+    options_b.auxiliaries(&data)?;
+
+    for _ in 0..1000 {
+        let (sa, sb) = disjoint_samples(&mut rng, &options_a, &options_b, lpm_1)?;
+        assert_eq!(sa.len(), 2);
+        assert_eq!(sb.len(), 4);
+        assert!(sa.iter().all(|id| !sb.contains(id)));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn disjoint_too_large() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let pb = [0.9; 10];
+    let options_a = SampleOptions::new(&PROB_10_U)?;
+    let options_b = SampleOptions::new(&pb)?;
+
+    disjoint_samples(&mut rng, &options_a, &options_b, lpm_1).unwrap_err();
+
+    Ok(())
+}
+
+#[test]
+fn prn() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let p = &PROB_10_U;
+    let mut counts = [0usize; 10];
+    let n_reps = 10000;
+
+    for _ in 0..n_reps {
+        let prn: Vec<f64> = (0..10).map(|_| rng.gen::<f64>()).collect();
+        let s = poisson_prn(SampleOptions::new(p)?.random_values(&prn)?)?;
+        s.iter().for_each(|&id| counts[id] += 1);
+
+        // Raising the probability of unit 3 only ever adds it
+        let mut p_raised = p.to_vec();
+        p_raised[3] = 0.95;
+        let s_raised = poisson_prn(SampleOptions::new(&p_raised)?.random_values(&prn)?)?;
+        assert!(s.iter().all(|id| s_raised.contains(id)));
+        assert!(s_raised.iter().all(|&id| id == 3 || s.contains(&id)));
+    }
+
+    for (c, &pi) in counts.iter().zip(p.iter()) {
+        assert_delta!(usize_to_f64(*c) / f64::from(n_reps), pi, 2e-2);
+    }
+
+    poisson_prn(&SampleOptions::new(p)?).unwrap_err();
+    poisson_prn(SampleOptions::new(&[0.5, 0.5])?.random_values(&[0.5, 1.5])?).unwrap_err();
+
+    // Units with probability 1.0 are included, also for a prn of 1.0
+    let s = poisson_prn(SampleOptions::new(&[1.0, 0.5])?.random_values(&[1.0, 1.0])?)?;
+    assert_eq!(s, vec![0]);
+    Ok(())
+}
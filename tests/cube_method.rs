@@ -37,6 +37,34 @@ fn test_lcube() -> Result<(), SamplingError> {
     test_wor(local_cube, &mut rng, &opts, p, 1e-2, 100000)
 }
 
+#[test]
+fn test_cube_flight_only() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let p = &PROB_10_U;
+    let baldata = Matrix::from_ref(&BAL_DATA_10_1_P, 10);
+    let mut opts = SampleOptions::new(p)?;
+    opts.balancing(&baldata)?;
+
+    let iter: u32 = 100000;
+    let mut means = [0.0; 10];
+
+    for _ in 0..iter {
+        let q = cube_flight_only(&mut rng, &opts)?;
+        assert_eq!(q.len(), 10);
+        assert!(q.iter().all(|&x| (0.0..=1.0).contains(&x)));
+        assert!(q.iter().filter(|&&x| 0.0 < x && x < 1.0).count() <= baldata.ncol());
+        assert_delta!(sum(&q), sum(p), 1e-9);
+        means.iter_mut().zip(q.iter()).for_each(|(m, &x)| *m += x);
+    }
+
+    // The flight phase is a martingale, and preserves the inclusion probabilities
+    for (m, &pi) in means.iter().zip(p.iter()) {
+        assert_delta!(m / f64::from(iter), pi, 1e-2);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_cube_stratified() -> Result<(), SamplingError> {
     let eps = 1e-2;
@@ -59,7 +87,10 @@ fn test_cube_stratified() -> Result<(), SamplingError> {
             s.iter().for_each(|&id| sel[id] += 1);
         }
 
-        let q: Vec<f64> = sel.iter().map(|&s| (s as f64) / (iter as f64)).collect();
+        let q: Vec<f64> = sel
+            .iter()
+            .map(|&s| f64::from(s) / f64::from(iter))
+            .collect();
         let d: Vec<f64> = probs.iter().zip(q.iter()).map(|(p, r)| p - r).collect();
 
         if !d.iter().all(|&x| x.abs() < eps) {
@@ -89,7 +120,10 @@ fn test_lcube_stratified() -> Result<(), SamplingError> {
             s.iter().for_each(|&id| sel[id] += 1);
         }
 
-        let q: Vec<f64> = sel.iter().map(|&s| (s as f64) / (iter as f64)).collect();
+        let q: Vec<f64> = sel
+            .iter()
+            .map(|&s| f64::from(s) / f64::from(iter))
+            .collect();
         let d: Vec<f64> = probs.iter().zip(q.iter()).map(|(p, r)| p - r).collect();
 
         if !d.iter().all(|&x| x.abs() < eps) {
@@ -98,3 +132,55 @@ fn test_lcube_stratified() -> Result<(), SamplingError> {
     }
     Ok(())
 }
+
+#[test]
+fn test_all_zero_probabilities() -> Result<(), SamplingError> {
+    let data = Matrix::from_ref(&DATA_10_2, 10);
+
+    for p in [[0.0; 10], [1e-14; 10]] {
+        let mut opts = SampleOptions::new(&p)?;
+        opts.auxiliaries(&data)?.balancing(&data)?;
+
+        assert_eq!(cube(&mut seeded_rng(), &opts)?, vec![]);
+        assert_eq!(local_cube(&mut seeded_rng(), &opts)?, vec![]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_lcube_balance_and_spread() -> Result<(), SamplingError> {
+    // Units on a line, balanced on the probabilities and on y
+    let mut rng = seeded_rng();
+    let p = [0.25; 40];
+    let x: Vec<f64> = (0..40).map(f64::from).collect();
+    let y: Vec<f64> = x.iter().map(|&v| 1.0 + v).collect();
+    let data = Matrix::from_ref(&x, 40);
+    let baldata = Matrix::from_vec([p.to_vec(), y.clone()].concat(), 40);
+    let mut opts = SampleOptions::new(&p)?;
+    opts.balancing(&baldata)?.auxiliaries(&data)?;
+
+    let iter: u32 = 1000;
+    let (mut lcube_error, mut cube_error) = (0.0, 0.0);
+    let (mut lcube_pairs, mut cube_pairs) = (0usize, 0usize);
+    let adjacent_pairs = |s: &[usize]| s.windows(2).filter(|w| w[1] - w[0] == 1).count();
+
+    for _ in 0..iter {
+        let s = local_cube(&mut rng, &opts)?;
+        assert_eq!(s.len(), 10);
+        lcube_error += (s.iter().map(|&id| y[id] / 0.25).sum::<f64>() - sum(&y)).abs();
+        lcube_pairs += adjacent_pairs(&s);
+
+        let s = cube(&mut rng, &opts)?;
+        cube_error += (s.iter().map(|&id| y[id] / 0.25).sum::<f64>() - sum(&y)).abs();
+        cube_pairs += adjacent_pairs(&s);
+    }
+
+    // The estimated total of y deviates much less than under e.g. srs, where the mean relative
+    // deviation is about 0.125
+    assert!(lcube_error / f64::from(iter) / sum(&y) < 0.08);
+    assert!(cube_error / f64::from(iter) / sum(&y) < 0.08);
+    // The sample is well spread, with few pairs of neighbouring units in the sample
+    assert!(lcube_pairs * 4 < cube_pairs);
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use envisim_samplr::multiphase::*;
+use envisim_samplr::poisson;
+use envisim_samplr::unequal::brewer;
+use envisim_test_utils::*;
+
+mod test_utils;
+use test_utils::*;
+
+#[test]
+fn test_two_phase() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let p1 = &PROB_10_U;
+    let cond: Vec<f64> = (0..10).map(|i| 0.1 * f64::from(i) + 0.05).collect();
+    let p: Vec<f64> = p1.iter().zip(cond.iter()).map(|(a, b)| a * b).collect();
+    let opts = SampleOptions::new(p1)?;
+
+    test_wor2(
+        || {
+            let s = two_phase(
+                &mut rng,
+                &opts,
+                brewer,
+                |s1| s1.iter().map(|&id| cond[id]).collect(),
+                poisson::sample,
+            )?;
+            assert_eq!(s.first_phase.len(), 5);
+            assert!(s.second_phase.iter().all(|id| s.first_phase.contains(id)));
+            s.second_phase
+                .iter()
+                .zip(s.probabilities.iter())
+                .for_each(|(&id, &pi)| assert_delta!(pi, p[id], EPS));
+            Ok(s.second_phase)
+        },
+        &p,
+        1e-2,
+        100000,
+    )
+}
+
+#[test]
+fn test_two_phase_empty_first_phase() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let s = two_phase(
+        &mut rng,
+        &SampleOptions::new(&[0.0; 10])?,
+        poisson::sample,
+        |s| vec![0.5; s.len()],
+        brewer,
+    )?;
+
+    assert!(s.first_phase.is_empty());
+    assert!(s.second_phase.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_two_phase_carries_options() -> Result<(), SamplingError> {
+    use envisim_samplr::pivotal_method::lpm_1;
+    use envisim_utils::Matrix;
+
+    let mut rng = seeded_rng();
+    let m = Matrix::new(&DATA_10_2, 10);
+    let p = [0.8; 10];
+    let mut opts = SampleOptions::new(&p)?;
+    opts.auxiliaries(&m)?;
+
+    // The second phase is spatially balanced on the auxiliaries of the first phase units
+    let s = two_phase(
+        &mut rng,
+        &opts,
+        poisson::sample,
+        |s1| vec![0.5; s1.len()],
+        lpm_1,
+    )?;
+    assert!(s.second_phase.iter().all(|id| s.first_phase.contains(id)));
+
+    let p = [1.0; 10];
+    let mut opts = SampleOptions::new(&p)?;
+    opts.allow_empty_sample(false)?;
+    let err = two_phase(
+        &mut rng,
+        &opts,
+        poisson::sample,
+        |s1| vec![0.0; s1.len()],
+        brewer,
+    )
+    .unwrap_err();
+    assert!(matches!(err, SamplingError::EmptySample));
+
+    Ok(())
+}
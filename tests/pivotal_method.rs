@@ -15,6 +15,39 @@ fn test_spm() -> Result<(), SamplingError> {
     test_wor(spm, &mut rng, &opts, p, 1e-2, 10000)
 }
 
+#[test]
+fn test_ordered_pivotal() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let p = [0.3; 20];
+    let opts = SampleOptions::new(&p)?;
+    let iter: u32 = 20000;
+    let mut counts = [0u32; 20];
+    let mut pairs = [0u32; 19];
+
+    for _ in 0..iter {
+        let s = ordered_pivotal(&mut rng, &opts)?;
+        assert_eq!(s.len(), 6);
+        s.iter().for_each(|&id| counts[id] += 1);
+        s.windows(2)
+            .filter(|w| w[1] == w[0] + 1)
+            .for_each(|w| pairs[w[0]] += 1);
+    }
+
+    // The marginals are preserved, while adjacent units are negatively correlated, i.e. the
+    // joint probabilities are on average well below 0.3 * 0.3
+    for &c in counts.iter() {
+        assert_delta!(f64::from(c) / f64::from(iter), 0.3, 1e-2);
+    }
+    let mean_pair = pairs.iter().map(|&c| f64::from(c)).sum::<f64>() / 19.0 / f64::from(iter);
+    assert!(mean_pair < 0.06);
+
+    // The probabilities must sum to an integer
+    let p = [0.25; 10];
+    ordered_pivotal(&mut rng, &SampleOptions::new(&p)?).unwrap_err();
+
+    Ok(())
+}
+
 #[test]
 fn test_rpm() -> Result<(), SamplingError> {
     let mut rng = seeded_rng();
@@ -77,7 +110,10 @@ fn test_hlpm2() -> Result<(), SamplingError> {
             s.iter().flatten().for_each(|&id| sel[id] += 1);
         }
 
-        let q: Vec<f64> = sel.iter().map(|&s| (s as f64) / (iter as f64)).collect();
+        let q: Vec<f64> = sel
+            .iter()
+            .map(|&s| f64::from(s) / f64::from(iter))
+            .collect();
         let d: Vec<f64> = probs.iter().zip(q.iter()).map(|(p, r)| p - r).collect();
 
         if !d.iter().all(|&x| x.abs() < eps) {
@@ -94,7 +130,10 @@ fn test_hlpm2() -> Result<(), SamplingError> {
             s.iter().flatten().for_each(|&id| sel[id] += 1);
         }
 
-        let q: Vec<f64> = sel.iter().map(|&s| (s as f64) / (iter as f64)).collect();
+        let q: Vec<f64> = sel
+            .iter()
+            .map(|&s| f64::from(s) / f64::from(iter))
+            .collect();
         let d: Vec<f64> = probs.iter().zip(q.iter()).map(|(p, r)| p - r).collect();
 
         if !d.iter().all(|&x| x.abs() < eps) {
@@ -104,3 +143,20 @@ fn test_hlpm2() -> Result<(), SamplingError> {
 
     Ok(())
 }
+
+#[test]
+fn test_all_zero_probabilities() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let data = Matrix::from_ref(&DATA_10_2, 10);
+
+    for p in [[0.0; 10], [1e-14; 10]] {
+        let mut opts = SampleOptions::new(&p)?;
+        opts.auxiliaries(&data)?;
+
+        for sampler in [spm, rpm, lpm_1, lpm_1s, lpm_2] {
+            assert_eq!(sampler(&mut rng, &opts)?, vec![]);
+        }
+    }
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use envisim_samplr::poisson::*;
 use envisim_test_utils::*;
+use envisim_utils::utils::usize_to_f64;
 use envisim_utils::Matrix;
 
 mod test_utils;
@@ -14,6 +15,29 @@ fn test_poisson() -> Result<(), SamplingError> {
     test_wor(sample, &mut rng, &opts, p, 1e-2, 100000)
 }
 
+#[test]
+fn test_from_expected_size() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let sizes = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 40.0];
+    let p = from_expected_size(&sizes, 3.5, EPS)?;
+    assert_delta!(p.iter().sum::<f64>(), 3.5, EPS);
+    assert_delta!(p[9], 1.0, EPS);
+    assert_delta!(p[0] * 2.0, p[1], EPS);
+
+    let opts = SampleOptions::new(&p)?;
+    let iterations = 100000;
+    let total: usize = (0..iterations)
+        .map(|_| sample(&mut rng, &opts).map(|s| s.len()))
+        .sum::<Result<usize, SamplingError>>()?;
+    assert_delta!(usize_to_f64(total) / f64::from(iterations), 3.5, 1e-2);
+
+    from_expected_size(&sizes, 0.0, EPS).unwrap_err();
+    from_expected_size(&sizes, 10.5, EPS).unwrap_err();
+    from_expected_size(&[1.0, -1.0], 1.0, EPS).unwrap_err();
+
+    Ok(())
+}
+
 // So inefficient...
 #[test]
 fn test_conditional() -> Result<(), SamplingError> {
@@ -54,3 +78,141 @@ fn test_lcps() -> Result<(), SamplingError> {
 
     test_wor(lcps, &mut rng, &opts, p, 1e-2, 100000)
 }
+
+#[test]
+fn test_sample_from_iter() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let mut reference_rng = seeded_rng();
+    // Lazily generated probabilities, summing to 10
+    let source = (0..1000).map(|i| 0.02 * f64::from(i % 2 == 0));
+    let p: Vec<f64> = source.clone().collect();
+
+    for _ in 0..10 {
+        assert_eq!(
+            sample_from_iter(&mut rng, source.clone())?,
+            sample(&mut reference_rng, &SampleOptions::new(&p)?)?
+        );
+    }
+
+    sample_from_iter(&mut rng, [0.5, 1.5]).unwrap_err();
+    Ok(())
+}
+
+#[test]
+fn test_all_zero_probabilities() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let data = Matrix::from_ref(&DATA_10_2, 10);
+
+    for p in [[0.0; 10], [1e-14; 10]] {
+        let mut opts = SampleOptions::new(&p)?;
+        opts.auxiliaries(&data)?;
+
+        for sampler in [cps, scps] {
+            assert_eq!(sampler(&mut rng, &opts)?, vec![]);
+        }
+        assert_eq!(lcps(&mut seeded_rng(), &opts)?, vec![]);
+        assert_eq!(conditional(&mut rng, &opts, 0)?, vec![]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_sample_with_uniforms() -> Result<(), SamplingError> {
+    use rand::Rng;
+
+    let mut rng = seeded_rng();
+    let pa = PROB_10_U;
+    let pb: Vec<f64> = pa.iter().map(|&p| p * 0.5).collect();
+
+    // Identical uniforms yield nested samples for monotone probabilities
+    for _ in 0..100 {
+        let u: Vec<f64> = (0..10).map(|_| rng.gen::<f64>()).collect();
+        let sa = sample_with_uniforms(&u, &pa)?;
+        let sb = sample_with_uniforms(&u, &pb)?;
+        assert!(sb.iter().all(|id| sa.contains(id)));
+    }
+
+    let u = [0.5; 10];
+    test_wor2(
+        || {
+            let u: Vec<f64> = (0..10).map(|_| rng.gen::<f64>()).collect();
+            sample_with_uniforms(&u, &pa)
+        },
+        &pa,
+        1e-2,
+        10000,
+    )?;
+    sample_with_uniforms(&u[1..], &pa).unwrap_err();
+    sample_with_uniforms(&[1.5; 10], &pa).unwrap_err();
+    sample_with_uniforms(&[f64::NAN; 10], &pa).unwrap_err();
+
+    // Units with probability 1.0 are included, also for a uniform of 1.0
+    assert_eq!(sample_with_uniforms(&[1.0, 1.0], &[1.0, 0.5])?, vec![0]);
+    Ok(())
+}
+
+#[test]
+fn test_rejective() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let p = [0.2, 0.3, 0.4, 0.5, 0.6, 0.8];
+    let opts = SampleOptions::new(&p)?;
+
+    // Conditioning on the sample size yields conditional poisson sampling, where the probability
+    // of a sample s is proportional to the product of p/(1-p) over s
+    let mut cps = [0.0; 6];
+    let mut norm = 0.0;
+    for set in 0u32..(1 << p.len()) {
+        if set.count_ones() != 3 {
+            continue;
+        }
+        let units: Vec<usize> = (0..p.len()).filter(|&i| set & (1 << i) != 0).collect();
+        let prob: f64 = units.iter().map(|&i| p[i] / (1.0 - p[i])).product();
+        units.iter().for_each(|&i| cps[i] += prob);
+        norm += prob;
+    }
+    cps.iter_mut().for_each(|c| *c /= norm);
+
+    test_wor2(
+        || rejective(&mut rng, &opts, |s| s.len() == 3),
+        &cps,
+        1e-2,
+        10000,
+    )?;
+
+    // An arbitrary predicate, including at least one of the units 0 and 1
+    for _ in 0..100 {
+        let s = rejective(&mut rng, &opts, |s| s.iter().any(|&id| id < 2))?;
+        assert!(s.iter().any(|&id| id < 2));
+    }
+
+    // An impossible predicate
+    assert!(matches!(
+        rejective(&mut rng, &opts, |s| s.len() > 6),
+        Err(SamplingError::MaxIterations(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_empty_input() {
+    use envisim_utils::InputError;
+
+    let mut rng = seeded_rng();
+    let empty: [f64; 0] = [];
+
+    for err in [
+        sample_from_iter(&mut rng, empty).unwrap_err(),
+        sample_with_uniforms(&empty, &empty).unwrap_err(),
+        from_expected_size(&empty, 1.0, EPS).unwrap_err(),
+    ] {
+        assert!(matches!(err, SamplingError::Input(InputError::IsEmpty)));
+    }
+
+    // The designs taking options are protected by SampleOptions::new
+    assert!(matches!(
+        SampleOptions::new(&empty),
+        Err(InputError::IsEmpty)
+    ));
+}
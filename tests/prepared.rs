@@ -0,0 +1,109 @@
+use envisim_samplr::systematic;
+use envisim_samplr::unequal::with_replacement;
+use envisim_samplr::{PreparedDesign, PreparedFrame, SampleOptions, SamplingError};
+use envisim_test_utils::*;
+
+mod test_utils;
+use test_utils::*;
+
+#[test]
+fn test_systematic() -> Result<(), SamplingError> {
+    let p = [0.0, 0.5, 0.25, 0.0, 0.75, 1.0, 0.5, 0.0, 0.4, 0.6];
+    let frame = PreparedFrame::new(&p, EPS)?;
+    let opts = SampleOptions::new(&p)?;
+    let mut rng = seeded_rng();
+    let mut reference_rng = seeded_rng();
+
+    for _ in 0..1000 {
+        assert_eq!(
+            frame.sample(&mut rng, PreparedDesign::Systematic)?,
+            systematic::sample(&mut reference_rng, &opts)?
+        );
+    }
+
+    let frame = PreparedFrame::new(&PROB_10_U, EPS)?;
+    test_wor2(
+        || frame.sample(&mut rng, PreparedDesign::Systematic),
+        &PROB_10_U,
+        1e-2,
+        10000,
+    )
+}
+
+#[test]
+fn test_with_replacement() -> Result<(), SamplingError> {
+    let p = [0.1, 0.0, 0.05, 0.3, 0.15, 0.0, 0.2, 0.2];
+    let frame = PreparedFrame::new(&p, EPS)?;
+    let opts = SampleOptions::new(&p)?;
+    let mut rng = seeded_rng();
+    let n = 10;
+    let iterations: u32 = 20000;
+    let mut counts = vec![0u32; p.len()];
+    let mut reference_counts = vec![0u32; p.len()];
+
+    for _ in 0..iterations {
+        let s = frame.sample(&mut rng, PreparedDesign::WithReplacement(n))?;
+        assert!(s.is_sorted());
+        s.iter().for_each(|&id| counts[id] += 1);
+        with_replacement(&mut rng, &opts, n)?
+            .iter()
+            .for_each(|&id| reference_counts[id] += 1);
+    }
+
+    for ((&c, &r), &pi) in counts.iter().zip(reference_counts.iter()).zip(p.iter()) {
+        let scale = f64::from(iterations) * 10.0;
+        assert_delta!(f64::from(c) / scale, pi, 5e-3);
+        assert_delta!(f64::from(c) / scale, f64::from(r) / scale, 5e-3);
+    }
+
+    PreparedFrame::new(&PROB_10_U, EPS)?
+        .sample(&mut rng, PreparedDesign::WithReplacement(2))
+        .unwrap_err();
+
+    // Probabilities summing to slightly less than 1.0 are accepted within eps, as by
+    // with_replacement
+    let p = [0.5, 0.5 - 1e-3];
+    let mut opts = SampleOptions::new(&p)?;
+    opts.eps(1e-2)?;
+    with_replacement(&mut rng, &opts, 10)?;
+    let frame = PreparedFrame::new(&p, 1e-2)?;
+    assert_eq!(
+        frame
+            .sample(&mut rng, PreparedDesign::WithReplacement(10))?
+            .len(),
+        10
+    );
+    PreparedFrame::new(&p, EPS)?
+        .sample(&mut rng, PreparedDesign::WithReplacement(10))
+        .unwrap_err();
+    Ok(())
+}
+
+#[test]
+fn test_sync() -> Result<(), SamplingError> {
+    fn assert_sync<T: Sync>(_: &T) {}
+
+    let frame = PreparedFrame::new(&PROB_10_U, EPS)?;
+    assert_sync(&frame);
+
+    std::thread::scope(|scope| {
+        for seed in 0..4 {
+            let frame = &frame;
+            scope.spawn(move || {
+                let mut rng = envisim_samplr::derive_rng(seed, 0);
+                assert_eq!(
+                    frame
+                        .sample(&mut rng, PreparedDesign::Systematic)
+                        .unwrap()
+                        .len(),
+                    5
+                );
+            });
+        }
+    });
+
+    PreparedFrame::new(&[], EPS).unwrap_err();
+    PreparedFrame::new(&[0.5, 1.5], EPS).unwrap_err();
+    PreparedFrame::new(&[0.5, 0.5], 1.0).unwrap_err();
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use envisim_samplr::derive_rng;
+use envisim_test_utils::*;
+use rand::Rng;
+
+#[test]
+fn test_derive_rng() {
+    let n = 100000;
+    assert_eq!(
+        derive_rng(4242, 7).gen::<u64>(),
+        derive_rng(4242, 7).gen::<u64>()
+    );
+
+    let mut rng_0 = derive_rng(4242, 0);
+    let mut rng_1 = derive_rng(4242, 1);
+    let mut rng_2 = derive_rng(4243, 0);
+    let (mut s0, mut s1, mut s01, mut s02) = (0.0, 0.0, 0.0, 0.0);
+
+    for _ in 0..n {
+        let u0 = rng_0.gen::<f64>() - 0.5;
+        let u1 = rng_1.gen::<f64>() - 0.5;
+        let u2 = rng_2.gen::<f64>() - 0.5;
+        s0 += u0 * u0;
+        s1 += u1 * u1;
+        s01 += u0 * u1;
+        s02 += u0 * u2;
+    }
+
+    // The variance of a uniform is 1/12, and the streams should be uncorrelated
+    let n = f64::from(n);
+    assert_delta!(s0 / n, 1.0 / 12.0, 1e-3);
+    assert_delta!(s1 / n, 1.0 / 12.0, 1e-3);
+    assert_delta!(s01 / n * 12.0, 0.0, 1e-2);
+    assert_delta!(s02 / n * 12.0, 0.0, 1e-2);
+}
+
+#[test]
+fn test_antithetic_rng() {
+    use envisim_samplr::AntitheticRng;
+
+    let mut rng = AntitheticRng::new(derive_rng(4242, 0));
+    let mut reference = derive_rng(4242, 0);
+
+    for _ in 0..10 {
+        rng.new_pair();
+        let u: Vec<f64> = (0..5).map(|_| rng.gen::<f64>()).collect();
+        let expected: Vec<f64> = (0..5).map(|_| reference.gen::<f64>()).collect();
+        assert_eq!(u, expected);
+
+        rng.replay();
+        for &x in u.iter() {
+            assert_delta!(rng.gen::<f64>(), 1.0 - x, 1e-15);
+        }
+        // Beyond the recorded numbers, the inner rng is used
+        assert_eq!(rng.gen::<u64>(), reference.gen::<u64>());
+    }
+}
@@ -0,0 +1,47 @@
+use envisim_samplr::unequal::brewer;
+use envisim_samplr::SampleOptions;
+use envisim_test_utils::*;
+use envisim_utils::InputError;
+
+#[test]
+fn test_empty_probabilities() {
+    let err = SampleOptions::new(&[]).err();
+    assert!(matches!(err, Some(InputError::IsEmpty)));
+    assert_eq!(err.unwrap().to_string(), "slice is empty");
+}
+
+#[test]
+fn test_new_snapped() {
+    let mut p = [0.5, 0.5, 1.0, 1.0, 1.0000001, 0.5, 0.5, 0.0];
+    p[0] += 1e-7;
+    let opts = SampleOptions::new_snapped(&mut p, 1e-6).unwrap();
+    let snapped = opts.probabilities();
+    assert_delta!(snapped.iter().sum::<f64>(), 5.0, EPS);
+    assert_eq!(snapped[4], 1.0);
+    assert_eq!(snapped[7], 0.0);
+    assert_eq!(brewer(&mut seeded_rng(), &opts).unwrap().len(), 5);
+
+    let mut p = [1.0, 1.0, 1.0, 1.0, 1.0000001];
+    SampleOptions::new_snapped(&mut p, 1e-6).unwrap();
+    assert_eq!(p, [1.0; 5]);
+
+    // Outside of the tolerance, the probabilities are left as is
+    let mut p = [0.5, 0.5001];
+    SampleOptions::new_snapped(&mut p, 1e-6).unwrap();
+    assert_eq!(p, [0.5, 0.5001]);
+
+    // A sum rounding to zero is not snapped to a zero-probability frame
+    let mut p = [1e-7; 3];
+    SampleOptions::new_snapped(&mut p, 1e-6).unwrap();
+    assert_eq!(p, [1e-7; 3]);
+
+    SampleOptions::new_snapped(&mut [0.5, 0.5], 0.5)
+        .map(drop)
+        .unwrap_err();
+    SampleOptions::new_snapped(&mut [0.5, 1.1], 1e-6)
+        .map(drop)
+        .unwrap_err();
+    SampleOptions::new_snapped(&mut [], 1e-6)
+        .map(drop)
+        .unwrap_err();
+}
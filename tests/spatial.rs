@@ -0,0 +1,182 @@
+use envisim_samplr::spatial::*;
+use envisim_test_utils::*;
+use envisim_utils::kd_tree::{Searcher, TreeBuilder};
+use envisim_utils::utils::usize_to_f64;
+use envisim_utils::Matrix;
+use rand::Rng;
+use std::num::NonZeroU32;
+
+mod test_utils;
+use test_utils::*;
+
+#[test]
+fn test_partition_strata() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let n_units = 200;
+    let coords: Vec<f64> = (0..2 * n_units).map(|_| rng.gen::<f64>()).collect();
+    let m = Matrix::from_vec(coords, n_units);
+    let p: Vec<f64> = (0..n_units)
+        .map(|i| if i % 2 == 0 { 0.05 } else { 0.15 })
+        .collect();
+
+    for n_strata in [1, 7, 20] {
+        let strata = partition_strata(&m, &p, n_strata)?;
+        let mut mass = vec![0.0; n_strata];
+        strata
+            .iter()
+            .zip(p.iter())
+            .for_each(|(&h, &pi)| mass[h] += pi);
+
+        // Each stratum has approximately equal probability mass
+        for &m in mass.iter() {
+            assert_delta!(m, 20.0 / usize_to_f64(n_strata), 0.15);
+        }
+    }
+
+    partition_strata(&m, &p[1..], 5).unwrap_err();
+    partition_strata(&m, &p, 0).unwrap_err();
+    partition_strata(&m, &p, n_units + 1).unwrap_err();
+    partition_strata(&Matrix::new(&[0.0, f64::NAN], 2), &[0.5; 2], 1).unwrap_err();
+    Ok(())
+}
+
+#[test]
+fn test_partition_strata_compact() -> Result<(), SamplingError> {
+    // A 4 x 4 grid in 4 strata gives the four quadrants
+    let coords: Vec<f64> = (0..16)
+        .map(|i| f64::from(i % 4))
+        .chain((0..16).map(|i| f64::from(i / 4)))
+        .collect();
+    let m = Matrix::from_vec(coords, 16);
+    let strata = partition_strata(&m, &[0.25; 16], 4)?;
+
+    for id in 0..16 {
+        let quadrant = strata[(id % 4 / 2) * 2 + (id / 4 / 2) * 8];
+        assert_eq!(strata[id], quadrant);
+    }
+    let mut labels = vec![strata[0], strata[2], strata[8], strata[10]];
+    labels.sort_unstable();
+    assert_eq!(labels, vec![0, 1, 2, 3]);
+
+    Ok(())
+}
+
+#[test]
+fn test_spherical_coordinates() -> Result<(), SamplingError> {
+    let m = spherical_coordinates(&[0.0, 0.0, 90.0, -45.0], &[0.0, 90.0, 30.0, 180.0])?;
+    let h = 0.5f64.sqrt();
+    assert_fvec(&m.data()[0..4], &[1.0, 0.0, 0.0, -h]);
+    assert_fvec(&m.data()[4..8], &[0.0, 1.0, 0.0, 0.0]);
+    assert_fvec(&m.data()[8..12], &[0.0, 0.0, 1.0, -h]);
+
+    assert!(matches!(
+        spherical_coordinates(&[90.5], &[0.0]),
+        Err(SamplingError::Input(_))
+    ));
+    assert!(matches!(
+        spherical_coordinates(&[0.0], &[-180.5]),
+        Err(SamplingError::Input(_))
+    ));
+    assert!(matches!(
+        spherical_coordinates(&[f64::NAN], &[0.0]),
+        Err(SamplingError::Input(_))
+    ));
+    assert!(matches!(
+        spherical_coordinates(&[0.0, 0.0], &[0.0]),
+        Err(SamplingError::Input(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_spherical_neighbours_near_pole() -> Result<(), SamplingError> {
+    let lat = [89.0, 89.0, 80.0, 0.0];
+    let lon = [0.0, 180.0, 0.0, 0.0];
+    let mut units: Vec<usize> = (0..4).collect();
+
+    // On the sphere, the unit across the pole is the closest neighbour
+    let m = spherical_coordinates(&lat, &lon)?;
+    let tree = TreeBuilder::new(&m).build(&mut units)?;
+    let mut searcher = Searcher::new_1(&tree);
+    searcher.find_neighbours_of_id(&tree, 0)?;
+    assert_eq!(searcher.neighbours(), &[1]);
+
+    // Naively using the latitudes and longitudes, the unit across the pole is far away
+    let naive = Matrix::new(&[lat, lon].concat(), 4);
+    let tree = TreeBuilder::new(&naive).build(&mut units)?;
+    let mut searcher = Searcher::new_1(&tree);
+    searcher.find_neighbours_of_id(&tree, 0)?;
+    assert_eq!(searcher.neighbours(), &[2]);
+
+    Ok(())
+}
+
+#[test]
+fn test_lpm_spherical() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let lat: Vec<f64> = (0..10).map(|i| -85.0 + 19.0 * f64::from(i)).collect();
+    let lon: Vec<f64> = (0..10).map(|i| -180.0 + 36.0 * f64::from(i)).collect();
+    let options = SampleOptions::new(&PROB_10_U)?;
+
+    test_wor2(
+        || lpm_spherical(&mut rng, &options, &lat, &lon),
+        &PROB_10_U,
+        1e-2,
+        10000,
+    )?;
+
+    lpm_spherical(&mut rng, &options, &lat[1..], &lon[1..]).unwrap_err();
+
+    Ok(())
+}
+
+#[test]
+fn test_min_distance_sample() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let n_units = 50;
+    let coords: Vec<f64> = (0..2 * n_units).map(|_| rng.gen::<f64>()).collect();
+    let m = Matrix::from_vec(coords, n_units);
+    let p = vec![0.1; n_units];
+    let options = SampleOptions::new(&p)?;
+
+    let pi_reps = NonZeroU32::new(200).unwrap();
+    let mut counts = vec![0u32; n_units];
+    let n_reps = 200;
+
+    for _ in 0..n_reps {
+        let (s, pi) = min_distance_sample(&mut rng, &options, &m, 0.15, pi_reps)?;
+        s.iter().for_each(|&id| counts[id] += 1);
+        assert_eq!(s.len(), 5);
+
+        for (k, &i) in s.iter().enumerate() {
+            for &j in s[(k + 1)..].iter() {
+                let d2 = (m[(i, 0)] - m[(j, 0)]).powi(2) + (m[(i, 1)] - m[(j, 1)]).powi(2);
+                assert!(d2 >= 0.15f64.powi(2));
+            }
+        }
+
+        // The approximate inclusion probabilities sum to the fixed sample size
+        assert_eq!(pi.len(), n_units);
+        assert_delta!(pi.iter().sum::<f64>(), 5.0, 1e-9);
+    }
+
+    // The approximation agrees with the realized inclusion frequencies
+    let (_, pi) = min_distance_sample(&mut rng, &options, &m, 0.15, pi_reps)?;
+    let pi_diff = counts
+        .iter()
+        .zip(pi.iter())
+        .map(|(&c, &p)| (f64::from(c) / f64::from(n_reps) - p).abs())
+        .sum::<f64>();
+    assert!(pi_diff / usize_to_f64(n_units) < 0.05);
+
+    // No sample satisfies the constraint
+    assert!(matches!(
+        min_distance_sample(&mut rng, &options, &m, 2.0, pi_reps),
+        Err(SamplingError::MaxIterations(_))
+    ));
+    min_distance_sample(&mut rng, &options, &m, 0.0, pi_reps).unwrap_err();
+    min_distance_sample(&mut rng, &SampleOptions::new(&p[1..])?, &m, 0.1, pi_reps).unwrap_err();
+
+    Ok(())
+}
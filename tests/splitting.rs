@@ -0,0 +1,108 @@
+use envisim_samplr::splitting::*;
+use envisim_test_utils::*;
+
+mod test_utils;
+use test_utils::*;
+
+#[test]
+fn test_pivotal_splitter() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let p = &PROB_10_U;
+    let opts = SampleOptions::new(p)?;
+
+    test_wor2(
+        || splitting(&mut rng, &opts, pivotal_splitter),
+        p,
+        1e-2,
+        100000,
+    )
+}
+
+#[test]
+fn test_minimum_support_splitter() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let p = &PROB_10_U;
+    let opts = SampleOptions::new(p)?;
+
+    test_wor2(
+        || {
+            let s = splitting(&mut rng, &opts, minimum_support_splitter)?;
+            assert_eq!(s.len(), 5);
+            Ok(s)
+        },
+        p,
+        1e-2,
+        100000,
+    )
+}
+
+#[test]
+fn test_minimum_support() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let p = &PROB_10_U;
+    let opts = SampleOptions::new(p)?;
+
+    test_wor(minimum_support, &mut rng, &opts, p, 1e-2, 100000)?;
+
+    let p = [0.5, 0.4, 0.3];
+    minimum_support(&mut rng, &SampleOptions::new(&p)?).unwrap_err();
+    Ok(())
+}
+
+#[test]
+fn test_all_zero_probabilities() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+
+    for p in [[0.0; 10], [1e-14; 10]] {
+        let opts = SampleOptions::new(&p)?;
+        assert_eq!(minimum_support(&mut rng, &opts)?, vec![]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_invalid_splitter() -> Result<(), SamplingError> {
+    use envisim_utils::InputError;
+
+    let mut rng = seeded_rng();
+    let p = &PROB_10_U;
+    let opts = SampleOptions::new(p)?;
+
+    // A splitter deciding no unit
+    let err = splitting(&mut rng, &opts, |_, _| ProbabilitySplit {
+        lambda: 0.5,
+        changes: vec![],
+    })
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        SamplingError::Input(InputError::SplitUndecided)
+    ));
+
+    // A splitter deciding a unit only in `b`, which is never chosen
+    for _ in 0..100 {
+        let err = splitting(&mut rng, &opts, |probabilities, units| ProbabilitySplit {
+            lambda: 1.0,
+            changes: vec![(units[0], probabilities[units[0]], 0.0)],
+        })
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            SamplingError::Input(InputError::SplitUndecided)
+        ));
+    }
+
+    // A splitter not preserving the probabilities
+    let err = splitting(&mut rng, &opts, |_, units| ProbabilitySplit {
+        lambda: 0.5,
+        changes: vec![(units[0], 1.0, 1.0)],
+    })
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        SamplingError::Input(InputError::SplitNotPreserving(0, _))
+    ));
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use envisim_samplr::srs::*;
 use envisim_test_utils::*;
+use envisim_utils::utils::usize_to_f64;
 
 mod test_utils;
 use test_utils::*;
@@ -22,3 +23,22 @@ fn srs_wr() -> Result<(), SamplingError> {
         100000,
     )
 }
+
+#[test]
+fn kish() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+
+    for roster_size in 1..=8 {
+        let p = vec![1.0 / usize_to_f64(roster_size); roster_size];
+        test_wor2(
+            || kish_grid(&mut rng, roster_size).map(|m| vec![m]),
+            &p,
+            1e-2,
+            100000,
+        )?;
+    }
+
+    kish_grid(&mut rng, 0).unwrap_err();
+
+    Ok(())
+}
@@ -21,3 +21,67 @@ fn srs_wr() -> Result<(), SamplingError> {
 
     test_wor(sample_random_order, &mut rng, &opts, p, 1e-2, 100000)
 }
+
+#[test]
+fn all_zero_probabilities() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+
+    for p in [[0.0; 10], [1e-14; 10]] {
+        let opts = SampleOptions::new(&p)?;
+        assert_eq!(sample(&mut rng, &opts)?, vec![]);
+        assert_eq!(sample_random_order(&mut rng, &opts)?, vec![]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn systematic_second_order() -> Result<(), SamplingError> {
+    // Units 0 and 2, and units 1 and 3, are always selected together
+    let p = [0.5; 4];
+    let pi2 = second_order(&SampleOptions::new(&p)?, &[0, 1, 2, 3])?;
+    let expected = [
+        [0.5, 0.0, 0.5, 0.0],
+        [0.0, 0.5, 0.0, 0.5],
+        [0.5, 0.0, 0.5, 0.0],
+        [0.0, 0.5, 0.0, 0.5],
+    ];
+    for i in 0..4 {
+        for j in 0..4 {
+            assert_delta!(pi2[(i, j)], expected[i][j]);
+        }
+    }
+
+    // Compare with the empirical joint probabilities
+    let mut rng = seeded_rng();
+    let p = &PROB_10_U;
+    let opts = SampleOptions::new(p)?;
+    let units: Vec<usize> = (0..10).collect();
+    let pi2 = second_order(&opts, &units)?;
+    let iter: u32 = 100000;
+    let mut counts = [[0u32; 10]; 10];
+
+    for _ in 0..iter {
+        let s = sample(&mut rng, &opts)?;
+        for &i in s.iter() {
+            for &j in s.iter() {
+                counts[i][j] += 1;
+            }
+        }
+    }
+
+    for i in 0..10 {
+        assert_delta!(pi2[(i, i)], p[i]);
+        for j in 0..10 {
+            assert_delta!(f64::from(counts[i][j]) / f64::from(iter), pi2[(i, j)], 1e-2);
+            // Joint probabilities of zero are never observed
+            if pi2[(i, j)] == 0.0 {
+                assert_eq!(counts[i][j], 0);
+            }
+        }
+    }
+
+    second_order(&opts, &[]).unwrap_err();
+    second_order(&opts, &[10]).unwrap_err();
+    Ok(())
+}
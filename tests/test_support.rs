@@ -0,0 +1,32 @@
+use envisim_samplr::test_support::*;
+use envisim_samplr::unequal::{brewer, sampford};
+use envisim_samplr::{poisson, SampleOptions, SamplingError};
+use envisim_test_utils::*;
+use std::num::NonZeroU32;
+
+#[test]
+fn test_assert_fixed_size() -> Result<(), SamplingError> {
+    let options = SampleOptions::new(&PROB_10_U)?;
+    let reps = NonZeroU32::new(1000).unwrap();
+    assert_fixed_size(brewer, &options, reps, 4242)?;
+    assert_fixed_size(sampford, &options, reps, 4242)?;
+    assert_fixed_size(
+        |rng, opts| poisson::conditional(rng, opts, 5),
+        &options,
+        NonZeroU32::new(10).unwrap(),
+        4242,
+    )?;
+
+    // The probabilities must sum to an integer
+    let p = [0.5, 0.2];
+    assert_fixed_size(brewer, &SampleOptions::new(&p)?, reps, 4242).unwrap_err();
+    Ok(())
+}
+
+#[test]
+#[should_panic(expected = "has sample size")]
+fn test_assert_fixed_size_panics() {
+    let options = SampleOptions::new(&PROB_10_U).unwrap();
+    let reps = NonZeroU32::new(1000).unwrap();
+    assert_fixed_size(poisson::sample, &options, reps, 4242).unwrap();
+}
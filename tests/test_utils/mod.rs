@@ -22,11 +22,14 @@ where
             .for_each(|&id| sel[id] += 1);
     }
 
-    let q: Vec<f64> = sel.iter().map(|&s| (s as f64) / (iter as f64)).collect();
+    let q: Vec<f64> = sel
+        .iter()
+        .map(|&s| f64::from(s) / f64::from(iter))
+        .collect();
     let d: Vec<f64> = probs.iter().zip(q.iter()).map(|(p, r)| p - r).collect();
 
     if !d.iter().all(|&x| x.abs() < eps) {
-        panic!("{d:?} >= {eps}\n(sums: {} vs. {})", sum(&probs), sum(&q));
+        panic!("{d:?} >= {eps}\n(sums: {} vs. {})", sum(probs), sum(&q));
     }
 
     Ok(())
@@ -43,11 +46,14 @@ where
         sampler()?.iter().for_each(|&id| sel[id] += 1);
     }
 
-    let q: Vec<f64> = sel.iter().map(|&s| (s as f64) / (iter as f64)).collect();
+    let q: Vec<f64> = sel
+        .iter()
+        .map(|&s| f64::from(s) / f64::from(iter))
+        .collect();
     let d: Vec<f64> = probs.iter().zip(q.iter()).map(|(p, r)| p - r).collect();
 
     if !d.iter().all(|&x| x.abs() < eps) {
-        panic!("{d:?} >= {eps}\n(sums: {} vs. {})", sum(&probs), sum(&q));
+        panic!("{d:?} >= {eps}\n(sums: {} vs. {})", sum(probs), sum(&q));
     }
 
     Ok(())
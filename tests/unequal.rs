@@ -1,5 +1,7 @@
 use envisim_samplr::unequal::*;
 use envisim_test_utils::*;
+use envisim_utils::utils::usize_to_f64;
+use rand::Rng;
 
 mod test_utils;
 use test_utils::*;
@@ -13,6 +15,64 @@ fn test_sampford() -> Result<(), SamplingError> {
     test_wor2(|| sampford(&mut rng, &opts), p, 1e-2, 10000)
 }
 
+#[test]
+fn test_sampford_progress() -> Result<(), SamplingError> {
+    use envisim_samplr::ProgressEvent;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let mut rng = seeded_rng();
+    let p = &PROB_10_U;
+    let count = AtomicUsize::new(0);
+    let hook = |event: ProgressEvent| {
+        if let ProgressEvent::Iteration { iteration, .. } = event {
+            assert_eq!(iteration, count.fetch_add(1, Ordering::Relaxed));
+        }
+    };
+    let mut opts = SampleOptions::new(p)?;
+    opts.progress(&hook)?;
+
+    for _ in 0..100 {
+        count.store(0, Ordering::Relaxed);
+        sampford(&mut rng, &opts)?;
+        assert!(count.load(Ordering::Relaxed) >= 1);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_sampford_resumable() -> Result<(), SamplingError> {
+    use rand::rngs::SmallRng;
+    use std::num::NonZeroUsize;
+
+    let p = &PROB_10_U;
+    let mut opts = SampleOptions::new(p)?;
+
+    for seed in 0..20 {
+        opts.max_iterations(NonZeroUsize::new(10000).unwrap())?;
+        let Resumable::Sample(full) = sampford_resumable::<SmallRng>(seed, &opts, 0)? else {
+            panic!("no sample within iterations");
+        };
+
+        opts.max_iterations(NonZeroUsize::new(3).unwrap())?;
+        let mut start = 0;
+        let resumed = loop {
+            match sampford_resumable::<SmallRng>(seed, &opts, start)? {
+                Resumable::Sample(s) => break s,
+                Resumable::Pending(iterations) => {
+                    assert_eq!(iterations, start + 3);
+                    start = iterations;
+                }
+                _ => unreachable!(),
+            }
+        };
+
+        assert_eq!(full, resumed);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_pareto() -> Result<(), SamplingError> {
     let mut rng = seeded_rng();
@@ -22,6 +82,20 @@ fn test_pareto() -> Result<(), SamplingError> {
     test_wor(pareto, &mut rng, &opts, p, 1e-2, 100000)
 }
 
+#[test]
+fn test_pareto_ties() -> Result<(), SamplingError> {
+    // A constant rng gives equal q values for all units
+    let mut rng = rand::rngs::mock::StepRng::new(1 << 63, 0);
+    let p = [0.5; 6];
+    let opts = SampleOptions::new(&p)?;
+
+    for _ in 0..5 {
+        assert_eq!(pareto(&mut rng, &opts)?, vec![0, 1, 2]);
+    }
+
+    Ok(())
+}
+
 #[test]
 fn test_brewer() -> Result<(), SamplingError> {
     let mut rng = seeded_rng();
@@ -30,3 +104,570 @@ fn test_brewer() -> Result<(), SamplingError> {
 
     test_wor(brewer, &mut rng, &opts, p, 1e-2, 100000)
 }
+
+#[test]
+fn test_brewer_unequal() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let p = &PROB_10_U;
+    let opts = SampleOptions::new(p)?;
+
+    test_wor(brewer, &mut rng, &opts, p, 1e-2, 100000)
+}
+
+#[test]
+fn test_weighted_wor() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let w: Vec<f64> = (1..=10).map(f64::from).collect();
+    let p: Vec<f64> = w.iter().map(|&x| x / 55.0).collect();
+
+    // With n = 1, the first inclusion probabilities are proportional to the weights
+    test_wor2(
+        || weighted_wor(&mut rng, w.iter().copied(), 1),
+        &p,
+        1e-2,
+        100000,
+    )?;
+
+    let s = weighted_wor(&mut rng, [0.0, 1.0, 0.0, 2.0, 3.0], 3)?;
+    assert_eq!(s, vec![1, 3, 4]);
+    weighted_wor(&mut rng, [0.0, 1.0, 0.0, 2.0], 3).unwrap_err();
+    weighted_wor(&mut rng, [-1.0, 1.0, 2.0], 1).unwrap_err();
+    weighted_wor(&mut rng, [f64::INFINITY, 1.0, 2.0], 1).unwrap_err();
+    weighted_wor(&mut rng, [f64::NAN, 1.0, 2.0], 1).unwrap_err();
+
+    Ok(())
+}
+
+#[test]
+fn test_unsorted_output() -> Result<(), SamplingError> {
+    let p = &PROB_10_U;
+    let sorted_opts = SampleOptions::new(p)?;
+    let mut unsorted_opts = SampleOptions::new(p)?;
+    unsorted_opts.sorted_output(false)?;
+
+    for sampler in [sampford, brewer] {
+        let mut rng_a = seeded_rng();
+        let mut rng_b = seeded_rng();
+        let mut any_unsorted = false;
+
+        for _ in 0..100 {
+            let a = sampler(&mut rng_a, &sorted_opts)?;
+            let mut b = sampler(&mut rng_b, &unsorted_opts)?;
+            assert!(a.is_sorted());
+            any_unsorted |= !b.is_sorted();
+            b.sort_unstable();
+            assert_eq!(a, b);
+        }
+
+        assert!(any_unsorted);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_zero_probabilities() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let p = [0.0, 0.5, 1e-14, 0.5, 0.0, 0.6, 0.0, 0.4, 1e-13, 1.0];
+    let opts = SampleOptions::new(&p)?;
+
+    for sampler in [sampford, pareto, brewer] {
+        for _ in 0..10000 {
+            let s = sampler(&mut rng, &opts)?;
+            assert_eq!(s.len(), 3);
+            assert!(s.iter().all(|&id| p[id] > 1e-12), "{s:?}");
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_with_replacement_sum_below_one() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    // Probabilities summing to slightly less than 1.0, within eps, still yield n units
+    let p = [0.5, 0.5 - 1e-3];
+    let mut opts = SampleOptions::new(&p)?;
+    opts.eps(1e-2)?;
+    for _ in 0..100 {
+        assert_eq!(with_replacement(&mut rng, &opts, 1000)?.len(), 1000);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_with_replacement_large_n() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let p: Vec<f64> = PROB_10_U.iter().map(|&x| x / 5.0).collect();
+    let opts = SampleOptions::new(&p)?;
+    let n = 10 * p.len();
+    let iterations: u32 = 10000;
+    let mut counts = vec![0u32; p.len()];
+
+    for _ in 0..iterations {
+        let s = with_replacement(&mut rng, &opts, n)?;
+        assert_eq!(s.len(), n);
+        assert!(s.is_sorted());
+        s.iter().for_each(|&id| counts[id] += 1);
+    }
+
+    for (&c, &pi) in counts.iter().zip(p.iter()) {
+        assert_delta!(f64::from(c) / f64::from(iterations), pi * 100.0, 0.15);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_with_replacement_antithetic() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let p: Vec<f64> = PROB_10_U.iter().map(|&x| x / 5.0).collect();
+    let opts = SampleOptions::new(&p)?;
+    let iterations: u32 = 10000;
+
+    // Each draw selects units according to the draw probabilities
+    for n in [4, 5] {
+        let mut counts = vec![0u32; p.len()];
+
+        for _ in 0..iterations {
+            let s = with_replacement_antithetic(&mut rng, &opts, n)?;
+            assert_eq!(s.len(), n);
+            assert!(s.is_sorted());
+            s.iter().for_each(|&id| counts[id] += 1);
+        }
+
+        for (&c, &pi) in counts.iter().zip(p.iter()) {
+            assert_delta!(
+                f64::from(c) / f64::from(iterations),
+                pi * usize_to_f64(n),
+                0.05
+            );
+        }
+    }
+
+    // The variance of the Hansen-Hurwitz estimator of a total is reduced, as y / p is increasing
+    let y: Vec<f64> = p
+        .iter()
+        .zip(1u32..)
+        .map(|(&pi, i)| pi * f64::from(i))
+        .collect();
+    let variance = |antithetic: bool, rng: &mut _| -> Result<f64, SamplingError> {
+        let estimates = (0..iterations)
+            .map(|_| {
+                let s = if antithetic {
+                    with_replacement_antithetic(rng, &opts, 4)?
+                } else {
+                    with_replacement(rng, &opts, 4)?
+                };
+                Ok(s.iter().map(|&i| y[i] / p[i]).sum::<f64>() / 4.0)
+            })
+            .collect::<Result<Vec<f64>, SamplingError>>()?;
+        let mean = estimates.iter().sum::<f64>() / f64::from(iterations);
+        Ok(estimates.iter().map(|&e| (e - mean).powi(2)).sum::<f64>() / f64::from(iterations))
+    };
+    assert!(variance(true, &mut rng)? < 0.5 * variance(false, &mut rng)?);
+
+    with_replacement_antithetic(&mut rng, &SampleOptions::new(&PROB_10_U)?, 4).unwrap_err();
+    assert!(with_replacement_antithetic(&mut rng, &opts, 0)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_with_replacement_weights() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let w = [2.0, 3.0, 5.0];
+    let n = 10;
+    let iterations: u32 = 10000;
+    let mut counts = vec![0u32; w.len()];
+
+    for _ in 0..iterations {
+        let s = with_replacement_weights(&mut rng, &w, n)?;
+        assert_eq!(s.len(), n);
+        s.iter().for_each(|&id| counts[id] += 1);
+    }
+
+    for (&c, &p) in counts.iter().zip([0.2, 0.3, 0.5].iter()) {
+        assert_delta!(f64::from(c) / f64::from(iterations * 10), p, 1e-2);
+    }
+
+    with_replacement_weights(&mut rng, &[0.0, 0.0], 1).unwrap_err();
+    with_replacement_weights(&mut rng, &[1.0, -1.0, 2.0], 1).unwrap_err();
+    with_replacement_weights(&mut rng, &[1.0, f64::NAN], 1).unwrap_err();
+    with_replacement_weights(&mut rng, &[], 1).unwrap_err();
+    Ok(())
+}
+
+#[test]
+fn test_certainty_units() -> Result<(), SamplingError> {
+    let p = [0.5, 0.999, 0.25, 0.25, 1.0];
+    assert_eq!(certainty_units(&SampleOptions::new(&p)?)?, vec![4]);
+    assert_eq!(
+        certainty_units(SampleOptions::new(&p)?.eps(0.01)?)?,
+        vec![1, 4]
+    );
+
+    let mut rng = seeded_rng();
+    let p = [0.5, 0.25, 0.25, 1.0];
+    let opts = SampleOptions::new(&p)?;
+    for _ in 0..10 {
+        assert!(brewer(&mut rng, &opts)?.contains(&3));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_with_replacement_uniforms() -> Result<(), SamplingError> {
+    // The bulk generated uniforms equal successive calls to rng.gen()
+    let mut rng = seeded_rng();
+    let mut reference_rng = rng.clone();
+    let p = [0.1, 0.05, 0.3, 0.15, 0.2, 0.2];
+    let options = SampleOptions::new(&p)?;
+    let n = 1000;
+
+    let mut rvs: Vec<f64> = (0..n).map(|_| reference_rng.gen::<f64>()).collect();
+    rvs.sort_unstable_by(f64::total_cmp);
+    let expected: Vec<usize> = rvs
+        .iter()
+        .map(|&rv| {
+            let mut psum = 0.0;
+            p.iter()
+                .position(|&pi| {
+                    psum += pi;
+                    rv < psum
+                })
+                .unwrap()
+        })
+        .collect();
+
+    assert_eq!(with_replacement(&mut rng, &options, n)?, expected);
+    assert_eq!(rng.gen::<u64>(), reference_rng.gen::<u64>());
+    Ok(())
+}
+
+#[test]
+fn test_pareto_from_iter() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let mut reference_rng = seeded_rng();
+    // Lazily generated probabilities, summing to 10, with some zeros
+    let source = (0..1000).map(|i| match i % 4 {
+        0 => 0.0,
+        1 => 0.01,
+        _ => 0.015,
+    });
+    let p: Vec<f64> = source.clone().collect();
+    let options = SampleOptions::new(&p)?;
+
+    for _ in 0..10 {
+        let s = pareto_from_iter(&mut rng, source.clone(), 1e-12)?;
+        assert_eq!(s.len(), 10);
+        assert_eq!(s, pareto(&mut reference_rng, &options)?);
+    }
+
+    pareto_from_iter(&mut rng, [0.5, 0.4], 1e-12).unwrap_err();
+    pareto_from_iter(&mut rng, [], 1e-12).unwrap_err();
+    Ok(())
+}
+
+#[test]
+fn test_all_zero_probabilities() -> Result<(), SamplingError> {
+    use rand::rngs::SmallRng;
+
+    let mut rng = seeded_rng();
+
+    for p in [[0.0; 10], [1e-14; 10]] {
+        let opts = SampleOptions::new(&p)?;
+
+        for sampler in [sampford, pareto, brewer] {
+            assert_eq!(sampler(&mut rng, &opts)?, vec![]);
+        }
+
+        assert_eq!(pareto_from_iter(&mut rng, p, 1e-12)?, vec![]);
+        let Resumable::Sample(s) = sampford_resumable::<SmallRng>(0, &opts, 0)? else {
+            panic!("no sample within iterations");
+        };
+        assert_eq!(s, vec![]);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_allow_empty_sample() -> Result<(), SamplingError> {
+    use rand::rngs::SmallRng;
+
+    let mut rng = seeded_rng();
+    let p = [0.0; 10];
+    let mut opts = SampleOptions::new(&p)?;
+
+    opts.allow_empty_sample(true)?;
+    for sampler in [sampford, pareto, brewer] {
+        assert_eq!(sampler(&mut rng, &opts)?, vec![]);
+    }
+
+    opts.allow_empty_sample(false)?;
+    for sampler in [sampford, pareto, brewer] {
+        assert!(matches!(
+            sampler(&mut rng, &opts),
+            Err(SamplingError::EmptySample)
+        ));
+    }
+    assert!(matches!(
+        sampford_resumable::<SmallRng>(0, &opts, 0),
+        Err(SamplingError::EmptySample)
+    ));
+
+    // Non-empty samples are unaffected
+    let mut opts = SampleOptions::new(&PROB_10_U)?;
+    opts.allow_empty_sample(false)?;
+    for sampler in [sampford, pareto, brewer] {
+        assert_eq!(sampler(&mut rng, &opts)?.len(), 5);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_brewer_traced() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let mut reference_rng = seeded_rng();
+    let p = [0.2, 0.4, 0.6, 0.8];
+    let opts = SampleOptions::new(&p)?;
+
+    // The first unit is drawn with probability proportional to p (2 - p) / (2 - 2p), and the
+    // second unit with probability proportional to p
+    let q: Vec<f64> = p.iter().map(|&x| x * (2.0 - x) / (2.0 - 2.0 * x)).collect();
+    let qsum: f64 = q.iter().sum();
+
+    for _ in 0..100 {
+        let (s, trace) = brewer_traced(&mut rng, &opts)?;
+        assert_eq!(s, brewer(&mut reference_rng, &opts)?);
+        assert_eq!(trace.len(), 2);
+
+        let first = trace[0].unit;
+        let second = trace[1].unit;
+        assert_eq!((trace[0].step, trace[1].step), (0, 1));
+        assert_delta!(trace[0].probability, q[first] / qsum, EPS);
+        assert_delta!(trace[1].probability, p[second] / (2.0 - p[first]), EPS);
+        assert!(s.contains(&first) && s.contains(&second));
+    }
+
+    // Certainty units are selected first
+    let p = [0.5, 0.0, 1.0, 0.5];
+    let (s, trace) = brewer_traced(&mut rng, &SampleOptions::new(&p)?)?;
+    assert_eq!(s.len(), 2);
+    assert_eq!(
+        trace[0],
+        SelectionStep {
+            step: 0,
+            unit: 2,
+            probability: 1.0
+        }
+    );
+    assert_delta!(trace[1].probability, 0.5, EPS);
+
+    Ok(())
+}
+
+#[test]
+fn test_sampford_second_order() -> Result<(), SamplingError> {
+    // Equal probabilities give simple random sampling
+    let p = [0.3; 10];
+    let units: Vec<usize> = (0..10).collect();
+    let m = sampford_second_order(&SampleOptions::new(&p)?, &units)?;
+    assert_delta!(m[(0, 1)], 3.0 * 2.0 / 90.0, EPS);
+    assert_delta!(m[(9, 4)], 3.0 * 2.0 / 90.0, EPS);
+
+    // Compared to the relative frequencies of sampford samples, including a certainty unit and a
+    // zero probability unit
+    let mut rng = seeded_rng();
+    let p = [0.2, 0.25, 0.35, 0.4, 0.0, 0.5, 0.6, 1.0, 0.7, 1.0];
+    let opts = SampleOptions::new(&p)?;
+    let m = sampford_second_order(&opts, &units)?;
+    let iterations: u32 = 100000;
+    let mut counts = vec![0u32; 100];
+
+    for _ in 0..iterations {
+        let s = sampford(&mut rng, &opts)?;
+        for &i in s.iter() {
+            for &j in s.iter() {
+                counts[i * 10 + j] += 1;
+            }
+        }
+    }
+
+    for i in 0..10 {
+        // The second order probabilities of a fixed size design sum to (n - 1) p_i
+        let row_sum: f64 = (0..10).filter(|&j| j != i).map(|j| m[(i, j)]).sum();
+        assert_delta!(row_sum, 4.0 * p[i], 1e-9);
+
+        for j in 0..10 {
+            assert_delta!(
+                m[(i, j)],
+                f64::from(counts[i * 10 + j]) / f64::from(iterations),
+                5e-3
+            );
+        }
+    }
+
+    // The units may be in any order
+    let m2 = sampford_second_order(&opts, &[3, 0, 3])?;
+    assert_delta!(m2[(0, 1)], m[(3, 0)], EPS);
+    assert_delta!(m2[(0, 2)], p[3], EPS);
+
+    sampford_second_order(&opts, &[]).unwrap_err();
+    sampford_second_order(&opts, &[10]).unwrap_err();
+    Ok(())
+}
+
+#[test]
+fn test_lahiri() -> Result<(), SamplingError> {
+    use std::num::NonZeroUsize;
+
+    let mut rng = seeded_rng();
+    let sizes = [1.0, 2.0, 3.0, 4.0];
+    let max_iterations = NonZeroUsize::new(10000).unwrap();
+    let p: Vec<f64> = sizes.iter().map(|&s| s / 10.0).collect();
+
+    // With n = 1, the probabilities are proportional to size
+    test_wor2(
+        || lahiri(&mut rng, &sizes, 1, max_iterations),
+        &p,
+        1e-2,
+        100000,
+    )?;
+
+    // With n = 2, the design is successive sampling
+    let pi2: Vec<f64> = (0..4)
+        .map(|i| {
+            p[i] + (0..4)
+                .filter(|&j| j != i)
+                .map(|j| p[j] * p[i] / (1.0 - p[j]))
+                .sum::<f64>()
+        })
+        .collect();
+    test_wor2(
+        || lahiri(&mut rng, &sizes, 2, max_iterations),
+        &pi2,
+        1e-2,
+        100000,
+    )?;
+
+    assert_eq!(
+        lahiri(&mut rng, &sizes, 4, max_iterations)?,
+        vec![0, 1, 2, 3]
+    );
+    assert!(matches!(
+        lahiri(&mut rng, &sizes, 4, NonZeroUsize::new(3).unwrap()),
+        Err(SamplingError::MaxIterations(_))
+    ));
+    lahiri(&mut rng, &sizes, 5, max_iterations).unwrap_err();
+    lahiri(&mut rng, &[1.0, 0.0], 1, max_iterations).unwrap_err();
+    lahiri(&mut rng, &[1.0, -1.0], 1, max_iterations).unwrap_err();
+    lahiri(&mut rng, &[1.0, f64::INFINITY], 1, max_iterations).unwrap_err();
+    Ok(())
+}
+
+#[test]
+fn test_pareto_calibration() -> Result<(), SamplingError> {
+    let p = [0.1, 0.2, 0.3, 0.5, 0.6, 0.9, 0.4, 0.0, 1.0];
+    let n = 4;
+    let mut opts = SampleOptions::new(&p)?;
+
+    // The exact inclusion probabilities of the plain design differ from the targets
+    let plain = pareto_inclusion_probabilities(&opts, n)?;
+    assert_delta!(plain.iter().sum::<f64>(), 4.0, 1e-9);
+    assert!(plain
+        .iter()
+        .zip(p.iter())
+        .any(|(a, b)| (a - b).abs() > 5e-3));
+    assert_eq!((plain[7], plain[8]), (0.0, 1.0));
+
+    opts.pareto_calibration(true)?;
+    let parameters = pareto_parameters(&opts)?;
+    let calibrated = pareto_inclusion_probabilities(&SampleOptions::new(&parameters)?, n)?;
+    for (&a, &b) in calibrated.iter().zip(p.iter()) {
+        assert_delta!(a, b, 1e-9);
+    }
+
+    // The calibrated design is the plain design using the calibrated parameters
+    let parameter_opts = SampleOptions::new(&parameters)?;
+    assert_eq!(
+        pareto(&mut seeded_rng(), &opts)?,
+        pareto(&mut seeded_rng(), &parameter_opts)?
+    );
+
+    // The realized probabilities match the exact probabilities, with and without calibration
+    let mut rng = seeded_rng();
+    test_wor(pareto, &mut rng, &parameter_opts, &p, 1e-2, 100000)?;
+    opts.pareto_calibration(false)?;
+    test_wor(pareto, &mut rng, &opts, &plain, 1e-2, 100000)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_pareto_dyn_rng() -> Result<(), SamplingError> {
+    use rand::rngs::{SmallRng, StdRng};
+    use rand::{RngCore, SeedableRng};
+
+    let p = &PROB_10_U;
+    let opts = SampleOptions::new(p)?;
+
+    // The rng is chosen at runtime
+    for use_std in [false, true] {
+        let mut rng: Box<dyn RngCore> = if use_std {
+            Box::new(StdRng::seed_from_u64(4242))
+        } else {
+            Box::new(SmallRng::seed_from_u64(4242))
+        };
+
+        assert_eq!(pareto(rng.as_mut(), &opts)?.len(), 5);
+        test_wor(pareto, rng.as_mut(), &opts, p, 1e-2, 100000)?;
+    }
+
+    // A dyn rng yields the same sample as the concrete rng
+    let mut rng = seeded_rng();
+    let dyn_rng: &mut dyn RngCore = &mut seeded_rng();
+    assert_eq!(pareto(dyn_rng, &opts)?, pareto(&mut rng, &opts)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_distinct_with_replacement() -> Result<(), SamplingError> {
+    let mut rng = seeded_rng();
+    let p = [0.05, 0.1, 0.15, 0.2, 0.0, 0.25, 0.25];
+    let opts = SampleOptions::new(&p)?;
+
+    for n in 0..=6 {
+        let s = distinct_with_replacement(&mut rng, &opts, n)?;
+        assert_eq!(s.len(), n);
+        assert!(s.windows(2).all(|w| w[0] < w[1]));
+        assert!(!s.contains(&4));
+    }
+
+    distinct_with_replacement(&mut rng, &opts, 7).unwrap_err();
+
+    let mut opts = SampleOptions::new(&p)?;
+    opts.max_iterations(std::num::NonZeroUsize::new(2).unwrap())?;
+    distinct_with_replacement(&mut rng, &opts, 6).unwrap_err();
+    Ok(())
+}
+
+#[test]
+fn test_empty_input() {
+    use envisim_utils::InputError;
+
+    let mut rng = seeded_rng();
+    let empty: [f64; 0] = [];
+
+    for err in [
+        with_replacement_weights(&mut rng, &empty, 1).unwrap_err(),
+        weighted_wor(&mut rng, empty, 0).unwrap_err(),
+        pareto_from_iter(&mut rng, empty, 1e-12).unwrap_err(),
+    ] {
+        assert!(matches!(err, SamplingError::Input(InputError::IsEmpty)));
+    }
+}
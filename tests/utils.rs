@@ -0,0 +1,65 @@
+use envisim_samplr::srs;
+use envisim_samplr::{
+    mask_to_sample, merge_samples, sample_jaccard, sample_overlap, sample_to_mask,
+};
+use envisim_test_utils::*;
+
+#[test]
+fn test_merge_samples() {
+    // Strata of sizes 5, 3 and 4
+    let samples = [vec![4, 0], vec![], vec![0, 3]];
+    let offsets = [0, 5, 8];
+
+    assert_eq!(
+        merge_samples(&samples, &offsets).unwrap(),
+        vec![0, 4, 8, 11]
+    );
+    assert!(merge_samples(&[], &[]).unwrap().is_empty());
+    merge_samples(&samples, &offsets[0..2]).unwrap_err();
+
+    let mut rng = seeded_rng();
+    let s1 = srs::sample(&mut rng, 2, 10).unwrap();
+    let s2 = srs::sample(&mut rng, 3, 20).unwrap();
+    let merged = merge_samples(&[s1, s2], &[0, 10]).unwrap();
+    assert_eq!(merged.len(), 5);
+    assert_eq!(merged.iter().filter(|&&id| id < 10).count(), 2);
+    assert!(merged.iter().all(|&id| id < 30));
+}
+
+#[test]
+fn test_sample_overlap() {
+    assert_eq!(sample_overlap(&[0, 2, 5, 7], &[1, 2, 7, 8, 9]), 2);
+    assert_eq!(sample_overlap(&[0, 2, 5], &[0, 2, 5]), 3);
+    assert_eq!(sample_overlap(&[0, 2], &[1, 3]), 0);
+    assert_eq!(sample_overlap(&[], &[1, 3]), 0);
+
+    assert_delta!(sample_jaccard(&[0, 2, 5, 7], &[1, 2, 7, 8]), 2.0 / 6.0, EPS);
+    assert_delta!(sample_jaccard(&[0, 2, 5], &[0, 2, 5]), 1.0, EPS);
+    assert_delta!(sample_jaccard(&[0, 2], &[1, 3]), 0.0, EPS);
+    assert_delta!(sample_jaccard(&[], &[]), 1.0, EPS);
+
+    // Equals the overlap by set intersection
+    let mut rng = seeded_rng();
+    for _ in 0..100 {
+        let a = srs::sample(&mut rng, 10, 30).unwrap();
+        let b = srs::sample(&mut rng, 15, 30).unwrap();
+        let common = a.iter().filter(|id| b.contains(id)).count();
+        assert_eq!(sample_overlap(&a, &b), common);
+    }
+}
+
+#[test]
+fn test_sample_to_mask() {
+    let mask = sample_to_mask(&[1, 3, 3, 4], 6).unwrap();
+    assert_eq!(mask, vec![false, true, false, true, true, false]);
+    assert_eq!(mask_to_sample(&mask), vec![1, 3, 4]);
+    assert!(sample_to_mask(&[], 0).unwrap().is_empty());
+    assert!(mask_to_sample(&[false; 4]).is_empty());
+
+    let mut rng = seeded_rng();
+    let s = srs::sample(&mut rng, 5, 20).unwrap();
+    assert_eq!(mask_to_sample(&sample_to_mask(&s, 20).unwrap()), s);
+
+    sample_to_mask(&[1, 6], 6).unwrap_err();
+    sample_to_mask(&[0], 0).unwrap_err();
+}